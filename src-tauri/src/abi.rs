@@ -0,0 +1,280 @@
+use crate::vm::{Register, Syntax};
+use crate::x86_asm::{self, AssembleResult};
+use crate::x86_runtime::Session;
+use serde::Serialize;
+
+/// Registers a System V function must restore before it returns.
+const CALLEE_SAVED: [Register; 6] = [
+    Register::RBX,
+    Register::RBP,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+];
+
+/// Spellings of RAX and its low parts.
+const RAX_NAMES: [&str; 5] = ["rax", "eax", "ax", "al", "ah"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AbiRule {
+    /// RBX, RBP and R12-R15 hold the same values after a call as before it.
+    CalleeSaved,
+    /// RSP at every `call` is a multiple of 16 bytes below its value when the program began.
+    StackAlignment,
+    /// A function whose caller uses RAX has put its result there.
+    ReturnValue,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AbiViolation {
+    pub rule: AbiRule,
+    /// The function at fault: the caller for alignment, the callee otherwise.
+    pub function: String,
+    /// Source line of the offending `call` or `ret` (none in a loaded executable).
+    pub line: Option<usize>,
+    pub address: u64,
+    pub message: String,
+}
+
+/// Registers as they were when a `call` was made.
+struct CallSnapshot {
+    saved: [u64; 6],
+    rax: u64,
+}
+
+/// Run `session` for up to `max_instructions` instructions and report every place it breaks
+/// the System V calling convention. Start it before its first instruction: stack alignment is
+/// measured against the RSP the program started with.
+pub fn check(session: &mut Session, max_instructions: usize) -> Vec<AbiViolation> {
+    let start_rsp = session.register(Register::RSP);
+    let mut snapshots: Vec<CallSnapshot> = Vec::new();
+    let mut violations = Vec::new();
+
+    for _ in 0..max_instructions {
+        if session.is_finished() || session.is_past_end() {
+            break;
+        }
+        let pc = session.rip();
+        let line = session.instruction_at(pc).map(|i| i.line);
+        let rsp = session.register(Register::RSP);
+        let before = session.call_stack();
+        let saved = CALLEE_SAVED.map(|r| session.register(r));
+        let rax = session.register(Register::RAX);
+
+        session.step();
+        let after = session.call_stack();
+
+        if after.len() > before.len() {
+            let depth = start_rsp.wrapping_sub(rsp);
+            if !depth.is_multiple_of(16) {
+                violations.push(AbiViolation {
+                    rule: AbiRule::StackAlignment,
+                    function: function_at(session.assembled(), pc),
+                    line,
+                    address: pc,
+                    message: format!(
+                        "RSP is {} bytes below its start at `call`, not a multiple of 16",
+                        depth as i64
+                    ),
+                });
+            }
+            snapshots.push(CallSnapshot { saved, rax });
+            continue;
+        }
+
+        // A `ret` that skips frames returns from all of them at once; judge the innermost.
+        let Some(frame) = before.get(after.len()) else {
+            continue;
+        };
+        snapshots.truncate(after.len() + 1);
+        let Some(snapshot) = snapshots.pop() else {
+            continue;
+        };
+        for (i, reg) in CALLEE_SAVED.iter().enumerate() {
+            let now = session.register(*reg);
+            if now != snapshot.saved[i] {
+                violations.push(AbiViolation {
+                    rule: AbiRule::CalleeSaved,
+                    function: frame.function.clone(),
+                    line,
+                    address: pc,
+                    message: format!(
+                        "{:?} is 0x{:x} after the call but was 0x{:x} before it; push and pop it",
+                        reg, now, snapshot.saved[i]
+                    ),
+                });
+            }
+        }
+        let resume = session.rip();
+        let uses_rax = session
+            .instruction_at(resume)
+            .is_some_and(|i| reads_rax(&i.text));
+        if uses_rax && session.register(Register::RAX) == snapshot.rax {
+            let at = session
+                .instruction_at(resume)
+                .map_or(format!("0x{:x}", resume), |i| format!("line {}", i.line));
+            violations.push(AbiViolation {
+                rule: AbiRule::ReturnValue,
+                function: frame.function.clone(),
+                line,
+                address: pc,
+                message: format!(
+                    "returns without setting RAX, but the caller reads it at {}",
+                    at
+                ),
+            });
+        }
+    }
+    violations
+}
+
+/// Assemble and check a program from the start.
+pub fn check_program(
+    code: &str,
+    syntax: Syntax,
+    input: Vec<i64>,
+    max_instructions: usize,
+) -> Result<Vec<AbiViolation>, String> {
+    let mut session = Session::new(code, syntax, input, false)?;
+    Ok(check(&mut session, max_instructions))
+}
+
+/// The nearest label at or before `addr` that is not a local `.label`.
+fn function_at(assembled: &AssembleResult, addr: u64) -> String {
+    assembled
+        .labels
+        .iter()
+        .filter(|(name, &at)| !name.starts_with('.') && at <= addr)
+        .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+        .map_or(format!("0x{:x}", addr), |(name, _)| name.clone())
+}
+
+fn names_rax(operand: &str) -> bool {
+    operand
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| RAX_NAMES.contains(&word.to_ascii_lowercase().as_str()))
+}
+
+fn is_memory(operand: &str) -> bool {
+    operand.contains('[') || operand.contains('(')
+}
+
+/// Whether an instruction (as handed to Keystone, Intel or AT&T) uses the value in RAX.
+fn reads_rax(text: &str) -> bool {
+    let text = text.trim();
+    let (mnemonic, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let mnemonic = mnemonic.to_ascii_lowercase();
+    let mut operands = x86_asm::split_operands(rest);
+    let att = text.contains('%');
+
+    let implicit = [
+        "mul", "div", "idiv", "cqo", "cdq", "cwd", "cdqe", "cwde", "cbw", "cqto", "cltd", "cltq",
+        "cwtl", "cbtw", "syscall", "stosb", "stosw", "stosd", "stosq", "scasb", "scasw", "scasd",
+        "scasq", "out",
+    ];
+    // AT&T may add a size suffix: `mulq`, `xorl`, `popq`.
+    let stem = match mnemonic.strip_suffix(['b', 'w', 'l', 'q']) {
+        Some(stem) if att => stem,
+        _ => mnemonic.as_str(),
+    };
+    let is = |names: &[&str]| names.contains(&mnemonic.as_str()) || names.contains(&stem);
+    if is(&implicit) || (operands.len() == 1 && is(&["imul"])) {
+        return true;
+    }
+    if operands.is_empty() {
+        return false;
+    }
+
+    let dest = if att {
+        operands.pop().unwrap_or_default()
+    } else {
+        operands.remove(0)
+    };
+    let zeroing = is(&["xor", "sub", "pxor", "xorps", "xorpd"])
+        && operands.len() == 1
+        && operands[0].eq_ignore_ascii_case(dest);
+    if zeroing {
+        return false;
+    }
+    let write_only =
+        mnemonic.starts_with("mov") || mnemonic.starts_with("set") || is(&["lea", "pop", "in"]);
+    let dest_read = if is_memory(dest) || !write_only {
+        names_rax(dest)
+    } else {
+        false
+    };
+    dest_read || operands.iter().any(|op| names_rax(op))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_reads_of_rax() {
+        for text in [
+            "add rbx, rax",
+            "mov rdi, rax",
+            "cmp eax, 0",
+            "push rax",
+            "mov qword ptr [rax], 1",
+            "cqo",
+            "idiv rcx",
+            "movq %rax, %rdi",
+            "testl %eax, %eax",
+            "movb $1, (%rax)",
+            "imul rcx",
+        ] {
+            assert!(reads_rax(text), "{}", text);
+        }
+        for text in [
+            "mov rax, 60",
+            "movzx eax, byte ptr [rbx]",
+            "lea rax, [rbx + 8]",
+            "xor eax, eax",
+            "pop rax",
+            "movl $0, %eax",
+            "xorq %rax, %rax",
+            "sete al",
+            "imul rcx, rdx",
+            "ret",
+        ] {
+            assert!(!reads_rax(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn reports_each_broken_rule() {
+        let code = "section .text
+    global _start
+_start:
+    sub rsp, 8
+    call clobbers
+    add rsp, 8
+    call aligned
+    mov rdi, rax
+    mov rax, 60
+    syscall
+clobbers:
+    mov rbx, 1
+    mov rax, 2
+    ret
+aligned:
+    ret
+";
+        let violations = check_program(code, Syntax::Intel, vec![], 1_000).unwrap();
+        let found: Vec<(AbiRule, &str, Option<usize>)> = violations
+            .iter()
+            .map(|v| (v.rule, v.function.as_str(), v.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (AbiRule::StackAlignment, "_start", Some(5)),
+                (AbiRule::CalleeSaved, "clobbers", Some(14)),
+                (AbiRule::ReturnValue, "aligned", Some(16)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use crate::memory::{self, sign_extend};
+use crate::vm::Register;
+use crate::x86_asm;
+use crate::x86_runtime::Session;
+
+/// RFLAGS bits a condition may name.
+const FLAGS: [(&str, u32); 6] = [
+    ("cf", 0),
+    ("pf", 2),
+    ("zf", 6),
+    ("sf", 7),
+    ("df", 10),
+    ("of", 11),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Num(i64),
+    /// A register or part of one: `shift` and `bits` select it from the full register.
+    Reg {
+        reg: Register,
+        shift: u32,
+        bits: u32,
+    },
+    Flag(u32),
+    /// `size` bytes at an address.
+    Mem(Box<Expr>, usize),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+/// A breakpoint condition over registers, flags and memory, e.g. `rcx == 3 && zf` or
+/// `byte [buf+1] != 0`. Values are signed 64-bit; parts of registers and memory are sign-extended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub text: String,
+    expr: Expr,
+}
+
+impl Condition {
+    /// Parse `text`, resolving labels against `session`.
+    pub fn parse(text: &str, session: &Session) -> Result<Condition, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            at: 0,
+            session,
+        };
+        let expr = parser.or()?;
+        if let Some(extra) = tokens.get(parser.at) {
+            return Err(format!("Unexpected `{}` in condition", extra));
+        }
+        Ok(Condition {
+            text: text.trim().to_string(),
+            expr,
+        })
+    }
+
+    pub fn holds(&self, session: &Session) -> Result<bool, String> {
+        Ok(eval(&self.expr, session)? != 0)
+    }
+}
+
+fn eval(expr: &Expr, session: &Session) -> Result<i64, String> {
+    Ok(match expr {
+        Expr::Num(n) => *n,
+        Expr::Reg { reg, shift, bits } => sign_extend(session.register(*reg) >> shift, *bits),
+        Expr::Flag(bit) => ((session.rflags() >> bit) & 1) as i64,
+        Expr::Mem(addr, size) => {
+            let bytes = session.read_memory(eval(addr, session)? as u64, *size)?;
+            sign_extend(memory::le_u64(&bytes), 8 * *size as u32)
+        }
+        Expr::Not(e) => (eval(e, session)? == 0) as i64,
+        Expr::Neg(e) => eval(e, session)?.wrapping_neg(),
+        Expr::Binary(op, a, b) => {
+            let a = eval(a, session)?;
+            // `&&` and `||` short-circuit, so `rbx != 0 && [rbx] == 1` is safe.
+            match op {
+                Op::And if a == 0 => return Ok(0),
+                Op::Or if a != 0 => return Ok(1),
+                _ => {}
+            }
+            let b = eval(b, session)?;
+            match op {
+                Op::Or | Op::And => (b != 0) as i64,
+                Op::Eq => (a == b) as i64,
+                Op::Ne => (a != b) as i64,
+                Op::Lt => (a < b) as i64,
+                Op::Le => (a <= b) as i64,
+                Op::Gt => (a > b) as i64,
+                Op::Ge => (a >= b) as i64,
+                Op::Add => a.wrapping_add(b),
+                Op::Sub => a.wrapping_sub(b),
+            }
+        }
+    })
+}
+
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$') {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || matches!(chars[i], '_' | '.' | '$'))
+            {
+                i += 1;
+            }
+            tokens.push(chars[start..i].iter().collect());
+        } else {
+            let pair: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+            if ["==", "!=", "<=", ">=", "&&", "||"].contains(&pair.as_str()) {
+                tokens.push(pair);
+                i += 2;
+            } else if "<>!+-()[]".contains(c) {
+                tokens.push(c.to_string());
+                i += 1;
+            } else {
+                return Err(format!("Unexpected `{}` in condition", c));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// `eax` is bits 0..32 of RAX, `ah` bits 8..16, and so on.
+fn register_part(name: &str) -> Option<(Register, u32, u32)> {
+    let reg = x86_asm::register_family(name)?;
+    let name = name.to_lowercase();
+    let (shift, bits) = if name.ends_with('h') && name.len() == 2 {
+        (8, 8)
+    } else if name.starts_with('e') || name.ends_with('d') {
+        (0, 32)
+    } else if name.ends_with('w') || (name.len() == 2 && name.ends_with(['x', 'i', 'p'])) {
+        (0, 16)
+    } else if name.ends_with('l') || name.ends_with('b') {
+        (0, 8)
+    } else {
+        (0, 64)
+    };
+    Some((reg, shift, bits))
+}
+
+struct Parser<'a> {
+    tokens: &'a [String],
+    at: usize,
+    session: &'a Session,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.at).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<&str, String> {
+        let token = self.tokens.get(self.at).ok_or("Condition ends too early")?;
+        self.at += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, want: &str) -> Result<(), String> {
+        match self.next()? {
+            got if got == want => Ok(()),
+            got => Err(format!("Expected `{}` but found `{}`", want, got)),
+        }
+    }
+
+    /// Left-associative chain of `ops` over `operand`.
+    fn chain(
+        &mut self,
+        ops: &[(&str, Op)],
+        operand: fn(&mut Self) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut left = operand(self)?;
+        while let Some(op) = self
+            .peek()
+            .and_then(|t| ops.iter().find(|(s, _)| *s == t))
+            .map(|(_, op)| *op)
+        {
+            self.at += 1;
+            left = Expr::Binary(op, Box::new(left), Box::new(operand(self)?));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.chain(&[("||", Op::Or)], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.chain(&[("&&", Op::And)], Self::comparison)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let ops = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ];
+        let left = self.sum()?;
+        match self.peek().and_then(|t| ops.iter().find(|(s, _)| *s == t)) {
+            Some((_, op)) => {
+                let op = *op;
+                self.at += 1;
+                Ok(Expr::Binary(op, Box::new(left), Box::new(self.sum()?)))
+            }
+            None => Ok(left),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.chain(&[("+", Op::Add), ("-", Op::Sub)], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some("!") => {
+                self.at += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some("-") => {
+                self.at += 1;
+                Ok(Expr::Neg(Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.next()?.to_string();
+        let size = match token.to_lowercase().as_str() {
+            "byte" => Some(1),
+            "word" => Some(2),
+            "dword" => Some(4),
+            "qword" => Some(8),
+            _ => None,
+        };
+        if token == "[" || size.is_some() {
+            if size.is_some() {
+                self.expect("[")?;
+            }
+            let addr = self.or()?;
+            self.expect("]")?;
+            return Ok(Expr::Mem(Box::new(addr), size.unwrap_or(8)));
+        }
+        if token == "(" {
+            let inner = self.or()?;
+            self.expect(")")?;
+            return Ok(inner);
+        }
+        let number = match token.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16).ok(),
+            None => token.parse().ok(),
+        };
+        if let Some(n) = number {
+            return Ok(Expr::Num(n));
+        }
+        let name = token.trim_start_matches('$');
+        if let Some((reg, shift, bits)) = register_part(name) {
+            return Ok(Expr::Reg { reg, shift, bits });
+        }
+        if let Some((_, bit)) = FLAGS.iter().find(|(f, _)| name.eq_ignore_ascii_case(f)) {
+            return Ok(Expr::Flag(*bit));
+        }
+        let label = self
+            .session
+            .bss_labels()
+            .get(name)
+            .or_else(|| self.session.assembled().labels.get(name));
+        match label {
+            Some(addr) => Ok(Expr::Num(*addr as i64)),
+            None => Err(format!("Unknown name in condition: {}", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_conditions_into_tokens() {
+        assert_eq!(
+            tokenize("rcx==3&&!zf || byte [buf+1] >= -2").unwrap(),
+            vec![
+                "rcx", "==", "3", "&&", "!", "zf", "||", "byte", "[", "buf", "+", "1", "]", ">=",
+                "-", "2"
+            ]
+        );
+        assert!(tokenize("rax % 2").is_err());
+    }
+
+    #[test]
+    fn names_parts_of_registers() {
+        assert_eq!(register_part("rcx"), Some((Register::RCX, 0, 64)));
+        assert_eq!(register_part("ecx"), Some((Register::RCX, 0, 32)));
+        assert_eq!(register_part("cx"), Some((Register::RCX, 0, 16)));
+        assert_eq!(register_part("cl"), Some((Register::RCX, 0, 8)));
+        assert_eq!(register_part("ch"), Some((Register::RCX, 8, 8)));
+        assert_eq!(register_part("r9d"), Some((Register::R9, 0, 32)));
+        assert_eq!(register_part("sil"), Some((Register::RSI, 0, 8)));
+        assert_eq!(register_part("r12"), Some((Register::R12, 0, 64)));
+    }
+}
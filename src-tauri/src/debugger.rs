@@ -0,0 +1,948 @@
+use crate::condition::Condition;
+use crate::memory::{self, MemoryFormat};
+use crate::vm::{Register, Syntax};
+use crate::x86_asm;
+use crate::x86_runtime::{Session, WatchHit, WatchKind, Watchpoint};
+
+/// Instructions `continue`, `step` and `next` may run before giving up.
+const DEFAULT_RUN_LIMIT: usize = 50_000;
+
+const REGISTERS: [Register; 16] = [
+    Register::RAX,
+    Register::RBX,
+    Register::RCX,
+    Register::RDX,
+    Register::RSI,
+    Register::RDI,
+    Register::RSP,
+    Register::RBP,
+    Register::R8,
+    Register::R9,
+    Register::R10,
+    Register::R11,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+];
+
+pub const HELP: &str = "\
+step [n]        run to the next source line (into calls), n times
+stepi [n]       run one machine instruction, n times
+next [n]        like step, but run over calls
+continue        run until a breakpoint, exit or fault
+break <line|label> [if <cond>]   set a breakpoint, e.g. break .loop if rcx == 3 && zf;
+                `break` alone lists them
+condition <n> [cond]   set or clear the condition of breakpoint n
+ignore <n> <count>     pass breakpoint n the next count times it is hit
+delete <n>      remove breakpoint n
+watch <expr> [len]   stop when len bytes (default 1) at expr are written, e.g. watch buf+3
+rwatch <expr> [len]  stop when they are read; awatch: read or written; `watch` alone lists them
+unwatch <n>     remove watchpoint n
+regs            registers and flags
+x/<n><x|d|c><b|h|w|g> <expr>   examine memory, e.g. x/16xb buf, x/2xg $rsp+8
+stack [n]       n qwords from RSP upwards (default 8)
+input [values]  show the remaining input, or append decimal values
+output          values written so far
+where           current location
+bt              active calls, innermost first
+maps            mapped memory: code, data, stack, heap and mmap regions
+quit";
+
+/// `x/16xb`: count, format (`x` hex, `d` signed decimal, `c` char) and unit size in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Examine {
+    pub count: usize,
+    pub format: char,
+    pub unit: usize,
+}
+
+pub fn parse_examine(spec: &str) -> Result<Examine, String> {
+    let rest = spec
+        .strip_prefix("x/")
+        .or_else(|| spec.strip_prefix('x'))
+        .ok_or_else(|| format!("Not an examine command: {}", spec))?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    let mut ex = Examine {
+        count: if digits.is_empty() {
+            1
+        } else {
+            digits
+                .parse()
+                .map_err(|_| format!("Bad count: {}", digits))?
+        },
+        format: 'x',
+        unit: 1,
+    };
+    for c in rest[digits.len()..].chars() {
+        match c {
+            'x' | 'd' | 'c' => ex.format = c,
+            'b' => ex.unit = 1,
+            'h' => ex.unit = 2,
+            'w' => ex.unit = 4,
+            'g' => ex.unit = 8,
+            other => return Err(format!("Unknown examine letter: {}", other)),
+        }
+    }
+    Ok(ex)
+}
+
+fn parse_number(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// A breakpoint on the first instruction of a source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u64,
+    pub line: usize,
+    /// What the user typed (`12`, `.loop`).
+    pub spec: String,
+    /// Only stop when this holds.
+    pub condition: Option<Condition>,
+    /// Hits (with the condition true) to pass before stopping again.
+    pub ignore_count: usize,
+    /// Times it was reached with the condition true.
+    pub hit_count: usize,
+}
+
+/// A watchpoint and the expression it was set with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    pub point: Watchpoint,
+    /// What the user typed (`buf+3`).
+    pub spec: String,
+}
+
+/// `0x2a (42)` for a 1/2/4/8-byte little-endian value, hex bytes otherwise.
+fn format_value(bytes: &[u8]) -> String {
+    if !matches!(bytes.len(), 1 | 2 | 4 | 8) {
+        let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        return hex.join(" ");
+    }
+    let value = memory::le_u64(bytes);
+    format!(
+        "0x{:0width$x} ({})",
+        value,
+        memory::sign_extend(value, 8 * bytes.len() as u32),
+        width = bytes.len() * 2
+    )
+}
+
+/// Line-oriented debugger over an `x86_runtime::Session`; the same engine the app grades with.
+pub struct Debugger {
+    session: Session,
+    source: Vec<String>,
+    breakpoints: Vec<Breakpoint>,
+    watches: Vec<Watch>,
+    run_limit: usize,
+}
+
+impl Debugger {
+    pub fn new(code: &str, syntax: Syntax, input: Vec<i64>) -> Result<Debugger, String> {
+        let mut session = Session::new(code, syntax, input, false)?;
+        session.track_memory_changes()?;
+        Ok(Debugger {
+            session,
+            source: code.lines().map(str::to_string).collect(),
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            run_limit: DEFAULT_RUN_LIMIT,
+        })
+    }
+
+    /// Debug a static executable; without source, locations are shown as `symbol+offset`.
+    pub fn from_elf(image: &[u8], args: &[String], input: Vec<i64>) -> Result<Debugger, String> {
+        let mut session = Session::from_elf(image, args, &[], input, false)?;
+        session.track_memory_changes()?;
+        Ok(Debugger {
+            session,
+            source: Vec::new(),
+            breakpoints: Vec::new(),
+            watches: Vec::new(),
+            run_limit: DEFAULT_RUN_LIMIT,
+        })
+    }
+
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+
+    /// Source line of the instruction at RIP, if RIP is inside the program.
+    pub fn current_line(&self) -> Option<usize> {
+        self.session
+            .instruction_at(self.session.rip())
+            .map(|i| i.line)
+    }
+
+    /// `0x100012  L7: mov rax, 1`, or why the program is no longer running.
+    pub fn location(&self) -> String {
+        let state = self.session.state();
+        if state.exited {
+            let code = self.session.register(Register::RDI) as i64;
+            return format!("Program exited with code {}.", code);
+        }
+        if let Some(err) = state.error {
+            return format!("Program stopped: {}", err);
+        }
+        let rip = self.session.rip();
+        match self.current_line() {
+            Some(line) => format!(
+                "0x{:x}  L{}: {}",
+                rip,
+                line,
+                self.source.get(line - 1).map_or("", |l| l.trim())
+            ),
+            None => match self.symbol_at(rip) {
+                Some(symbol) => format!("0x{:x}  <{}>", rip, symbol),
+                None => format!("0x{:x}  (outside the program)", rip),
+            },
+        }
+    }
+
+    /// `L7: mov [buf], al` or `<_start+5>` for the instruction at `addr`.
+    fn describe_address(&self, addr: u64) -> String {
+        match self.session.instruction_at(addr) {
+            Some(inst) => format!(
+                "L{}: {}",
+                inst.line,
+                self.source.get(inst.line - 1).map_or("", |l| l.trim())
+            ),
+            None => match self.symbol_at(addr) {
+                Some(symbol) => format!("<{}>", symbol),
+                None => "(outside the program)".to_string(),
+            },
+        }
+    }
+
+    /// `_start+5` for an address in the code, from the nearest label at or below it.
+    fn symbol_at(&self, addr: u64) -> Option<String> {
+        if self.session.is_past_end() {
+            return None;
+        }
+        let (name, base) = self
+            .session
+            .assembled()
+            .labels
+            .iter()
+            .filter(|(_, a)| **a <= addr)
+            .max_by(|x, y| x.1.cmp(y.1).then_with(|| y.0.cmp(x.0)))?;
+        Some(match addr - base {
+            0 => name.clone(),
+            offset => format!("{}+{}", name, offset),
+        })
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.session.is_finished() || self.session.is_past_end()
+    }
+
+    fn stepi(&mut self) -> Option<String> {
+        if self.is_stopped() {
+            return None;
+        }
+        let pc = self.session.rip();
+        self.session.step();
+        let hits = self.session.take_watch_hits();
+        if hits.is_empty() {
+            return None;
+        }
+        let reports: Vec<String> = hits.iter().map(|h| self.describe_hit(h, pc)).collect();
+        Some(reports.join("\n"))
+    }
+
+    fn describe_hit(&self, hit: &WatchHit, pc: u64) -> String {
+        let watch = &self.watches[hit.watchpoint];
+        let verb = match hit.access {
+            WatchKind::Read => "read",
+            _ => "written",
+        };
+        let head = format!(
+            "Watchpoint {} ({}) {} at 0x{:x} by 0x{:x}  {}",
+            hit.watchpoint + 1,
+            watch.spec,
+            verb,
+            hit.address,
+            pc,
+            self.describe_address(pc)
+        );
+        match hit.access {
+            WatchKind::Read => format!("{}\nValue = {}", head, format_value(&hit.old)),
+            _ => format!(
+                "{}\nOld value = {}\nNew value = {}",
+                head,
+                format_value(&hit.old),
+                format_value(&hit.new)
+            ),
+        }
+    }
+
+    /// Run until the source line changes. With `over_calls`, a `call` runs until it returns.
+    fn step_line(&mut self, over_calls: bool) -> Option<String> {
+        let start_line = self.current_line();
+        if start_line.is_none() {
+            // No source (a loaded executable): a line is an instruction.
+            return self.stepi();
+        }
+        for n in 0..self.run_limit {
+            if self.is_stopped() {
+                return None;
+            }
+            // The breakpoint being stepped away from does not count.
+            if n > 0 {
+                if let Some(msg) = self.check_breakpoints() {
+                    return Some(msg);
+                }
+            }
+            let rip = self.session.rip();
+            let call_return = self
+                .session
+                .instruction_at(rip)
+                .filter(|i| over_calls && i.text.to_lowercase().starts_with("call"))
+                .map(|i| i.address + i.size as u64);
+            if let Some(msg) = self.stepi() {
+                return Some(msg);
+            }
+            if let Some(ret) = call_return {
+                let depth = self.session.register(Register::RSP);
+                if let Some(msg) =
+                    self.run_until(|s| s.rip() == ret && s.register(Register::RSP) > depth)
+                {
+                    return Some(msg);
+                }
+            }
+            if self.current_line() != start_line {
+                return None;
+            }
+        }
+        Some(format!("Stopped after {} instructions.", self.run_limit))
+    }
+
+    /// Step until `done` holds, a breakpoint is reached, or the program stops.
+    fn run_until(&mut self, done: impl Fn(&Session) -> bool) -> Option<String> {
+        for _ in 0..self.run_limit {
+            if self.is_stopped() || done(&self.session) {
+                return None;
+            }
+            if let Some(msg) = self.check_breakpoints() {
+                return Some(msg);
+            }
+            if let Some(msg) = self.stepi() {
+                return Some(msg);
+            }
+        }
+        Some(format!("Stopped after {} instructions.", self.run_limit))
+    }
+
+    /// Count a hit on the breakpoints at RIP and say why to stop, if one should.
+    fn check_breakpoints(&mut self) -> Option<String> {
+        let rip = self.session.rip();
+        let mut stop = None;
+        for (n, bp) in self.breakpoints.iter_mut().enumerate() {
+            if bp.address != rip {
+                continue;
+            }
+            let holds = match &bp.condition {
+                Some(cond) => match cond.holds(&self.session) {
+                    Ok(holds) => holds,
+                    Err(e) => {
+                        return Some(format!(
+                            "Breakpoint {} ({}): cannot evaluate `{}`: {}",
+                            n + 1,
+                            bp.spec,
+                            cond.text,
+                            e
+                        ))
+                    }
+                },
+                None => true,
+            };
+            if !holds {
+                continue;
+            }
+            bp.hit_count += 1;
+            if bp.ignore_count > 0 {
+                bp.ignore_count -= 1;
+                continue;
+            }
+            stop.get_or_insert(format!("Breakpoint {} ({})", n + 1, bp.spec));
+        }
+        stop
+    }
+
+    /// `break` arguments: a line or label, then optionally `if <condition>`.
+    fn add_breakpoint(&mut self, args: &[&str]) -> Result<String, String> {
+        let (spec, condition) = match args.iter().position(|a| *a == "if") {
+            Some(i) => (&args[..i], Some(args[i + 1..].join(" "))),
+            None => (args, None),
+        };
+        let [spec] = spec else {
+            return Err("Usage: break <line|label> [if <condition>]".to_string());
+        };
+        let mut bp = self.resolve_breakpoint(spec)?;
+        bp.condition = condition
+            .map(|c| Condition::parse(&c, &self.session))
+            .transpose()?;
+        let msg = format!(
+            "Breakpoint {} at 0x{:x}: L{}",
+            self.breakpoints.len() + 1,
+            bp.address,
+            bp.line
+        );
+        self.breakpoints.push(bp);
+        Ok(msg)
+    }
+
+    /// `condition <n> <words>`; no words make breakpoint `n` unconditional.
+    fn set_condition(&mut self, n: usize, words: &[&str]) -> Result<String, String> {
+        let condition = match words {
+            [] => None,
+            words => Some(Condition::parse(&words.join(" "), &self.session)?),
+        };
+        let msg = match &condition {
+            Some(c) => format!("Breakpoint {} stops only if {}.", n, c.text),
+            None => format!("Breakpoint {} is now unconditional.", n),
+        };
+        self.breakpoints[n - 1].condition = condition;
+        Ok(msg)
+    }
+
+    fn continue_run(&mut self) -> Option<String> {
+        // Leave the breakpoint we may be sitting on before checking again.
+        if let Some(msg) = self.stepi() {
+            return Some(msg);
+        }
+        self.run_until(|_| false)
+    }
+
+    /// Address of a `break` argument: a source line number or a code label.
+    pub fn resolve_breakpoint(&self, spec: &str) -> Result<Breakpoint, String> {
+        let insts = &self.session.assembled().instructions;
+        let inst = if let Ok(line) = spec.parse::<usize>() {
+            insts
+                .iter()
+                .filter(|i| i.line >= line)
+                .min_by_key(|i| (i.line, i.address))
+                .ok_or_else(|| format!("No code at or after line {}", line))?
+        } else {
+            let addr = self
+                .session
+                .assembled()
+                .labels
+                .get(spec)
+                .ok_or_else(|| format!("Unknown label: {}", spec))?;
+            if insts.is_empty() {
+                // A loaded executable has symbols but no source lines.
+                return Ok(Breakpoint {
+                    address: *addr,
+                    line: 0,
+                    spec: spec.to_string(),
+                    condition: None,
+                    ignore_count: 0,
+                    hit_count: 0,
+                });
+            }
+            insts
+                .iter()
+                .find(|i| i.address >= *addr)
+                .ok_or_else(|| format!("Label {} has no code after it", spec))?
+        };
+        Ok(Breakpoint {
+            address: inst.address,
+            line: inst.line,
+            spec: spec.to_string(),
+            condition: None,
+            ignore_count: 0,
+            hit_count: 0,
+        })
+    }
+
+    /// Evaluate `buf`, `buf+3`, `$rsp-8`, `rcx`, `0x200000` or `42` to an address.
+    pub fn resolve_address(&self, expr: &str) -> Result<u64, String> {
+        let expr = expr.replace(' ', "");
+        let split = expr
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| matches!(c, '+' | '-'))
+            .map_or(expr.len(), |(i, _)| i);
+        let (base, offset) = expr.split_at(split);
+        let base_value = if let Some(n) = parse_number(base) {
+            n
+        } else if let Some(reg) = x86_asm::register_family(base.trim_start_matches('$')) {
+            self.session.register(reg)
+        } else if let Some(addr) = self.session.bss_labels().get(base) {
+            *addr
+        } else if let Some(addr) = self.session.assembled().labels.get(base) {
+            *addr
+        } else {
+            return Err(format!("Cannot resolve `{}`", base));
+        };
+        if offset.is_empty() {
+            return Ok(base_value);
+        }
+        let amount = parse_number(&offset[1..]).ok_or_else(|| format!("Bad offset: {}", offset))?;
+        Ok(if offset.starts_with('+') {
+            base_value.wrapping_add(amount)
+        } else {
+            base_value.wrapping_sub(amount)
+        })
+    }
+
+    /// `watch`, `rwatch` or `awatch` of `len` bytes at an address expression.
+    fn add_watch(
+        &mut self,
+        kind: WatchKind,
+        expr: &str,
+        len: Option<&str>,
+    ) -> Result<String, String> {
+        let address = self.resolve_address(expr)?;
+        let len = match len {
+            Some(n) => parse_number(n)
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("Bad length: {}", n))?,
+            None => 1,
+        };
+        self.session.read_memory(address, len as usize)?;
+        self.watches.push(Watch {
+            point: Watchpoint { address, len, kind },
+            spec: expr.to_string(),
+        });
+        self.sync_watchpoints()?;
+        Ok(format!(
+            "Watchpoint {} ({:?}): {} byte(s) at 0x{:x}",
+            self.watches.len(),
+            kind,
+            len,
+            address
+        ))
+    }
+
+    fn sync_watchpoints(&mut self) -> Result<(), String> {
+        let points = self.watches.iter().map(|w| w.point.clone()).collect();
+        self.session.set_watchpoints(points)
+    }
+
+    fn examine(&self, spec: &str, expr: &str) -> Result<String, String> {
+        let ex = parse_examine(spec)?;
+        let addr = self.resolve_address(expr)?;
+        let (format, unit) = match (ex.format, ex.unit) {
+            ('c', _) => (MemoryFormat::Ascii, 1),
+            (_, 1) => (MemoryFormat::Bytes, 1),
+            (_, 2) => (MemoryFormat::Words, 2),
+            (_, 4) => (MemoryFormat::Dwords, 4),
+            _ => (MemoryFormat::Qwords, 8),
+        };
+        let bytes = self.session.read_memory(addr, ex.count * unit)?;
+        let cells = memory::format_cells(addr, &bytes, format, ex.format == 'd');
+        Ok(cells
+            .chunks(16 / unit)
+            .map(|row| {
+                let texts: Vec<&str> = row.iter().map(|c| c.text.as_str()).collect();
+                format!("0x{:x}:  {}", row[0].address, texts.join(" "))
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn memory_map(&self) -> String {
+        self.session
+            .state()
+            .memory_map
+            .iter()
+            .map(|r| {
+                format!(
+                    "0x{:012x}-0x{:012x} {} {:?}",
+                    r.start,
+                    r.start + r.size,
+                    r.perms,
+                    r.origin
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn backtrace(&self) -> String {
+        let stack = self.session.state().call_stack;
+        if stack.is_empty() {
+            return "No active calls.".to_string();
+        }
+        stack
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, f)| {
+                let from = f
+                    .call_line
+                    .map_or(String::new(), |l| format!(" from L{}", l));
+                format!(
+                    "#{} {}, called{} (returns to 0x{:x}, frame 0x{:x}..0x{:x})",
+                    i,
+                    f.function,
+                    from,
+                    f.return_address,
+                    f.rsp,
+                    f.entry_rsp + 8
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn regs(&self) -> String {
+        let mut out = Vec::new();
+        for reg in REGISTERS {
+            let v = self.session.register(reg);
+            out.push(format!(
+                "{:<4} 0x{:016x}  {}",
+                format!("{:?}", reg),
+                v,
+                v as i64
+            ));
+        }
+        out.push(format!("RIP  0x{:016x}", self.session.rip()));
+        let rflags = self.session.rflags();
+        let flags: Vec<String> = [
+            ("CF", 0),
+            ("PF", 2),
+            ("ZF", 6),
+            ("SF", 7),
+            ("DF", 10),
+            ("OF", 11),
+        ]
+        .iter()
+        .map(|(name, bit)| format!("{}={}", name, (rflags >> bit) & 1))
+        .collect();
+        out.push(format!("flags {}", flags.join(" ")));
+        out.join("\n")
+    }
+
+    fn stack(&self, count: usize) -> Result<String, String> {
+        let rsp = self.session.register(Register::RSP);
+        let top = self.session.stack_top();
+        if rsp >= top {
+            return Ok("(stack is empty)".to_string());
+        }
+        let available = ((top - rsp) / 8) as usize;
+        let mut out = Vec::new();
+        for i in 0..count.min(available) {
+            let addr = rsp + 8 * i as u64;
+            let bytes = self.session.read_memory(addr, 8)?;
+            let v = u64::from_le_bytes(bytes.try_into().unwrap_or([0; 8]));
+            let marker = if i == 0 { "<- rsp" } else { "" };
+            out.push(format!(
+                "0x{:x}:  0x{:016x}  {:>6} {}",
+                addr, v, v as i64, marker
+            ));
+        }
+        Ok(out.join("\n").trim_end().to_string())
+    }
+
+    /// Run one command line and return what to print. `quit` is handled by the caller.
+    pub fn execute(&mut self, line: &str) -> String {
+        let mut words = line.split_whitespace();
+        let Some(cmd) = words.next() else {
+            return String::new();
+        };
+        let args: Vec<&str> = words.collect();
+        let repeat = args.first().and_then(|a| a.parse().ok()).unwrap_or(1usize);
+
+        let result: Result<String, String> = match cmd {
+            "s" | "step" | "n" | "next" => {
+                let over = cmd.starts_with('n');
+                let mut note = None;
+                for _ in 0..repeat {
+                    note = self.step_line(over);
+                    if note.is_some() || self.is_stopped() {
+                        break;
+                    }
+                }
+                Ok(note.map_or(self.location(), |n| format!("{}\n{}", n, self.location())))
+            }
+            "si" | "stepi" => {
+                let mut note = None;
+                for _ in 0..repeat {
+                    note = self.stepi();
+                    if note.is_some() {
+                        break;
+                    }
+                }
+                Ok(note.map_or(self.location(), |n| format!("{}\n{}", n, self.location())))
+            }
+            "c" | "continue" => {
+                let note = self.continue_run();
+                Ok(note.map_or(self.location(), |n| format!("{}\n{}", n, self.location())))
+            }
+            "b" | "break" => match args.first() {
+                None if self.breakpoints.is_empty() => Ok("No breakpoints.".to_string()),
+                None => Ok(self
+                    .breakpoints
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| {
+                        let mut line =
+                            format!("{}: {} at 0x{:x} (L{})", i + 1, b.spec, b.address, b.line);
+                        if let Some(cond) = &b.condition {
+                            line.push_str(&format!(" if {}", cond.text));
+                        }
+                        if b.ignore_count > 0 {
+                            line.push_str(&format!(", ignore next {}", b.ignore_count));
+                        }
+                        if b.hit_count > 0 {
+                            line.push_str(&format!(", hit {} time(s)", b.hit_count));
+                        }
+                        line
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                Some(_) => self.add_breakpoint(&args),
+            },
+            "condition" => match args.first().and_then(|a| a.parse::<usize>().ok()) {
+                Some(n) if (1..=self.breakpoints.len()).contains(&n) => {
+                    self.set_condition(n, &args[1..])
+                }
+                _ => Err("Usage: condition <breakpoint number> [condition]".to_string()),
+            },
+            "ignore" => {
+                let n = args.first().and_then(|a| a.parse::<usize>().ok());
+                let count = args.get(1).and_then(|a| a.parse::<usize>().ok());
+                match (n, count) {
+                    (Some(n), Some(count)) if (1..=self.breakpoints.len()).contains(&n) => {
+                        self.breakpoints[n - 1].ignore_count = count;
+                        Ok(format!(
+                            "Will ignore next {} crossing(s) of breakpoint {}.",
+                            count, n
+                        ))
+                    }
+                    _ => Err("Usage: ignore <breakpoint number> <count>".to_string()),
+                }
+            }
+            "d" | "delete" => match args.first().and_then(|a| a.parse::<usize>().ok()) {
+                Some(n) if (1..=self.breakpoints.len()).contains(&n) => {
+                    self.breakpoints.remove(n - 1);
+                    Ok(format!("Deleted breakpoint {}.", n))
+                }
+                _ => Err("Usage: delete <breakpoint number>".to_string()),
+            },
+            "watch" if args.is_empty() && self.watches.is_empty() => {
+                Ok("No watchpoints.".to_string())
+            }
+            "watch" if args.is_empty() => Ok(self
+                .watches
+                .iter()
+                .enumerate()
+                .map(|(i, w)| {
+                    format!(
+                        "{}: {} ({:?}) {} byte(s) at 0x{:x}",
+                        i + 1,
+                        w.spec,
+                        w.point.kind,
+                        w.point.len,
+                        w.point.address
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "watch" | "rwatch" | "awatch" => {
+                let kind = match cmd {
+                    "rwatch" => WatchKind::Read,
+                    "awatch" => WatchKind::Access,
+                    _ => WatchKind::Write,
+                };
+                match args.first() {
+                    Some(expr) => self.add_watch(kind, expr, args.get(1).copied()),
+                    None => Err(format!("Usage: {} <expr> [len]", cmd)),
+                }
+            }
+            "unwatch" => match args.first().and_then(|a| a.parse::<usize>().ok()) {
+                Some(n) if (1..=self.watches.len()).contains(&n) => {
+                    self.watches.remove(n - 1);
+                    self.sync_watchpoints()
+                        .map(|_| format!("Deleted watchpoint {}.", n))
+                }
+                _ => Err("Usage: unwatch <watchpoint number>".to_string()),
+            },
+            "regs" | "registers" => Ok(self.regs()),
+            "stack" => self.stack(args.first().and_then(|a| a.parse().ok()).unwrap_or(8)),
+            "input" if args.is_empty() => Ok(format!("{:?}", self.session.input())),
+            "input" => args
+                .iter()
+                .map(|a| {
+                    a.parse::<i64>()
+                        .map_err(|_| format!("Invalid input value: {}", a))
+                })
+                .collect::<Result<Vec<i64>, String>>()
+                .map(|values| {
+                    self.session.push_input(&values);
+                    format!("{:?}", self.session.input())
+                }),
+            "output" => Ok(format!("{:?}", self.session.state().output)),
+            "where" | "w" => Ok(self.location()),
+            "bt" | "backtrace" => Ok(self.backtrace()),
+            "maps" => Ok(self.memory_map()),
+            "help" | "h" => Ok(HELP.to_string()),
+            x if x.starts_with('x') => match args.first() {
+                Some(_) => self.examine(x, &args.join(" ")),
+                None => Err("Usage: x/<n><fmt><unit> <address>".to_string()),
+            },
+            other => Err(format!("Unknown command: {} (try `help`)", other)),
+        };
+        result.unwrap_or_else(|e| format!("error: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_examine_specs() {
+        assert_eq!(
+            parse_examine("x/16xb").unwrap(),
+            Examine {
+                count: 16,
+                format: 'x',
+                unit: 1
+            }
+        );
+        assert_eq!(
+            parse_examine("x/2dg").unwrap(),
+            Examine {
+                count: 2,
+                format: 'd',
+                unit: 8
+            }
+        );
+        assert_eq!(parse_examine("x").unwrap().count, 1);
+        assert!(parse_examine("x/4q").is_err());
+    }
+
+    const PROGRAM: &str = "section .bss
+    buf resb 4
+
+section .text
+    global _start
+
+_start:
+    mov rcx, 2
+.loop:
+    dec rcx
+    jnz .loop
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+";
+
+    #[test]
+    fn breaks_on_labels_and_lines() {
+        let mut dbg = Debugger::new(PROGRAM, Syntax::Intel, vec![]).unwrap();
+        assert!(dbg.execute("break .loop").starts_with("Breakpoint 1"));
+        assert!(dbg.execute("break 12").starts_with("Breakpoint 2"));
+        assert_eq!(dbg.breakpoints()[0].line, 10);
+        assert_eq!(dbg.breakpoints()[1].line, 12);
+
+        assert!(dbg.execute("continue").contains("Breakpoint 1"));
+        assert_eq!(dbg.current_line(), Some(10));
+        assert!(dbg.execute("continue").contains("Breakpoint 1"));
+        assert!(dbg.execute("continue").contains("Breakpoint 2"));
+        assert!(dbg.execute("continue").contains("exited with code 0"));
+    }
+
+    #[test]
+    fn conditions_and_ignore_counts_skip_hits() {
+        let mut dbg = Debugger::new(PROGRAM, Syntax::Intel, vec![]).unwrap();
+        assert!(dbg
+            .execute("break .loop if rcx == 1 && !zf")
+            .starts_with("Breakpoint 1"));
+        assert!(dbg.execute("continue").contains("Breakpoint 1"));
+        assert_eq!(dbg.session().register(Register::RCX), 1);
+        assert!(dbg.execute("continue").contains("exited"));
+
+        let mut dbg = Debugger::new(PROGRAM, Syntax::Intel, vec![]).unwrap();
+        dbg.execute("break .loop");
+        dbg.execute("ignore 1 1");
+        assert!(dbg.execute("continue").contains("Breakpoint 1"));
+        assert_eq!(dbg.session().register(Register::RCX), 1);
+        assert!(dbg.execute("break").contains("hit 2 time(s)"));
+        assert!(dbg.execute("condition 1 rcx ==").starts_with("error"));
+    }
+
+    #[test]
+    fn resolves_label_offsets() {
+        let dbg = Debugger::new(PROGRAM, Syntax::Intel, vec![]).unwrap();
+        let buf = dbg.session().bss_labels()["buf"];
+        assert_eq!(dbg.resolve_address("buf+3").unwrap(), buf + 3);
+        assert_eq!(dbg.resolve_address("0x10").unwrap(), 16);
+        assert!(dbg.resolve_address("nope").is_err());
+        assert!(dbg.resolve_address("é+1").is_err());
+    }
+
+    #[test]
+    fn formats_watched_values() {
+        assert_eq!(format_value(&[0xff]), "0xff (-1)");
+        assert_eq!(format_value(&[0x2a, 0, 0, 0]), "0x0000002a (42)");
+        assert_eq!(format_value(&[1, 2, 3]), "01 02 03");
+    }
+
+    #[test]
+    fn watchpoints_report_who_wrote() {
+        let code = "section .bss
+    buf resb 4
+
+section .text
+_start:
+    mov byte [buf], 1
+    mov byte [buf + 3], 9
+    movzx eax, byte [buf + 3]
+    mov rax, 60
+    syscall
+";
+        let mut dbg = Debugger::new(code, Syntax::Intel, vec![]).unwrap();
+        assert!(dbg.execute("watch buf+3").starts_with("Watchpoint 1"));
+        let out = dbg.execute("continue");
+        assert!(out.contains("written"), "{}", out);
+        assert!(out.contains("L7: mov byte [buf + 3], 9"), "{}", out);
+        assert!(out.contains("Old value = 0x00 (0)"), "{}", out);
+        assert!(out.contains("New value = 0x09 (9)"), "{}", out);
+        assert_eq!(dbg.current_line(), Some(8));
+
+        dbg.execute("unwatch 1");
+        dbg.execute("rwatch buf+3");
+        let out = dbg.execute("continue");
+        assert!(out.contains("read") && out.contains("L8"), "{}", out);
+        assert!(dbg.execute("continue").contains("exited"));
+    }
+
+    #[test]
+    fn steps_by_source_line() {
+        let mut dbg = Debugger::new(PROGRAM, Syntax::Intel, vec![1]).unwrap();
+        assert_eq!(dbg.current_line(), Some(8));
+        dbg.execute("step");
+        assert_eq!(dbg.current_line(), Some(10));
+        assert_eq!(dbg.execute("input 5"), "[1, 5]");
+    }
+
+    #[test]
+    fn stepping_within_a_line_stops_at_breakpoints() {
+        let code = "section .text
+_start:
+.spin:
+    jmp .spin
+";
+        let mut dbg = Debugger::new(code, Syntax::Intel, vec![]).unwrap();
+        assert!(dbg.execute("break .spin").starts_with("Breakpoint 1"));
+        assert!(dbg.execute("next").contains("Breakpoint 1"));
+        assert!(dbg.execute("break").contains("hit 1 time(s)"));
+    }
+}
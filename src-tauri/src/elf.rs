@@ -0,0 +1,825 @@
+//! Static ELF64 executables built from a solution, for running it natively on x86-64 Linux.
+//!
+//! The image mirrors what `x86_runtime` loads: `.text` at `CODE_BASE`, a zero-filled `.bss` at
+//! `BSS_BASE`, and execution starting at `_start`. `parse_executable` reads such an image (or one
+//! linked by `ld`) back for `x86_runtime` to load. `write_object` emits relocatable objects
+//! instead, for linking a solution's functions into a C program.
+
+use std::collections::HashMap;
+
+use crate::vm::Syntax;
+use crate::x86_asm;
+use crate::x86_runtime::{self, BSS_BASE, CODE_BASE};
+
+const PAGE_SIZE: u64 = 0x1000;
+const EHDR_SIZE: u64 = 64;
+const PHDR_SIZE: u64 = 56;
+const SHDR_SIZE: u64 = 64;
+
+const PT_LOAD: u32 = 1;
+const PT_INTERP: u32 = 3;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+const SHF_WRITE: u64 = 1;
+const SHF_ALLOC: u64 = 2;
+const SHF_EXECINSTR: u64 = 4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+const SYM_SIZE: u64 = 24;
+const RELA_SIZE: u64 = 24;
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const STT_SECTION: u8 = 3;
+
+fn put16(out: &mut Vec<u8>, v: u16) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, v: u32) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn put64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn program_header(out: &mut Vec<u8>, flags: u32, offset: u64, vaddr: u64, filesz: u64, memsz: u64) {
+    put32(out, PT_LOAD);
+    put32(out, flags);
+    put64(out, offset);
+    put64(out, vaddr);
+    put64(out, vaddr); // p_paddr
+    put64(out, filesz);
+    put64(out, memsz);
+    put64(out, PAGE_SIZE);
+}
+
+#[derive(Default)]
+struct Section {
+    name: u32,
+    kind: u32,
+    flags: u64,
+    addr: u64,
+    offset: u64,
+    size: u64,
+    link: u32,
+    info: u32,
+    align: u64,
+    entsize: u64,
+}
+
+fn section_header(out: &mut Vec<u8>, s: &Section) {
+    put32(out, s.name);
+    put32(out, s.kind);
+    put64(out, s.flags);
+    put64(out, s.addr);
+    put64(out, s.offset);
+    put64(out, s.size);
+    put32(out, s.link);
+    put32(out, s.info);
+    put64(out, s.align);
+    put64(out, s.entsize);
+}
+
+/// The 64-byte ELF header of an `ET_EXEC` (2) or `ET_REL` (1) file.
+fn file_header(out: &mut Vec<u8>, kind: u16, entry: u64, phnum: u16, shoff: u64, shnum: u16) {
+    out.extend_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+    out.extend_from_slice(&[0; 8]);
+    put16(out, kind);
+    put16(out, 0x3e); // EM_X86_64
+    put32(out, 1);
+    put64(out, entry);
+    put64(out, if phnum > 0 { EHDR_SIZE } else { 0 });
+    put64(out, shoff);
+    put32(out, 0);
+    put16(out, EHDR_SIZE as u16);
+    put16(out, if phnum > 0 { PHDR_SIZE as u16 } else { 0 });
+    put16(out, phnum);
+    put16(out, SHDR_SIZE as u16);
+    put16(out, shnum);
+    put16(out, shnum - 1); // .shstrtab comes last
+}
+
+/// Lay out an executable: headers, then `.text` on its own page, then the section table.
+fn image(text: &[u8], entry: u64, bss_size: u64) -> Vec<u8> {
+    let phnum: u16 = if bss_size > 0 { 2 } else { 1 };
+    let text_offset = PAGE_SIZE;
+    let shstrtab: &[u8] = b"\0.text\0.bss\0.shstrtab\0";
+    let shstrtab_offset = text_offset + text.len() as u64;
+    let shoff = (shstrtab_offset + shstrtab.len() as u64).next_multiple_of(8);
+
+    let mut out = Vec::new();
+    // Sections: null, .text, .bss, .shstrtab
+    file_header(&mut out, 2, entry, phnum, shoff, 4);
+
+    let text_len = text.len() as u64;
+    program_header(
+        &mut out,
+        PF_R | PF_X,
+        text_offset,
+        CODE_BASE,
+        text_len,
+        text_len,
+    );
+    if bss_size > 0 {
+        program_header(&mut out, PF_R | PF_W, 0, BSS_BASE, 0, bss_size);
+    }
+
+    out.resize(text_offset as usize, 0);
+    out.extend_from_slice(text);
+    out.extend_from_slice(shstrtab);
+    out.resize(shoff as usize, 0);
+
+    let sections = [
+        Section::default(),
+        Section {
+            name: 1,
+            kind: SHT_PROGBITS,
+            flags: SHF_ALLOC | SHF_EXECINSTR,
+            addr: CODE_BASE,
+            offset: text_offset,
+            size: text_len,
+            align: 16,
+            ..Section::default()
+        },
+        Section {
+            name: 7,
+            kind: SHT_NOBITS,
+            flags: SHF_ALLOC | SHF_WRITE,
+            addr: BSS_BASE,
+            offset: text_offset + text_len,
+            size: bss_size,
+            align: 16,
+            ..Section::default()
+        },
+        Section {
+            name: 12,
+            kind: SHT_STRTAB,
+            flags: 0,
+            addr: 0,
+            offset: shstrtab_offset,
+            size: shstrtab.len() as u64,
+            align: 1,
+            ..Section::default()
+        },
+    ];
+    for s in &sections {
+        section_header(&mut out, s);
+    }
+    out
+}
+
+fn emulator_only_input(code: &str) -> Vec<String> {
+    x86_asm::source_instructions(code)
+        .iter()
+        .filter(|inst| inst.mnemonic == "in")
+        .map(|inst| {
+            format!(
+                "line {}: `in` only exists in the emulator; use the read syscall (rax = 0)",
+                inst.line
+            )
+        })
+        .collect()
+}
+
+/// Build a static x86-64 Linux executable from a solution.
+///
+/// The `in` pseudo-instruction reads from the emulator's input queue and has no native
+/// equivalent, so programs using it are rejected; read input with the `read` syscall instead.
+/// `loop` is already assembled as `dec rcx; jnz`, which runs natively as is.
+pub fn build_executable(code: &str, syntax: Syntax) -> Result<Vec<u8>, String> {
+    let mut problems = emulator_only_input(code);
+    for (idx, raw) in code.lines().enumerate() {
+        let line = raw
+            .split([';', '#'])
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_lowercase();
+        if line.starts_with("section ") && line.contains(".data") {
+            problems.push(format!(
+                "line {}: `.data` is not supported; reserve space in `.bss` instead",
+                idx + 1
+            ));
+        }
+    }
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+
+    let assembled = x86_runtime::assemble_program(code, syntax)?;
+    let entry = *assembled
+        .labels
+        .get("_start")
+        .ok_or("No `_start` label; the executable needs an entry point")?;
+    let bss = x86_runtime::parse_bss_layout(code);
+    Ok(image(&assembled.bytes, entry, bss.total_size))
+}
+
+/// Serialize object code as an ELF64 relocatable object (`.o`), as `nasm -f elf64` would.
+///
+/// Sections: null, `.text`, `.data`, `.bss`, `.symtab`, `.strtab`, `.rela.text`,
+/// `.note.GNU-stack` (so the linker does not ask for an executable stack), `.shstrtab`.
+pub fn write_object(object: &x86_asm::ObjectCode) -> Vec<u8> {
+    use x86_asm::{ObjectSection, RelocationKind};
+
+    fn add_name(table: &mut Vec<u8>, name: &str) -> u32 {
+        let at = table.len() as u32;
+        table.extend_from_slice(name.as_bytes());
+        table.push(0);
+        at
+    }
+    let section_index = |s: ObjectSection| match s {
+        ObjectSection::Text => 1u16,
+        ObjectSection::Data => 2,
+        ObjectSection::Bss => 3,
+    };
+
+    // Null and section symbols first, then locals, then globals, as ELF requires.
+    let mut strtab = vec![0u8];
+    let mut symtab = vec![0u8; SYM_SIZE as usize];
+    let put_symbol = |symtab: &mut Vec<u8>, name: u32, info: u8, shndx: u16, value: u64| {
+        put32(symtab, name);
+        symtab.push(info);
+        symtab.push(0); // st_other
+        put16(symtab, shndx);
+        put64(symtab, value);
+        put64(symtab, 0); // st_size
+    };
+    for shndx in 1..=3 {
+        put_symbol(&mut symtab, 0, (STB_LOCAL << 4) | STT_SECTION, shndx, 0);
+    }
+    let mut ordered: Vec<&x86_asm::ObjectSymbol> = object.symbols.iter().collect();
+    ordered.sort_by_key(|s| s.global);
+    let first_global = 4 + ordered.iter().filter(|s| !s.global).count() as u32;
+    let mut indices: HashMap<&str, u64> = HashMap::new();
+    for (i, symbol) in ordered.iter().enumerate() {
+        indices.insert(symbol.name.as_str(), 4 + i as u64);
+        let kind = match symbol.section {
+            Some(ObjectSection::Text) if symbol.global => STT_FUNC,
+            Some(ObjectSection::Data | ObjectSection::Bss) => STT_OBJECT,
+            _ => STT_NOTYPE,
+        };
+        let bind = if symbol.global { STB_GLOBAL } else { STB_LOCAL };
+        let name = add_name(&mut strtab, &symbol.name);
+        let shndx = symbol.section.map_or(0, section_index);
+        put_symbol(&mut symtab, name, (bind << 4) | kind, shndx, symbol.offset);
+    }
+
+    let mut rela = Vec::new();
+    for r in &object.relocations {
+        let kind: u64 = match r.kind {
+            RelocationKind::Abs64 => 1,
+            RelocationKind::Pc32 => 2,
+            RelocationKind::Plt32 => 4,
+            RelocationKind::Abs32S => 11,
+        };
+        put64(&mut rela, r.offset);
+        put64(&mut rela, (indices[r.symbol.as_str()] << 32) | kind);
+        put64(&mut rela, r.addend as u64);
+    }
+
+    let mut shstrtab = vec![0u8];
+    let names: Vec<u32> = [
+        ".text",
+        ".data",
+        ".bss",
+        ".symtab",
+        ".strtab",
+        ".rela.text",
+        ".note.GNU-stack",
+        ".shstrtab",
+    ]
+    .iter()
+    .map(|n| add_name(&mut shstrtab, n))
+    .collect();
+
+    let mut out = vec![0u8; EHDR_SIZE as usize];
+    let place = |out: &mut Vec<u8>, bytes: &[u8], align: u64| {
+        out.resize((out.len() as u64).next_multiple_of(align) as usize, 0);
+        let offset = out.len() as u64;
+        out.extend_from_slice(bytes);
+        offset
+    };
+    let text = place(&mut out, &object.text, 16);
+    let data = place(&mut out, &object.data, 16);
+    let symtab_offset = place(&mut out, &symtab, 8);
+    let strtab_offset = place(&mut out, &strtab, 1);
+    let rela_offset = place(&mut out, &rela, 8);
+    let shstrtab_offset = place(&mut out, &shstrtab, 1);
+    let shoff = place(&mut out, &[], 8);
+
+    let sections = [
+        Section::default(),
+        Section {
+            name: names[0],
+            kind: SHT_PROGBITS,
+            flags: SHF_ALLOC | SHF_EXECINSTR,
+            offset: text,
+            size: object.text.len() as u64,
+            align: 16,
+            ..Section::default()
+        },
+        Section {
+            name: names[1],
+            kind: SHT_PROGBITS,
+            flags: SHF_ALLOC | SHF_WRITE,
+            offset: data,
+            size: object.data.len() as u64,
+            align: 16,
+            ..Section::default()
+        },
+        Section {
+            name: names[2],
+            kind: SHT_NOBITS,
+            flags: SHF_ALLOC | SHF_WRITE,
+            offset: data + object.data.len() as u64,
+            size: object.bss_size,
+            align: 16,
+            ..Section::default()
+        },
+        Section {
+            name: names[3],
+            kind: SHT_SYMTAB,
+            offset: symtab_offset,
+            size: symtab.len() as u64,
+            link: 5,
+            info: first_global,
+            align: 8,
+            entsize: SYM_SIZE,
+            ..Section::default()
+        },
+        Section {
+            name: names[4],
+            kind: SHT_STRTAB,
+            offset: strtab_offset,
+            size: strtab.len() as u64,
+            align: 1,
+            ..Section::default()
+        },
+        Section {
+            name: names[5],
+            kind: SHT_RELA,
+            flags: SHF_INFO_LINK,
+            offset: rela_offset,
+            size: rela.len() as u64,
+            link: 4,
+            info: 1,
+            align: 8,
+            entsize: RELA_SIZE,
+            ..Section::default()
+        },
+        Section {
+            name: names[6],
+            kind: SHT_PROGBITS,
+            offset: shstrtab_offset,
+            align: 1,
+            ..Section::default()
+        },
+        Section {
+            name: names[7],
+            kind: SHT_STRTAB,
+            offset: shstrtab_offset,
+            size: shstrtab.len() as u64,
+            align: 1,
+            ..Section::default()
+        },
+    ];
+    let mut header = Vec::new();
+    file_header(&mut header, 1, 0, 0, shoff, sections.len() as u16);
+    out[..EHDR_SIZE as usize].copy_from_slice(&header);
+    for s in &sections {
+        section_header(&mut out, s);
+    }
+    out
+}
+
+/// Build a relocatable object (`.o`) from a solution, e.g. a `global` function to call from C.
+pub fn build_object(code: &str, syntax: Syntax) -> Result<Vec<u8>, String> {
+    let problems = emulator_only_input(code);
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+    Ok(write_object(&x86_asm::assemble_object(code, syntax)?))
+}
+
+/// One `PT_LOAD` segment of an executable.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub vaddr: u64,
+    pub memsz: u64,
+    /// `PF_R`/`PF_W`/`PF_X` bits.
+    pub flags: u32,
+    /// The file contents; the rest of `memsz` is zero-filled.
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    pub fn is_executable(&self) -> bool {
+        self.flags & PF_X != 0
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.flags & PF_W != 0
+    }
+
+    pub fn contains(&self, addr: u64) -> bool {
+        (self.vaddr..self.vaddr + self.memsz).contains(&addr)
+    }
+}
+
+/// What a loader needs from a static executable.
+#[derive(Debug, Clone)]
+pub struct Executable {
+    pub entry: u64,
+    pub segments: Vec<Segment>,
+    /// Address the program headers are loaded at, if a segment covers them (for `AT_PHDR`).
+    pub phdr_addr: Option<u64>,
+    pub phnum: u16,
+    /// Named `.symtab` entries (labels), unless the binary was stripped.
+    pub symbols: HashMap<String, u64>,
+}
+
+/// Little-endian field of `len` bytes at `at`.
+fn field(image: &[u8], at: usize, len: usize) -> Result<u64, String> {
+    let bytes = at
+        .checked_add(len)
+        .and_then(|end| image.get(at..end))
+        .ok_or_else(|| format!("ELF file is truncated at offset 0x{:x}", at))?;
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0, |acc, b| (acc << 8) | u64::from(*b)))
+}
+
+fn c_string(image: &[u8], at: usize) -> Option<String> {
+    let bytes = image.get(at..)?;
+    let end = bytes.iter().position(|b| *b == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+/// Defined function, object and untyped symbols of every `.symtab`.
+fn symbols(image: &[u8]) -> Result<HashMap<String, u64>, String> {
+    let mut out = HashMap::new();
+    let shoff = field(image, 40, 8)? as usize;
+    let shnum = field(image, 60, 2)? as usize;
+    if shoff == 0 {
+        return Ok(out);
+    }
+    let section = |i: usize| shoff + i * SHDR_SIZE as usize;
+    for i in 0..shnum {
+        let sh = section(i);
+        if field(image, sh + 4, 4)? as u32 != SHT_SYMTAB {
+            continue;
+        }
+        let (offset, size) = (field(image, sh + 24, 8)?, field(image, sh + 32, 8)?);
+        let strtab = field(image, section(field(image, sh + 40, 4)? as usize) + 24, 8)? as usize;
+        for sym in (offset..offset + size).step_by(24).map(|at| at as usize) {
+            let name = field(image, sym, 4)? as usize;
+            let kind = field(image, sym + 4, 1)? & 0xf;
+            let shndx = field(image, sym + 6, 2)?;
+            // STT_NOTYPE, STT_OBJECT and STT_FUNC; skip undefined symbols.
+            if name == 0 || kind > 2 || shndx == 0 {
+                continue;
+            }
+            if let Some(name) = c_string(image, strtab + name) {
+                out.insert(name, field(image, sym + 8, 8)?);
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Read a static x86-64 Linux executable: its entry point, loadable segments and symbols.
+pub fn parse_executable(image: &[u8]) -> Result<Executable, String> {
+    if !image.starts_with(b"\x7fELF") {
+        return Err("Not an ELF file".to_string());
+    }
+    if field(image, 4, 1)? != 2 || field(image, 5, 1)? != 1 {
+        return Err("Only 64-bit little-endian ELF files can be loaded".to_string());
+    }
+    if field(image, 18, 2)? != 0x3e {
+        return Err("Not an x86-64 executable".to_string());
+    }
+    if field(image, 16, 2)? != 2 {
+        return Err(
+            "Only static, non-PIE executables can be loaded (link with `ld`, not `gcc`)"
+                .to_string(),
+        );
+    }
+    let entry = field(image, 24, 8)?;
+    let phoff = field(image, 32, 8)?;
+    let phnum = field(image, 56, 2)? as u16;
+
+    let mut segments = Vec::new();
+    let mut phdr_addr = None;
+    for i in 0..phnum as usize {
+        let ph = phoff as usize + i * PHDR_SIZE as usize;
+        let kind = field(image, ph, 4)? as u32;
+        if kind == PT_INTERP {
+            return Err("Dynamically linked executables cannot be loaded".to_string());
+        }
+        if kind != PT_LOAD {
+            continue;
+        }
+        let offset = field(image, ph + 8, 8)?;
+        let vaddr = field(image, ph + 16, 8)?;
+        let (filesz, memsz) = (field(image, ph + 32, 8)?, field(image, ph + 40, 8)?);
+        if filesz > memsz {
+            return Err(format!(
+                "Segment at 0x{:x} has more file bytes than memory",
+                vaddr
+            ));
+        }
+        let data = image
+            .get(offset as usize..(offset + filesz) as usize)
+            .ok_or_else(|| format!("Segment at 0x{:x} lies outside the file", vaddr))?
+            .to_vec();
+        if (offset..offset + filesz).contains(&phoff) {
+            phdr_addr = Some(vaddr + phoff - offset);
+        }
+        segments.push(Segment {
+            vaddr,
+            memsz,
+            flags: field(image, ph + 4, 4)? as u32,
+            data,
+        });
+    }
+    if !segments
+        .iter()
+        .any(|s| s.is_executable() && s.contains(entry))
+    {
+        return Err(format!(
+            "Entry point 0x{:x} is not in an executable segment",
+            entry
+        ));
+    }
+
+    Ok(Executable {
+        entry,
+        segments,
+        phdr_addr,
+        phnum,
+        symbols: symbols(image)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicorn_engine::unicorn_const::{Arch, Mode, Prot, RegisterX86, X86Insn};
+    use unicorn_engine::Unicorn;
+
+    fn u16_at(b: &[u8], at: usize) -> u16 {
+        u16::from_le_bytes(b[at..at + 2].try_into().unwrap())
+    }
+
+    fn u64_at(b: &[u8], at: usize) -> u64 {
+        u64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+    }
+
+    /// Map the PT_LOAD segments of `elf` into a fresh Unicorn and run it with just `write` and
+    /// `exit`, the way the kernel would. Returns (stdout bytes, exit code).
+    fn run_in_unicorn(elf: &[u8]) -> (Vec<u8>, u64) {
+        let mut emu = Unicorn::new_with_data(Arch::X86, Mode::MODE_64, (Vec::new(), 0u64)).unwrap();
+        let (phoff, phnum) = (u64_at(elf, 32) as usize, u16_at(elf, 56) as usize);
+        for i in 0..phnum {
+            let ph = &elf[phoff + i * PHDR_SIZE as usize..];
+            let (offset, vaddr) = (u64_at(ph, 8) as usize, u64_at(ph, 16));
+            let (filesz, memsz) = (u64_at(ph, 32) as usize, u64_at(ph, 40));
+            emu.mem_map(vaddr, memsz.next_multiple_of(PAGE_SIZE), Prot::ALL)
+                .unwrap();
+            emu.mem_write(vaddr, &elf[offset..offset + filesz]).unwrap();
+        }
+        emu.mem_map(0x7000_0000, 0x10000, Prot::ALL).unwrap();
+        emu.reg_write(RegisterX86::RSP, 0x7000_f000).unwrap();
+        emu.add_insn_sys_hook(X86Insn::SYSCALL, 1, 0, |uc| {
+            let [rax, rdi, rsi, rdx] = [
+                RegisterX86::RAX,
+                RegisterX86::RDI,
+                RegisterX86::RSI,
+                RegisterX86::RDX,
+            ]
+            .map(|r| uc.reg_read(r).unwrap());
+            if rax == 1 {
+                let mut buf = vec![0u8; rdx as usize];
+                uc.mem_read(rsi, &mut buf).unwrap();
+                uc.get_data_mut().0.extend(buf);
+            } else {
+                uc.get_data_mut().1 = rdi;
+                uc.emu_stop().unwrap();
+            }
+        })
+        .unwrap();
+        emu.emu_start(u64_at(elf, 24), 0, 0, 10_000).unwrap();
+        emu.get_data().clone()
+    }
+
+    #[test]
+    fn image_has_loadable_text_and_bss() {
+        let elf = image(&[0x90, 0xc3], CODE_BASE + 1, 16);
+        assert_eq!(&elf[..4], b"\x7fELF");
+        assert_eq!(u64_at(&elf, 24), CODE_BASE + 1);
+        assert_eq!(u16_at(&elf, 56), 2);
+        let text = &elf[EHDR_SIZE as usize..];
+        assert_eq!(u64_at(text, 16), CODE_BASE);
+        assert_eq!(&elf[u64_at(text, 8) as usize..][..2], &[0x90, 0xc3]);
+        let bss = &elf[(EHDR_SIZE + PHDR_SIZE) as usize..];
+        assert_eq!(
+            (u64_at(bss, 16), u64_at(bss, 32), u64_at(bss, 40)),
+            (BSS_BASE, 0, 16)
+        );
+
+        assert_eq!(u16_at(&image(&[0xc3], CODE_BASE, 0), 56), 1);
+    }
+
+    #[test]
+    fn parses_its_own_images() {
+        let exe = parse_executable(&image(&[0x90, 0xc3], CODE_BASE + 1, 16)).unwrap();
+        assert_eq!(exe.entry, CODE_BASE + 1);
+        assert_eq!(exe.segments.len(), 2);
+        assert_eq!(exe.segments[0].data, vec![0x90, 0xc3]);
+        assert!(exe.segments[0].is_executable() && !exe.segments[0].is_writable());
+        assert_eq!(
+            (exe.segments[1].vaddr, exe.segments[1].memsz),
+            (BSS_BASE, 16)
+        );
+        assert!(exe.segments[1].data.is_empty());
+
+        assert_eq!(
+            parse_executable(b"#!/bin/sh").unwrap_err(),
+            "Not an ELF file"
+        );
+        let err = parse_executable(&image(&[0xc3], BSS_BASE, 16)).unwrap_err();
+        assert!(err.contains("not in an executable segment"), "{}", err);
+    }
+
+    #[test]
+    fn loaded_executable_sees_its_arguments() {
+        let text = [
+            0x48, 0x8b, 0x74, 0x24, 0x10, // mov rsi, [rsp + 16]   ; argv[1]
+            0xb8, 0x01, 0x00, 0x00, 0x00, // mov eax, 1
+            0xbf, 0x01, 0x00, 0x00, 0x00, // mov edi, 1
+            0xba, 0x01, 0x00, 0x00, 0x00, // mov edx, 1
+            0x0f, 0x05, // syscall
+            0x48, 0x8b, 0x3c, 0x24, // mov rdi, [rsp]        ; argc
+            0xb8, 0x3c, 0x00, 0x00, 0x00, // mov eax, 60
+            0x0f, 0x05, // syscall
+        ];
+        let args = ["prog".to_string(), "hi".to_string()];
+        let config = x86_runtime::RunConfig {
+            max_instructions: 100,
+            ..Default::default()
+        };
+        let run =
+            x86_runtime::run_elf(&image(&text, CODE_BASE, 0), &args, &[], vec![], &config).unwrap();
+        assert!(run.state.exited, "{:?}", run.state);
+        assert_eq!(run.state.output, vec![b'h' as i64]);
+        assert_eq!(run.state.registers[&crate::vm::Register::RDI], 2);
+        assert_eq!(run.state.instructions_executed, 8);
+    }
+
+    #[test]
+    fn rejects_emulator_only_input() {
+        let err = build_executable("_start:\n    in rax\n", Syntax::Intel).unwrap_err();
+        assert!(err.starts_with("line 2: `in`"), "{}", err);
+    }
+
+    #[test]
+    fn executable_behaves_like_the_runtime() {
+        let code = r#"
+section .bss
+    buf resb 4
+
+section .text
+    global _start
+
+_start:
+    mov byte [buf], 'H'
+    mov rcx, 2
+.again:
+    inc byte [buf]
+    loop .again
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 1
+    syscall
+    mov rax, 60
+    mov rdi, 7
+    syscall
+"#;
+        let elf = build_executable(code, Syntax::Intel).unwrap();
+        let (stdout, exit_code) = run_in_unicorn(&elf);
+        let expected = x86_runtime::run_x86_64(code, Syntax::Intel, vec![], 1_000).unwrap();
+        let expected_bytes: Vec<u8> = expected.state.output.iter().map(|v| *v as u8).collect();
+        assert_eq!(stdout, expected_bytes);
+        assert_eq!(stdout, b"J");
+        assert_eq!(exit_code, 7);
+    }
+
+    #[test]
+    fn object_parses_back_with_symbols_and_relocations() {
+        let code = r#"
+section .data
+    msg db "hi", 10
+
+section .bss
+    count resq 1
+
+section .text
+    global greet
+    extern puts
+
+greet:
+    lea rdi, [rel msg]
+    inc qword [rel count]
+    call puts
+    ret
+"#;
+        let obj = build_object(code, Syntax::Intel).unwrap();
+        assert_eq!(&obj[..4], b"\x7fELF");
+        assert_eq!(u16_at(&obj, 16), 1); // ET_REL
+
+        let shoff = u64_at(&obj, 40) as usize;
+        let shnum = u16_at(&obj, 60) as usize;
+        let header = |i: usize| &obj[shoff + i * SHDR_SIZE as usize..];
+        let shstrtab = u64_at(header(u16_at(&obj, 62) as usize), 24) as usize;
+        let name_at = |table: usize, at: usize| {
+            let bytes = &obj[table + at..];
+            String::from_utf8(bytes[..bytes.iter().position(|b| *b == 0).unwrap()].to_vec())
+                .unwrap()
+        };
+        let section = |name: &str| {
+            (0..shnum)
+                .map(header)
+                .find(|h| {
+                    name_at(
+                        shstrtab,
+                        u32::from_le_bytes(h[..4].try_into().unwrap()) as usize,
+                    ) == name
+                })
+                .unwrap_or_else(|| panic!("no {} section", name))
+        };
+        let contents = |name: &str| {
+            let h = section(name);
+            &obj[u64_at(h, 24) as usize..][..u64_at(h, 32) as usize]
+        };
+
+        assert_eq!(contents(".data"), b"hi\n");
+        assert_eq!(u64_at(section(".bss"), 32), 8);
+        let text = contents(".text");
+        assert_eq!(text.len(), 20);
+        assert_eq!(&text[..3], &[0x48, 0x8d, 0x3d]); // lea rdi, [rip + 0]
+
+        // (name, global, section index, value)
+        let strtab = u64_at(section(".strtab"), 24) as usize;
+        let symbols: Vec<(String, bool, u16, u64)> = contents(".symtab")
+            .chunks(SYM_SIZE as usize)
+            .map(|sym| {
+                let name = u32::from_le_bytes(sym[..4].try_into().unwrap()) as usize;
+                (
+                    name_at(strtab, name),
+                    sym[4] >> 4 == STB_GLOBAL,
+                    u16_at(sym, 6),
+                    u64_at(sym, 8),
+                )
+            })
+            .collect();
+        let symbol = |name: &str| symbols.iter().position(|s| s.0 == name).unwrap();
+        assert_eq!(symbols[symbol("greet")], ("greet".to_string(), true, 1, 0));
+        assert_eq!(symbols[symbol("puts")], ("puts".to_string(), true, 0, 0));
+        assert_eq!(symbols[symbol("msg")], ("msg".to_string(), false, 2, 0));
+        assert_eq!(symbols[symbol("count")], ("count".to_string(), false, 3, 0));
+        let first_global = u32::from_le_bytes(section(".symtab")[44..48].try_into().unwrap());
+        assert!(symbols[first_global as usize..].iter().all(|s| s.1));
+
+        // (offset, symbol, type, addend)
+        let relocations: Vec<(u64, String, u64, i64)> = contents(".rela.text")
+            .chunks(RELA_SIZE as usize)
+            .map(|r| {
+                let info = u64_at(r, 8);
+                let sym = symbols[(info >> 32) as usize].0.clone();
+                (u64_at(r, 0), sym, info & 0xffff_ffff, u64_at(r, 16) as i64)
+            })
+            .collect();
+        assert_eq!(
+            relocations,
+            vec![
+                (3, "msg".to_string(), 2, -4),
+                (10, "count".to_string(), 2, -4),
+                (15, "puts".to_string(), 4, -4),
+            ]
+        );
+    }
+}
@@ -0,0 +1,577 @@
+//! Machine-code view of a program: each instruction's address, bytes and source line, and a
+//! field-by-field explanation of how the bytes encode it.
+//!
+//! The decoder only needs to find field boundaries, so it knows the general-purpose one- and
+//! two-byte opcode maps and nothing about SSE/AVX. Bytes it cannot place are reported as
+//! [`FieldKind::Unknown`] rather than guessed.
+
+use crate::memory;
+use crate::vm::Syntax;
+use crate::x86_runtime;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum FieldKind {
+    Prefix,
+    Rex,
+    Opcode,
+    ModRm,
+    Sib,
+    Displacement,
+    Immediate,
+    Unknown,
+}
+
+/// A run of bytes with one role in an instruction.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EncodingField {
+    pub kind: FieldKind,
+    pub bytes: Vec<u8>,
+    /// e.g. `REX.W=1 (64-bit operand) R=0 X=0 B=0`, `mod=11 reg=000 (/0) rm=000 (rax)`.
+    pub detail: String,
+}
+
+/// One row of the machine-code panel.
+#[derive(Debug, Clone, Serialize)]
+pub struct MachineCodeLine {
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    /// Space-separated lowercase hex, e.g. `48 c7 c0 01 00 00 00`.
+    pub hex: String,
+    pub mnemonic: String,
+    /// Operands as assembled, labels already resolved to addresses.
+    pub operands: String,
+    /// 1-based source line; pseudo-instruction expansions share their line.
+    pub line: usize,
+    /// The source line as written, trimmed.
+    pub source: String,
+    pub encoding: Vec<EncodingField>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Imm {
+    None,
+    /// 8 bits
+    B,
+    /// 16 bits
+    W,
+    /// 16 or 32 bits by operand size
+    Z,
+    /// 16, 32 or 64 bits by operand size (`mov r64, imm64`)
+    V,
+}
+
+#[derive(Clone, Copy)]
+struct OpInfo {
+    modrm: bool,
+    imm: Imm,
+    /// Operates on 8-bit registers.
+    byte_op: bool,
+    /// ModRM.reg selects the operation (`/digit`) instead of a register.
+    group: bool,
+    /// Low three opcode bits select a register (`push r`, `mov r, imm`).
+    reg_in_opcode: bool,
+    /// The immediate is a branch displacement.
+    relative: bool,
+}
+
+const fn op(modrm: bool, imm: Imm) -> Option<OpInfo> {
+    Some(OpInfo {
+        modrm,
+        imm,
+        byte_op: false,
+        group: false,
+        reg_in_opcode: false,
+        relative: false,
+    })
+}
+
+fn byte_op(info: Option<OpInfo>) -> Option<OpInfo> {
+    info.map(|i| OpInfo { byte_op: true, ..i })
+}
+
+fn group(info: Option<OpInfo>) -> Option<OpInfo> {
+    info.map(|i| OpInfo { group: true, ..i })
+}
+
+fn reg_in_opcode(info: Option<OpInfo>) -> Option<OpInfo> {
+    info.map(|i| OpInfo {
+        reg_in_opcode: true,
+        ..i
+    })
+}
+
+fn relative(info: Option<OpInfo>) -> Option<OpInfo> {
+    info.map(|i| OpInfo {
+        relative: true,
+        ..i
+    })
+}
+
+/// One-byte opcode map (64-bit mode).
+fn one_byte(opcode: u8) -> Option<OpInfo> {
+    match opcode {
+        // add/or/adc/sbb/and/sub/xor/cmp in their six forms
+        0x00..=0x3f if opcode & 7 < 6 => match opcode & 7 {
+            0 | 2 => byte_op(op(true, Imm::None)),
+            1 | 3 => op(true, Imm::None),
+            4 => byte_op(op(false, Imm::B)),
+            _ => op(false, Imm::Z),
+        },
+        0x50..=0x5f => reg_in_opcode(op(false, Imm::None)),
+        0x63 => op(true, Imm::None),
+        0x68 => op(false, Imm::Z),
+        0x69 => op(true, Imm::Z),
+        0x6a => op(false, Imm::B),
+        0x6b => op(true, Imm::B),
+        0x70..=0x7f => relative(op(false, Imm::B)),
+        0x80 => group(byte_op(op(true, Imm::B))),
+        0x81 => group(op(true, Imm::Z)),
+        0x83 => group(op(true, Imm::B)),
+        0x84 | 0x86 | 0x88 | 0x8a => byte_op(op(true, Imm::None)),
+        0x85 | 0x87 | 0x89 | 0x8b | 0x8d => op(true, Imm::None),
+        0x8f => group(op(true, Imm::None)),
+        0x90..=0x97 => reg_in_opcode(op(false, Imm::None)),
+        0x98 | 0x99 | 0x9c | 0x9d => op(false, Imm::None),
+        0xa8 => byte_op(op(false, Imm::B)),
+        0xa9 => op(false, Imm::Z),
+        0xb0..=0xb7 => reg_in_opcode(byte_op(op(false, Imm::B))),
+        0xb8..=0xbf => reg_in_opcode(op(false, Imm::V)),
+        0xc0 => group(byte_op(op(true, Imm::B))),
+        0xc1 => group(op(true, Imm::B)),
+        0xc2 => op(false, Imm::W),
+        0xc3 | 0xc9 | 0xcc | 0xf4 | 0xf5 | 0xf8..=0xfd => op(false, Imm::None),
+        0xc6 => group(byte_op(op(true, Imm::B))),
+        0xc7 => group(op(true, Imm::Z)),
+        0xcd => op(false, Imm::B),
+        0xd0 | 0xd2 => group(byte_op(op(true, Imm::None))),
+        0xd1 | 0xd3 => group(op(true, Imm::None)),
+        0xe0..=0xe3 | 0xeb => relative(op(false, Imm::B)),
+        0xe4..=0xe7 => op(false, Imm::B),
+        0xe8 | 0xe9 => relative(op(false, Imm::Z)),
+        0xec..=0xef => op(false, Imm::None),
+        // test r/m, imm only for /0 and /1; patched in `explain`
+        0xf6 | 0xfe => group(byte_op(op(true, Imm::None))),
+        0xf7 | 0xff => group(op(true, Imm::None)),
+        _ => None,
+    }
+}
+
+/// Two-byte opcode map (after `0f`).
+fn two_byte(opcode: u8) -> Option<OpInfo> {
+    match opcode {
+        0x05 | 0x0b | 0xa2 => op(false, Imm::None),
+        0x1f => group(op(true, Imm::None)),
+        0x40..=0x4f | 0xa3 | 0xa5 | 0xab | 0xad | 0xaf | 0xb1 | 0xb3 | 0xb7 | 0xbb | 0xbf => {
+            op(true, Imm::None)
+        }
+        0xc1 => op(true, Imm::None),
+        0x80..=0x8f => relative(op(false, Imm::Z)),
+        0x90..=0x9f => byte_op(op(true, Imm::None)),
+        0xa4 | 0xac => op(true, Imm::B),
+        0xb0 | 0xc0 => byte_op(op(true, Imm::None)),
+        0xb6 | 0xbe => op(true, Imm::None),
+        0xba => group(op(true, Imm::B)),
+        0xc8..=0xcf => reg_in_opcode(op(false, Imm::None)),
+        _ => None,
+    }
+}
+
+fn register_name(number: u8, bits: u32, rex: bool) -> &'static str {
+    const R64: [&str; 16] = [
+        "rax", "rcx", "rdx", "rbx", "rsp", "rbp", "rsi", "rdi", "r8", "r9", "r10", "r11", "r12",
+        "r13", "r14", "r15",
+    ];
+    const R32: [&str; 16] = [
+        "eax", "ecx", "edx", "ebx", "esp", "ebp", "esi", "edi", "r8d", "r9d", "r10d", "r11d",
+        "r12d", "r13d", "r14d", "r15d",
+    ];
+    const R16: [&str; 16] = [
+        "ax", "cx", "dx", "bx", "sp", "bp", "si", "di", "r8w", "r9w", "r10w", "r11w", "r12w",
+        "r13w", "r14w", "r15w",
+    ];
+    const R8: [&str; 16] = [
+        "al", "cl", "dl", "bl", "spl", "bpl", "sil", "dil", "r8b", "r9b", "r10b", "r11b", "r12b",
+        "r13b", "r14b", "r15b",
+    ];
+    const R8_LEGACY: [&str; 4] = ["ah", "ch", "dh", "bh"];
+    let n = (number & 15) as usize;
+    match bits {
+        64 => R64[n],
+        32 => R32[n],
+        16 => R16[n],
+        _ if !rex && (4..8).contains(&n) => R8_LEGACY[n - 4],
+        _ => R8[n],
+    }
+}
+
+fn prefix_name(byte: u8) -> Option<&'static str> {
+    Some(match byte {
+        0x66 => "operand-size override (16-bit operands)",
+        0x67 => "address-size override (32-bit addresses)",
+        0xf0 => "lock",
+        0xf2 => "repne",
+        0xf3 => "rep/repe",
+        0x2e => "cs segment",
+        0x36 => "ss segment",
+        0x3e => "ds segment",
+        0x26 => "es segment",
+        0x64 => "fs segment",
+        0x65 => "gs segment",
+        _ => return None,
+    })
+}
+
+fn le_value(bytes: &[u8]) -> i64 {
+    // sign-extend from the field width
+    memory::sign_extend(memory::le_u64(bytes), 8 * bytes.len() as u32)
+}
+
+fn signed_hex(value: i64) -> String {
+    if value < 0 {
+        format!("-0x{:x}", value.unsigned_abs())
+    } else {
+        format!("0x{:x}", value)
+    }
+}
+
+/// Split one encoded instruction at `address` into prefix, REX, opcode, ModRM, SIB,
+/// displacement and immediate fields.
+pub fn explain(bytes: &[u8], address: u64) -> Vec<EncodingField> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    let take = |fields: &mut Vec<EncodingField>, pos: &mut usize, kind, len: usize, detail| {
+        fields.push(EncodingField {
+            kind,
+            bytes: bytes[*pos..*pos + len].to_vec(),
+            detail,
+        });
+        *pos += len;
+    };
+
+    let mut operand_16 = false;
+    while pos < bytes.len() {
+        let Some(name) = prefix_name(bytes[pos]) else {
+            break;
+        };
+        operand_16 |= bytes[pos] == 0x66;
+        take(
+            &mut fields,
+            &mut pos,
+            FieldKind::Prefix,
+            1,
+            name.to_string(),
+        );
+    }
+
+    let mut rex = 0u8;
+    if pos < bytes.len() && bytes[pos] & 0xf0 == 0x40 {
+        rex = bytes[pos];
+        let bit = |n: u8| (rex >> n) & 1;
+        let detail = format!(
+            "REX.W={}{} R={} X={} B={}",
+            bit(3),
+            if bit(3) == 1 { " (64-bit operand)" } else { "" },
+            bit(2),
+            bit(1),
+            bit(0)
+        );
+        take(&mut fields, &mut pos, FieldKind::Rex, 1, detail);
+    }
+    let (rex_w, rex_r, rex_x, rex_b) = (rex & 8 != 0, (rex >> 2) & 1, (rex >> 1) & 1, rex & 1);
+
+    let unknown = |fields: &mut Vec<EncodingField>, pos: usize| {
+        if pos < bytes.len() {
+            fields.push(EncodingField {
+                kind: FieldKind::Unknown,
+                bytes: bytes[pos..].to_vec(),
+                detail: "not decoded".to_string(),
+            });
+        }
+    };
+
+    let Some(&first) = bytes.get(pos) else {
+        return fields;
+    };
+    let (opcode_len, info) = if first == 0x0f {
+        match bytes.get(pos + 1) {
+            Some(&second) => (2, two_byte(second)),
+            None => (1, None),
+        }
+    } else {
+        (1, one_byte(first))
+    };
+    let Some(mut info) = info else {
+        unknown(&mut fields, pos);
+        return fields;
+    };
+    let last_opcode = bytes[pos + opcode_len - 1];
+    let bits = if info.byte_op {
+        8
+    } else if rex_w {
+        64
+    } else if operand_16 {
+        16
+    } else {
+        32
+    };
+
+    let mut detail = format!(
+        "opcode {}",
+        bytes[pos..pos + opcode_len]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+    if info.reg_in_opcode {
+        let n = (last_opcode & 7) | (rex_b << 3);
+        // push/pop are always 64-bit in long mode
+        let reg_bits = if matches!(first, 0x50..=0x5f) {
+            64
+        } else {
+            bits
+        };
+        detail.push_str(&format!(
+            " + {} in the low 3 bits ({})",
+            n & 7,
+            register_name(n, reg_bits, rex != 0)
+        ));
+    }
+    take(&mut fields, &mut pos, FieldKind::Opcode, opcode_len, detail);
+
+    if info.modrm {
+        let Some(&modrm) = bytes.get(pos) else {
+            unknown(&mut fields, pos);
+            return fields;
+        };
+        let (md, reg, rm) = (modrm >> 6, (modrm >> 3) & 7, modrm & 7);
+        if opcode_len == 1 && matches!(first, 0xf6 | 0xf7) {
+            // `test r/m, imm` is the only group-3 member with an immediate
+            if reg < 2 {
+                info.imm = if first == 0xf6 { Imm::B } else { Imm::Z };
+            }
+        }
+        let reg_text = if info.group {
+            format!("/{}", reg)
+        } else {
+            register_name(reg | (rex_r << 3), bits, rex != 0).to_string()
+        };
+        // movzx/movsx read a narrower source than the register they write
+        let rm_bits = match (opcode_len, last_opcode) {
+            (2, 0xb6 | 0xbe) => 8,
+            (2, 0xb7 | 0xbf) => 16,
+            _ => bits,
+        };
+        let rm_text = match (md, rm) {
+            (3, _) => register_name(rm | (rex_b << 3), rm_bits, rex != 0).to_string(),
+            (_, 4) => "memory, SIB follows".to_string(),
+            (0, 5) => "memory [rip + disp32]".to_string(),
+            _ => format!("memory [{}]", register_name(rm | (rex_b << 3), 64, true)),
+        };
+        take(
+            &mut fields,
+            &mut pos,
+            FieldKind::ModRm,
+            1,
+            format!(
+                "mod={:02b} reg={:03b} ({}) rm={:03b} ({})",
+                md, reg, reg_text, rm, rm_text
+            ),
+        );
+
+        let mut disp_len = match md {
+            1 => 1,
+            2 => 4,
+            0 if rm == 5 => 4,
+            _ => 0,
+        };
+        if md != 3 && rm == 4 {
+            let Some(&sib) = bytes.get(pos) else {
+                unknown(&mut fields, pos);
+                return fields;
+            };
+            let (scale, index, base) = (sib >> 6, (sib >> 3) & 7, sib & 7);
+            let index_text = match index | (rex_x << 3) {
+                4 => "none".to_string(),
+                n => register_name(n, 64, true).to_string(),
+            };
+            let base_text = if base == 5 && md == 0 {
+                disp_len = 4;
+                "none, disp32".to_string()
+            } else {
+                register_name(base | (rex_b << 3), 64, true).to_string()
+            };
+            take(
+                &mut fields,
+                &mut pos,
+                FieldKind::Sib,
+                1,
+                format!(
+                    "scale={} index={:03b} ({}) base={:03b} ({})",
+                    1 << scale,
+                    index,
+                    index_text,
+                    base,
+                    base_text
+                ),
+            );
+        }
+        if disp_len > 0 {
+            if pos + disp_len > bytes.len() {
+                unknown(&mut fields, pos);
+                return fields;
+            }
+            let value = le_value(&bytes[pos..pos + disp_len]);
+            take(
+                &mut fields,
+                &mut pos,
+                FieldKind::Displacement,
+                disp_len,
+                format!("disp{} = {}", disp_len * 8, signed_hex(value)),
+            );
+        }
+    }
+
+    let imm_len = match info.imm {
+        Imm::None => 0,
+        Imm::B => 1,
+        Imm::W => 2,
+        Imm::Z if operand_16 => 2,
+        Imm::Z => 4,
+        Imm::V if rex_w => 8,
+        Imm::V if operand_16 => 2,
+        Imm::V => 4,
+    };
+    if imm_len > 0 {
+        if pos + imm_len > bytes.len() {
+            unknown(&mut fields, pos);
+            return fields;
+        }
+        let value = le_value(&bytes[pos..pos + imm_len]);
+        let end = address.wrapping_add((pos + imm_len) as u64);
+        let detail = if info.relative {
+            format!(
+                "rel{} = {} (target 0x{:x})",
+                imm_len * 8,
+                signed_hex(value),
+                end.wrapping_add(value as u64)
+            )
+        } else {
+            format!("imm{} = {} ({})", imm_len * 8, value, signed_hex(value))
+        };
+        take(&mut fields, &mut pos, FieldKind::Immediate, imm_len, detail);
+    }
+
+    unknown(&mut fields, pos);
+    fields
+}
+
+/// Assemble `code` as the runtime would and describe every instruction's encoding.
+pub fn machine_code(code: &str, syntax: Syntax) -> Result<Vec<MachineCodeLine>, String> {
+    let assembled = x86_runtime::assemble_program(code, syntax)?;
+    let source: Vec<&str> = code.lines().collect();
+    Ok(assembled
+        .instructions
+        .iter()
+        .map(|inst| {
+            let bytes = assembled.bytes_of(inst).to_vec();
+            let text = inst.text.trim();
+            let (mnemonic, operands) = match text.split_once(char::is_whitespace) {
+                Some((m, ops)) => (m.to_string(), ops.trim().to_string()),
+                None => (text.to_string(), String::new()),
+            };
+            MachineCodeLine {
+                address: inst.address,
+                hex: bytes
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                encoding: explain(&bytes, inst.address),
+                bytes,
+                mnemonic,
+                operands,
+                line: inst.line,
+                source: source
+                    .get(inst.line - 1)
+                    .map(|l| l.trim().to_string())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(fields: &[EncodingField]) -> Vec<FieldKind> {
+        fields.iter().map(|f| f.kind).collect()
+    }
+
+    #[test]
+    fn explains_mov_register_immediate() {
+        let fields = explain(&[0x48, 0xc7, 0xc0, 0x01, 0x00, 0x00, 0x00], 0x100000);
+        assert_eq!(
+            kinds(&fields),
+            vec![
+                FieldKind::Rex,
+                FieldKind::Opcode,
+                FieldKind::ModRm,
+                FieldKind::Immediate
+            ]
+        );
+        assert_eq!(fields[0].detail, "REX.W=1 (64-bit operand) R=0 X=0 B=0");
+        assert_eq!(fields[2].detail, "mod=11 reg=000 (/0) rm=000 (rax)");
+        assert_eq!(fields[3].detail, "imm32 = 1 (0x1)");
+    }
+
+    #[test]
+    fn explains_sib_and_displacement() {
+        // mov al, [r15 + r8]
+        let fields = explain(&[0x43, 0x8a, 0x04, 0x07], 0);
+        assert_eq!(
+            fields[2].detail,
+            "mod=00 reg=000 (al) rm=100 (memory, SIB follows)"
+        );
+        assert_eq!(fields[3].detail, "scale=1 index=000 (r8) base=111 (r15)");
+
+        // mov [rbp - 8], rax
+        let fields = explain(&[0x48, 0x89, 0x45, 0xf8], 0);
+        assert_eq!(
+            fields[2].detail,
+            "mod=01 reg=000 (rax) rm=101 (memory [rbp])"
+        );
+        assert_eq!(fields[3].kind, FieldKind::Displacement);
+        assert_eq!(fields[3].detail, "disp8 = -0x8");
+    }
+
+    #[test]
+    fn explains_branches_and_register_opcodes() {
+        let fields = explain(&[0xeb, 0xfe], 0x100000);
+        assert_eq!(fields[1].detail, "rel8 = -0x2 (target 0x100000)");
+
+        let fields = explain(&[0x41, 0x50], 0);
+        assert_eq!(fields[1].detail, "opcode 50 + 0 in the low 3 bits (r8)");
+
+        assert_eq!(kinds(&explain(&[0x0f, 0x05], 0)), vec![FieldKind::Opcode]);
+        assert_eq!(
+            kinds(&explain(&[0x0f, 0x10, 0xc1], 0)),
+            vec![FieldKind::Unknown]
+        );
+    }
+
+    #[test]
+    fn sizes_the_source_of_movzx_and_movsx() {
+        // movzx eax, bl
+        let fields = explain(&[0x0f, 0xb6, 0xc3], 0);
+        assert_eq!(fields[1].detail, "mod=11 reg=000 (eax) rm=011 (bl)");
+        // movsx rax, cx
+        let fields = explain(&[0x48, 0x0f, 0xbf, 0xc1], 0);
+        assert_eq!(fields[2].detail, "mod=11 reg=000 (rax) rm=001 (cx)");
+        // movzx esi, sil
+        let fields = explain(&[0x40, 0x0f, 0xb6, 0xf6], 0);
+        assert_eq!(fields[2].detail, "mod=11 reg=110 (esi) rm=110 (sil)");
+    }
+}
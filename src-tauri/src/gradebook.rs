@@ -0,0 +1,382 @@
+use crate::grader::SolutionMetrics;
+use crate::levels;
+use crate::report::{self, LevelReport, RunOptions};
+use crate::vm::Syntax;
+use crate::x86_asm;
+use crate::x86_runtime::{self, RunLimits};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submission {
+    pub student: String,
+    pub level_id: String,
+    pub path: PathBuf,
+}
+
+fn is_asm(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("asm"))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// Collect submissions laid out as `<root>/<student>/<level_id>.asm` or
+/// `<root>/<student>/<level_id>/<any>.asm`.
+pub fn discover(root: &Path) -> Result<Vec<Submission>, String> {
+    let mut out = Vec::new();
+    for student_dir in sorted_entries(root)? {
+        if !student_dir.is_dir() {
+            continue;
+        }
+        let student = file_name(&student_dir);
+        for entry in sorted_entries(&student_dir)? {
+            if entry.is_dir() {
+                let level_id = file_name(&entry);
+                for file in sorted_entries(&entry)? {
+                    if file.is_file() && is_asm(&file) {
+                        out.push(Submission {
+                            student: student.clone(),
+                            level_id: level_id.clone(),
+                            path: file,
+                        });
+                    }
+                }
+            } else if is_asm(&entry) {
+                let level_id = entry
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                out.push(Submission {
+                    student: student.clone(),
+                    level_id,
+                    path: entry,
+                });
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Read a JSON manifest of the form `{"<student>": {"<level_id>": "<path>"}}`.
+/// Relative paths are resolved against the manifest's directory.
+pub fn read_manifest(path: &Path) -> Result<Vec<Submission>, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+    let manifest: BTreeMap<String, BTreeMap<String, PathBuf>> = serde_json::from_str(&text)
+        .map_err(|e| format!("Invalid manifest {}: {}", path.display(), e))?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    Ok(manifest
+        .into_iter()
+        .flat_map(|(student, by_level)| {
+            by_level
+                .into_iter()
+                .map(move |(level_id, file)| Submission {
+                    student: student.clone(),
+                    level_id,
+                    path: base.join(file),
+                })
+        })
+        .collect())
+}
+
+/// One gradebook line per submission.
+#[derive(Debug, Clone, Serialize)]
+pub struct GradeRow {
+    pub student: String,
+    pub level_id: String,
+    pub file: String,
+    pub syntax: Option<Syntax>,
+    pub passed: bool,
+    pub cases_passed: usize,
+    pub cases_total: usize,
+    pub metrics: Option<SolutionMetrics>,
+    /// Everything that went wrong; joined with `; ` in the CSV.
+    pub errors: Vec<String>,
+}
+
+impl GradeRow {
+    fn failed(submission: &Submission, syntax: Option<Syntax>, error: String) -> GradeRow {
+        GradeRow {
+            student: submission.student.clone(),
+            level_id: submission.level_id.clone(),
+            file: submission.path.display().to_string(),
+            syntax,
+            passed: false,
+            cases_passed: 0,
+            cases_total: 0,
+            metrics: None,
+            errors: vec![error],
+        }
+    }
+
+    fn from_report(submission: &Submission, report: &LevelReport) -> GradeRow {
+        let mut errors = report.constraint_violations.clone();
+        for case in report.cases.iter().filter(|c| !c.passed) {
+            errors.push(match &case.error {
+                Some(e) => format!("case {}: {}", case.name, e),
+                None => format!("case {}: wrong output", case.name),
+            });
+        }
+        if let Some(generated) = &report.generated {
+            if let Some(e) = &generated.error {
+                errors.push(format!("generated: {}", e));
+            } else if let Some(f) = &generated.failure {
+                errors.push(format!("generated: input {:?}", f.minimal.input));
+            }
+        }
+        if report.hardcoded {
+            errors.push("output does not depend on input".to_string());
+        }
+        GradeRow {
+            student: submission.student.clone(),
+            level_id: submission.level_id.clone(),
+            file: submission.path.display().to_string(),
+            syntax: Some(report.syntax.clone()),
+            passed: report.passed(),
+            cases_passed: report.cases.len() - report.failed_cases(),
+            cases_total: report.cases.len(),
+            metrics: report.metrics.clone(),
+            errors,
+        }
+    }
+}
+
+fn grade_one(submission: &Submission, options: &RunOptions, timeout: Duration) -> GradeRow {
+    let Some(level) = levels::get_level(&submission.level_id) else {
+        return GradeRow::failed(
+            submission,
+            None,
+            format!("Unknown level id: {}", submission.level_id),
+        );
+    };
+    let code = match fs::read_to_string(&submission.path) {
+        Ok(code) => code,
+        Err(e) => return GradeRow::failed(submission, None, format!("Failed to read file: {}", e)),
+    };
+    let syntax = x86_asm::detect_syntax(&code);
+
+    // Every run of the submission stops at the deadline; the channel timeout is a backstop
+    // for time spent outside the emulator.
+    let (tx, rx) = mpsc::channel();
+    let options = options.clone();
+    let run_syntax = syntax.clone();
+    let limits = RunLimits {
+        deadline: Some(Instant::now() + timeout),
+        ..RunLimits::default()
+    };
+    let budget = limits.clone();
+    thread::spawn(move || {
+        let _ = tx.send(x86_runtime::with_limits(limits, || {
+            report::evaluate(&code, &run_syntax, &level, &options)
+        }));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(report) if !budget.has_timed_out() => GradeRow::from_report(submission, &report),
+        Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => GradeRow::failed(
+            submission,
+            Some(syntax),
+            format!("Timed out after {} ms", timeout.as_millis()),
+        ),
+        Err(mpsc::RecvTimeoutError::Disconnected) => {
+            GradeRow::failed(submission, Some(syntax), "Grader crashed".to_string())
+        }
+    }
+}
+
+/// Grade every submission on `jobs` worker threads. Rows come back in submission order.
+pub fn grade_all(
+    submissions: &[Submission],
+    options: &RunOptions,
+    jobs: usize,
+    timeout: Duration,
+) -> Vec<GradeRow> {
+    let next = Mutex::new(0usize);
+    let rows = Mutex::new(vec![None; submissions.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.max(1) {
+            scope.spawn(|| loop {
+                let index = {
+                    let mut next = next.lock().unwrap();
+                    *next += 1;
+                    *next - 1
+                };
+                let Some(submission) = submissions.get(index) else {
+                    break;
+                };
+                let row = grade_one(submission, options, timeout);
+                rows.lock().unwrap()[index] = Some(row);
+            });
+        }
+    });
+
+    rows.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+pub fn to_csv(rows: &[GradeRow]) -> String {
+    let mut out = String::from(
+        "student,level_id,file,syntax,passed,cases_passed,cases_total,instructions_executed,code_bytes,source_lines,registers_used,errors\n",
+    );
+    for row in rows {
+        let metric = |f: fn(&SolutionMetrics) -> usize| {
+            row.metrics
+                .as_ref()
+                .map(|m| f(m).to_string())
+                .unwrap_or_default()
+        };
+        let fields = [
+            row.student.clone(),
+            row.level_id.clone(),
+            row.file.clone(),
+            row.syntax
+                .as_ref()
+                .map(|s| format!("{:?}", s))
+                .unwrap_or_default(),
+            row.passed.to_string(),
+            row.cases_passed.to_string(),
+            row.cases_total.to_string(),
+            metric(|m| m.instructions_executed),
+            metric(|m| m.code_bytes),
+            metric(|m| m.source_lines),
+            metric(|m| m.registers_used),
+            row.errors.join("; "),
+        ];
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&line.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn to_json(rows: &[GradeRow]) -> String {
+    serde_json::to_string_pretty(rows).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gradebook-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn discovers_files_and_level_directories() {
+        let root = scratch_dir("discover");
+        fs::create_dir_all(root.join("alice/03_Subtraction")).unwrap();
+        fs::create_dir_all(root.join("bob")).unwrap();
+        fs::write(root.join("alice/02_Addition.asm"), "").unwrap();
+        fs::write(root.join("alice/03_Subtraction/try1.asm"), "").unwrap();
+        fs::write(root.join("alice/notes.txt"), "").unwrap();
+        fs::write(root.join("bob/02_Addition.asm"), "").unwrap();
+
+        let found: Vec<(String, String)> = discover(&root)
+            .unwrap()
+            .into_iter()
+            .map(|s| (s.student, s.level_id))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("alice".to_string(), "02_Addition".to_string()),
+                ("alice".to_string(), "03_Subtraction".to_string()),
+                ("bob".to_string(), "02_Addition".to_string()),
+            ]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn manifest_paths_are_relative_to_the_manifest() {
+        let root = scratch_dir("manifest");
+        let manifest = root.join("manifest.json");
+        fs::write(&manifest, r#"{"carol": {"02_Addition": "subs/c.asm"}}"#).unwrap();
+
+        let subs = read_manifest(&manifest).unwrap();
+        assert_eq!(
+            subs,
+            vec![Submission {
+                student: "carol".to_string(),
+                level_id: "02_Addition".to_string(),
+                path: root.join("subs/c.asm"),
+            }]
+        );
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn unknown_levels_and_missing_files_are_reported_per_row() {
+        let subs = vec![
+            Submission {
+                student: "dave".to_string(),
+                level_id: "99_Nope".to_string(),
+                path: PathBuf::from("x.asm"),
+            },
+            Submission {
+                student: "dave".to_string(),
+                level_id: "02_Addition".to_string(),
+                path: PathBuf::from("/nonexistent/x.asm"),
+            },
+        ];
+        let rows = grade_all(&subs, &RunOptions::default(), 2, Duration::from_secs(5));
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].errors[0].starts_with("Unknown level id"));
+        assert!(rows[1].errors[0].starts_with("Failed to read file"));
+        assert!(rows.iter().all(|r| !r.passed));
+    }
+
+    #[test]
+    fn csv_quotes_fields_with_commas() {
+        let row = GradeRow {
+            student: "eve".to_string(),
+            level_id: "02_Addition".to_string(),
+            file: "a.asm".to_string(),
+            syntax: Some(Syntax::Intel),
+            passed: false,
+            cases_passed: 1,
+            cases_total: 3,
+            metrics: None,
+            errors: vec![
+                "case 2: wrong output".to_string(),
+                "say \"hi\", ok".to_string(),
+            ],
+        };
+        let csv = to_csv(&[row]);
+        let line = csv.lines().nth(1).unwrap();
+        assert_eq!(
+            line,
+            "eve,02_Addition,a.asm,Intel,false,1,3,,,,,\"case 2: wrong output; say \"\"hi\"\", ok\""
+        );
+    }
+}
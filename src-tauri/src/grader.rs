@@ -0,0 +1,856 @@
+use crate::levels::{self, Constraints, InputGenerator, Level, Oracle, PropertyTest};
+use crate::vm::{Register, Syntax, VmState};
+use crate::x86_asm;
+use crate::x86_runtime;
+use serde::{Deserialize, Serialize};
+
+/// Number of generated cases checked per level when a caller has no opinion.
+pub const DEFAULT_PROPERTY_CASES: usize = 200;
+/// Fixed seed so that a failure reported by the app can be replayed from the CLI.
+pub const DEFAULT_SEED: u64 = 0x0C0D_E10C_1C5E_ED00;
+/// Instructions a graded run may execute in the app. Player and reference metrics are both
+/// measured under it.
+pub const MAX_INSTRUCTIONS: usize = 20_000;
+
+const MAX_SHRINK_STEPS: usize = 500;
+
+pub struct CaseResult {
+    pub input: Vec<i64>,
+    pub expected: Vec<i64>,
+    /// What the grader compared against `expected` (stream output, or RAX as a fallback).
+    pub got: Vec<i64>,
+    pub passed: bool,
+    pub state: VmState,
+    pub execution_log: Vec<String>,
+}
+
+/// The value a program "returned": its output stream, or RAX if nothing was written.
+pub fn observed_output(state: &VmState, expected: &[i64]) -> Vec<i64> {
+    if !state.output.is_empty() {
+        return state.output.clone();
+    }
+    let rax = *state.registers.get(&Register::RAX).unwrap_or(&0);
+    if state.exited && rax == 60 && expected.len() == 1 && expected[0] != 60 {
+        // RAX still holds the exit syscall number; it is not a result.
+        vec![]
+    } else {
+        vec![rax]
+    }
+}
+
+pub fn output_matches(state: &VmState, expected: &[i64]) -> bool {
+    let rax = *state.registers.get(&Register::RAX).unwrap_or(&0);
+    if expected.is_empty() {
+        state.output.is_empty()
+    } else if !state.output.is_empty() {
+        // Prioritize Stream check if output was produced
+        state.output.len() >= expected.len() && &state.output[0..expected.len()] == expected
+    } else if expected.len() == 1 {
+        // Fallback to RAX if no stream output was produced but we expect 1 value
+        if state.exited && rax == 60 && expected[0] != 60 {
+            false
+        } else {
+            rax == expected[0]
+        }
+    } else {
+        false
+    }
+}
+
+/// Run one case. `Err` means the program could not be run at all (e.g. it did not assemble);
+/// runtime faults are reported through `state.error` and fail the case.
+pub fn grade_case(
+    code: &str,
+    syntax: &Syntax,
+    input: &[i64],
+    expected: &[i64],
+    max_instructions: usize,
+) -> Result<CaseResult, String> {
+    let run = x86_runtime::run_x86_64(code, syntax.clone(), input.to_vec(), max_instructions)?;
+    let state = run.state;
+    let passed = state.error.is_none() && output_matches(&state, expected);
+    Ok(CaseResult {
+        input: input.to_vec(),
+        expected: expected.to_vec(),
+        got: observed_output(&state, expected),
+        passed,
+        state,
+        execution_log: run.execution_log,
+    })
+}
+
+fn mnemonic_matches(written: &str, rule: &str, syntax: &Syntax) -> bool {
+    let rule = rule.to_lowercase();
+    if written == rule {
+        return true;
+    }
+    match syntax {
+        Syntax::Att => written
+            .strip_prefix(rule.as_str())
+            .is_some_and(|suffix| ["b", "w", "l", "q"].contains(&suffix)),
+        Syntax::Intel => false,
+    }
+}
+
+/// Whether `insts[i]` does something, so it can meet a `required_mnemonics` rule. Idioms any
+/// program has do not: zeroing a register with itself (the `xor rdi, rdi` before `exit`), or a
+/// `push` the next instruction pops straight back.
+fn does_work(insts: &[x86_asm::SourceInstruction], i: usize, syntax: &Syntax) -> bool {
+    let inst = &insts[i];
+    let is = |inst: &x86_asm::SourceInstruction, rule: &str| {
+        mnemonic_matches(&inst.mnemonic, rule, syntax)
+    };
+    let undone_by = |other: Option<&x86_asm::SourceInstruction>, rule: &str| {
+        other.is_some_and(|o| is(o, rule) && o.operands.eq_ignore_ascii_case(&inst.operands))
+    };
+    let operands = x86_asm::split_operands(&inst.operands);
+    let zeroing = (is(inst, "xor") || is(inst, "sub"))
+        && operands.len() == 2
+        && operands[0].eq_ignore_ascii_case(operands[1]);
+    let popped_back = is(inst, "push") && undone_by(insts.get(i + 1), "pop");
+    let pushed_back = is(inst, "pop") && undone_by(i.checked_sub(1).map(|p| &insts[p]), "push");
+    !(zeroing || popped_back || pushed_back)
+}
+
+/// Check the parts of a level's constraints that do not need a test case.
+/// Returns one message per violation; `Err` only if the program had to be assembled and could not be.
+pub fn check_static_constraints(
+    code: &str,
+    syntax: &Syntax,
+    constraints: &Constraints,
+) -> Result<Vec<String>, String> {
+    let mut violations = Vec::new();
+    let insts = x86_asm::source_instructions(code);
+
+    for inst in &insts {
+        let allowed = constraints.allowed_mnemonics.as_ref().is_none_or(|list| {
+            list.iter()
+                .any(|m| mnemonic_matches(&inst.mnemonic, m, syntax))
+        });
+        let denied = constraints
+            .denied_mnemonics
+            .iter()
+            .any(|m| mnemonic_matches(&inst.mnemonic, m, syntax));
+        if !allowed || denied {
+            violations.push(format!(
+                "`{}` is not allowed in this level (line {})",
+                inst.mnemonic, inst.line
+            ));
+        }
+    }
+
+    for required in &constraints.required_mnemonics {
+        if !(0..insts.len()).any(|i| {
+            mnemonic_matches(&insts[i].mnemonic, required, syntax) && does_work(&insts, i, syntax)
+        }) {
+            violations.push(format!("`{}` must be used at least once", required));
+        }
+    }
+
+    if let Some(max) = constraints.max_source_lines {
+        if insts.len() > max {
+            violations.push(format!(
+                "Program has {} instruction lines (limit {})",
+                insts.len(),
+                max
+            ));
+        }
+    }
+
+    if let Some(max) = constraints.max_code_bytes {
+        let size = x86_runtime::assemble_program(code, syntax.clone())?
+            .bytes
+            .len();
+        if size > max {
+            violations.push(format!(
+                "Program assembles to {} bytes (limit {})",
+                size, max
+            ));
+        }
+    }
+
+    Ok(violations)
+}
+
+/// The dynamic part of a level's constraints, checked per graded case.
+pub fn instruction_limit_violation(constraints: &Constraints, case: &CaseResult) -> Option<String> {
+    let max = constraints.max_instructions_executed?;
+    let executed = case.state.instructions_executed;
+    (executed > max).then(|| format!("Executed {} instructions (limit {})", executed, max))
+}
+
+/// splitmix64: tiny, seedable and good enough to pick test inputs.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform-ish value in `0..n` (`n` must be non-zero).
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    fn len_between(&mut self, min: usize, max: usize) -> usize {
+        min + self.below((max.saturating_sub(min) + 1) as u64) as usize
+    }
+}
+
+// Boundaries that hard-coded or off-by-one solutions tend to get wrong.
+const EDGE_BYTES: &[i64] = &[
+    0, 1, -1, 127, -128, 0x20, 0x2f, 0x30, 0x39, 0x3a, 0x40, 0x41, 0x5a, 0x5b, 0x60, 0x61, 0x7a,
+    0x7b,
+];
+
+fn random_byte(rng: &mut Rng) -> i64 {
+    if rng.below(3) == 0 {
+        EDGE_BYTES[rng.below(EDGE_BYTES.len() as u64) as usize]
+    } else {
+        (rng.below(256) as u8 as i8) as i64
+    }
+}
+
+pub fn generate_input(generator: &InputGenerator, rng: &mut Rng) -> Vec<i64> {
+    match generator {
+        InputGenerator::Bytes { min_len, max_len } => {
+            let len = rng.len_between(*min_len, *max_len);
+            (0..len).map(|_| random_byte(rng)).collect()
+        }
+        InputGenerator::Digits { len } => (0..*len)
+            .map(|_| '0' as i64 + rng.below(10) as i64)
+            .collect(),
+        InputGenerator::ZeroTerminated { min_len, max_len } => {
+            let len = rng.len_between(*min_len, *max_len);
+            let mut out: Vec<i64> = (0..len)
+                .map(|_| match random_byte(rng) {
+                    0 => 1,
+                    b => b,
+                })
+                .collect();
+            out.push(0);
+            out
+        }
+    }
+}
+
+/// Smaller neighbours of `input` that still satisfy the generator's shape.
+pub fn shrink_candidates(generator: &InputGenerator, input: &[i64]) -> Vec<Vec<i64>> {
+    let mut out = Vec::new();
+    match generator {
+        InputGenerator::Bytes { min_len, .. } => {
+            if input.len() > *min_len {
+                for i in 0..input.len() {
+                    let mut c = input.to_vec();
+                    c.remove(i);
+                    out.push(c);
+                }
+            }
+            for (i, &v) in input.iter().enumerate() {
+                for simpler in [0, v / 2] {
+                    if simpler != v {
+                        let mut c = input.to_vec();
+                        c[i] = simpler;
+                        out.push(c);
+                    }
+                }
+            }
+        }
+        InputGenerator::Digits { .. } => {
+            for (i, &v) in input.iter().enumerate() {
+                if v > '0' as i64 {
+                    for simpler in ['0' as i64, v - 1] {
+                        let mut c = input.to_vec();
+                        c[i] = simpler;
+                        out.push(c);
+                    }
+                }
+            }
+        }
+        InputGenerator::ZeroTerminated { min_len, .. } => {
+            let body = &input[..input.len().saturating_sub(1)];
+            if body.len() > *min_len {
+                for i in 0..body.len() {
+                    let mut c = input.to_vec();
+                    c.remove(i);
+                    out.push(c);
+                }
+            }
+            for (i, &v) in body.iter().enumerate() {
+                for simpler in [1, v / 2] {
+                    if simpler != v && simpler != 0 {
+                        let mut c = input.to_vec();
+                        c[i] = simpler;
+                        out.push(c);
+                    }
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Greedily shrink a failing input while `fails` keeps reporting a failure.
+pub fn shrink<F>(
+    generator: &InputGenerator,
+    input: &[i64],
+    mut fails: F,
+) -> Result<Vec<i64>, String>
+where
+    F: FnMut(&[i64]) -> Result<bool, String>,
+{
+    let mut current = input.to_vec();
+    let mut steps = 0usize;
+    'outer: while steps < MAX_SHRINK_STEPS {
+        for candidate in shrink_candidates(generator, &current) {
+            steps += 1;
+            if fails(&candidate)? {
+                current = candidate;
+                continue 'outer;
+            }
+            if steps >= MAX_SHRINK_STEPS {
+                break 'outer;
+            }
+        }
+        break;
+    }
+    Ok(current)
+}
+
+pub struct PropertyFailure {
+    pub seed: u64,
+    /// 0-based index of the first generated case that failed.
+    pub case_index: usize,
+    pub original_input: Vec<i64>,
+    /// The shrunk failing case.
+    pub minimal: CaseResult,
+}
+
+pub struct PropertyReport {
+    pub cases_run: usize,
+    pub failure: Option<PropertyFailure>,
+}
+
+struct Expectation<'a> {
+    oracle: &'a Oracle,
+    reference: Option<String>,
+    max_instructions: usize,
+}
+
+impl Expectation<'_> {
+    fn expected_for(&self, input: &[i64]) -> Result<Vec<i64>, String> {
+        match self.oracle {
+            Oracle::Model(model) => Ok(model(input)),
+            Oracle::ReferenceSolution => {
+                let code = self.reference.as_deref().unwrap_or_default();
+                let run = x86_runtime::run_x86_64(
+                    code,
+                    Syntax::Intel,
+                    input.to_vec(),
+                    self.max_instructions,
+                )?;
+                if let Some(err) = run.state.error {
+                    return Err(format!(
+                        "Reference solution failed for input {:?}: {}",
+                        input, err
+                    ));
+                }
+                Ok(run.state.output)
+            }
+        }
+    }
+}
+
+/// Run `cases` seeded random inputs from the level's generator against its oracle.
+/// Levels without a `property` report zero cases run.
+pub fn check_property(
+    code: &str,
+    syntax: &Syntax,
+    level: &Level,
+    cases: usize,
+    seed: u64,
+    max_instructions: usize,
+) -> Result<PropertyReport, String> {
+    let Some(PropertyTest { generator, oracle }) = &level.property else {
+        return Ok(PropertyReport {
+            cases_run: 0,
+            failure: None,
+        });
+    };
+    let expectation = Expectation {
+        oracle,
+        reference: match oracle {
+            Oracle::ReferenceSolution => Some(levels::reference_solution(&level.id)?),
+            Oracle::Model(_) => None,
+        },
+        max_instructions,
+    };
+
+    let mut rng = Rng::new(seed);
+    for case_index in 0..cases {
+        let input = generate_input(generator, &mut rng);
+        let expected = expectation.expected_for(&input)?;
+        let result = grade_case(code, syntax, &input, &expected, max_instructions)?;
+        if result.passed {
+            continue;
+        }
+
+        let minimal_input = shrink(generator, &input, |candidate| {
+            let expected = expectation.expected_for(candidate)?;
+            Ok(!grade_case(code, syntax, candidate, &expected, max_instructions)?.passed)
+        })?;
+        let expected = expectation.expected_for(&minimal_input)?;
+        let minimal = grade_case(code, syntax, &minimal_input, &expected, max_instructions)?;
+        return Ok(PropertyReport {
+            cases_run: case_index + 1,
+            failure: Some(PropertyFailure {
+                seed,
+                case_index,
+                original_input: input,
+                minimal,
+            }),
+        });
+    }
+
+    Ok(PropertyReport {
+        cases_run: cases,
+        failure: None,
+    })
+}
+
+/// Scores for a passing solution, lower is better on every axis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolutionMetrics {
+    /// Executed instructions summed over every graded case (visible and hidden).
+    pub instructions_executed: usize,
+    /// Size of the assembled `.text`, pseudo-instructions expanded.
+    pub code_bytes: usize,
+    /// Instruction lines in the source.
+    pub source_lines: usize,
+    /// Distinct general-purpose registers named in the source.
+    pub registers_used: usize,
+}
+
+/// Measure a solution on `level`. Only meaningful once the solution passes; failing cases still
+/// contribute whatever they executed.
+pub fn measure(
+    code: &str,
+    syntax: &Syntax,
+    level: &Level,
+    max_instructions: usize,
+) -> Result<SolutionMetrics, String> {
+    let mut instructions_executed = 0;
+    for (input, expected, _) in level.graded_cases() {
+        let case = grade_case(code, syntax, input, expected, max_instructions)?;
+        instructions_executed += case.state.instructions_executed;
+    }
+    Ok(SolutionMetrics {
+        instructions_executed,
+        code_bytes: x86_runtime::assemble_program(code, syntax.clone())?
+            .bytes
+            .len(),
+        source_lines: x86_asm::source_instructions(code).len(),
+        registers_used: x86_asm::registers_used(code).len(),
+    })
+}
+
+/// Metrics of the stage's reference solutions, the range players compare against. Only the
+/// Intel files are measured: `collect_Att.asm` is `collect.asm` again.
+pub fn reference_metrics(
+    level: &Level,
+    max_instructions: usize,
+) -> Result<Vec<SolutionMetrics>, String> {
+    levels::reference_solutions(&level.id)?
+        .iter()
+        .filter(|(syntax, _)| matches!(syntax, Syntax::Intel))
+        .map(|(syntax, code)| measure(code, syntax, level, max_instructions))
+        .collect()
+}
+
+/// Number of extra inputs tried by [`output_ignores_input`].
+const INDEPENDENCE_PROBES: usize = 8;
+
+/// Heuristic against hard-coded answers: run the program on the level's own inputs plus a few
+/// random ones and report whether it printed the same thing every time. Levels whose graded
+/// cases all expect the same output are never flagged.
+pub fn output_ignores_input(
+    code: &str,
+    syntax: &Syntax,
+    level: &Level,
+    seed: u64,
+    max_instructions: usize,
+) -> Result<bool, String> {
+    let mut expected_outputs = level.graded_cases().map(|(_, expected, _)| expected);
+    let Some(first_expected) = expected_outputs.next() else {
+        return Ok(false);
+    };
+    if expected_outputs.all(|e| e == first_expected) {
+        return Ok(false);
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut probes: Vec<Vec<i64>> = level
+        .graded_cases()
+        .map(|(input, _, _)| input.clone())
+        .collect();
+    let graded = probes.len();
+    if graded == 0 {
+        return Ok(false);
+    }
+    for i in 0..INDEPENDENCE_PROBES {
+        let probe = match &level.property {
+            Some(PropertyTest { generator, .. }) => generate_input(generator, &mut rng),
+            None => {
+                // Keep zeros (terminators) in place and scramble everything else.
+                probes[i % graded]
+                    .iter()
+                    .map(|&v| match v {
+                        0 => 0,
+                        _ => match random_byte(&mut rng) {
+                            0 => 1,
+                            b => b,
+                        },
+                    })
+                    .collect()
+            }
+        };
+        probes.push(probe);
+    }
+
+    let mut first: Option<Vec<i64>> = None;
+    for input in &probes {
+        let run = x86_runtime::run_x86_64(code, syntax.clone(), input.clone(), max_instructions)?;
+        let got = observed_output(&run.state, &[]);
+        match &first {
+            None => first = Some(got),
+            Some(prev) if *prev != got => return Ok(false),
+            Some(_) => {}
+        }
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_deterministic_per_seed() {
+        let a: Vec<u64> = {
+            let mut r = Rng::new(7);
+            (0..4).map(|_| r.next_u64()).collect()
+        };
+        let b: Vec<u64> = {
+            let mut r = Rng::new(7);
+            (0..4).map(|_| r.next_u64()).collect()
+        };
+        assert_eq!(a, b);
+        assert_ne!(a, {
+            let mut r = Rng::new(8);
+            (0..4).map(|_| r.next_u64()).collect::<Vec<_>>()
+        });
+    }
+
+    #[test]
+    fn generators_respect_their_shape() {
+        let mut rng = Rng::new(DEFAULT_SEED);
+        for _ in 0..500 {
+            let bytes = generate_input(
+                &InputGenerator::Bytes {
+                    min_len: 2,
+                    max_len: 5,
+                },
+                &mut rng,
+            );
+            assert!((2..=5).contains(&bytes.len()));
+            assert!(bytes.iter().all(|v| (-128..=127).contains(v)));
+
+            let digits = generate_input(&InputGenerator::Digits { len: 1 }, &mut rng);
+            assert!(('0' as i64..='9' as i64).contains(&digits[0]));
+
+            let terminated = generate_input(
+                &InputGenerator::ZeroTerminated {
+                    min_len: 1,
+                    max_len: 4,
+                },
+                &mut rng,
+            );
+            let (last, body) = terminated.split_last().unwrap();
+            assert_eq!(*last, 0);
+            assert!((1..=4).contains(&body.len()));
+            assert!(body.iter().all(|&v| v != 0));
+        }
+    }
+
+    #[test]
+    fn shrink_finds_minimal_failing_input() {
+        // Pretend the program mishandles any byte >= 'Z'.
+        let generator = InputGenerator::Bytes {
+            min_len: 1,
+            max_len: 16,
+        };
+        let input = vec![3, 100, -7, 0x5a, 12];
+        let minimal = shrink(&generator, &input, |c| Ok(c.iter().any(|&v| v >= 0x5a))).unwrap();
+        assert_eq!(minimal, vec![0x5a]);
+    }
+
+    #[test]
+    fn shrink_keeps_zero_terminator() {
+        let generator = InputGenerator::ZeroTerminated {
+            min_len: 0,
+            max_len: 15,
+        };
+        let input = vec![5, -3, 9, 0];
+        let minimal = shrink(&generator, &input, |c| Ok(c.contains(&-3))).unwrap();
+        assert_eq!(minimal, vec![-3, 0]);
+    }
+
+    const CONSTANT_OUTPUT: &str = r#"
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    mov rsi, buf
+    mov byte [rsi], 11
+    mov byte [rsi + 1], 21
+    mov rax, 1
+    mov rdi, 1
+    mov rdx, 2
+    syscall
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+"#;
+
+    #[test]
+    fn constant_output_is_flagged_as_input_independent() {
+        let level = levels::get_level("02_Addition").unwrap();
+        assert!(output_ignores_input(
+            CONSTANT_OUTPUT,
+            &Syntax::Intel,
+            &level,
+            DEFAULT_SEED,
+            50_000
+        )
+        .unwrap());
+        // Still passes the first visible case, which is exactly what hidden cases are for.
+        let (input, expected) = &level.test_cases[0];
+        assert!(
+            grade_case(CONSTANT_OUTPUT, &Syntax::Intel, input, expected, 50_000)
+                .unwrap()
+                .passed
+        );
+    }
+
+    #[test]
+    fn levels_with_only_hidden_cases_are_probed_from_them() {
+        let mut level = levels::get_level("24_TheStackMachine").unwrap();
+        assert!(level.property.is_none());
+        let visible = std::mem::take(&mut level.test_cases);
+        level.hidden_test_cases.extend(visible);
+        assert!(output_ignores_input(
+            CONSTANT_OUTPUT,
+            &Syntax::Intel,
+            &level,
+            DEFAULT_SEED,
+            50_000
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn reference_solutions_depend_on_input() {
+        for id in ["02_Addition", "24_TheStackMachine"] {
+            let level = levels::get_level(id).unwrap();
+            let code = levels::reference_solution(id).unwrap();
+            assert!(
+                !output_ignores_input(&code, &Syntax::Intel, &level, DEFAULT_SEED, 50_000).unwrap(),
+                "{id}"
+            );
+        }
+    }
+
+    #[test]
+    fn static_constraints_check_mnemonics() {
+        let constraints = Constraints {
+            required_mnemonics: vec!["xor".to_string()],
+            denied_mnemonics: vec!["add".to_string()],
+            max_source_lines: Some(2),
+            ..Default::default()
+        };
+        let intel = "_start:\n    add rax, 1\n    mov rdi, rax\n    syscall\n";
+        let violations = check_static_constraints(intel, &Syntax::Intel, &constraints).unwrap();
+        assert_eq!(
+            violations,
+            vec![
+                "`add` is not allowed in this level (line 2)".to_string(),
+                "`xor` must be used at least once".to_string(),
+                "Program has 3 instruction lines (limit 2)".to_string(),
+            ]
+        );
+
+        let att = "_start:\n    xorq $0x20, %rax\n    syscall\n";
+        assert!(check_static_constraints(att, &Syntax::Att, &constraints)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn boilerplate_does_not_meet_required_mnemonics() {
+        let xor_trick = levels::get_level("04_TheXORTrick").unwrap().constraints;
+        // Flips bit 5 with `btc`: right answers, but no XOR beyond the exit sequence.
+        let code = "section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    mov rax, 0
+    mov rdi, 0
+    mov rsi, buf
+    mov rdx, 16
+    syscall
+    mov rcx, rax
+    mov r8, 0
+.loop:
+    cmp r8, rcx
+    jge .done
+    mov al, byte [buf + r8]
+    btc eax, 5
+    mov byte [buf + r8], al
+    inc r8
+    jmp .loop
+.done:
+    mov rdx, rcx
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+";
+        let violations = check_static_constraints(code, &Syntax::Intel, &xor_trick).unwrap();
+        assert_eq!(
+            violations,
+            vec!["`xor` must be used at least once".to_string()]
+        );
+
+        let stack = Constraints {
+            required_mnemonics: vec!["push".to_string(), "pop".to_string()],
+            ..Default::default()
+        };
+        let code = "_start:\n    pushq %rax\n    popq %rax\n    syscall\n";
+        assert_eq!(
+            check_static_constraints(code, &Syntax::Att, &stack).unwrap(),
+            vec![
+                "`push` must be used at least once".to_string(),
+                "`pop` must be used at least once".to_string(),
+            ]
+        );
+        let code = "_start:\n    push rax\n    xor eax, eax\n    pop rax\n    syscall\n";
+        assert!(check_static_constraints(code, &Syntax::Intel, &stack)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn allowlist_rejects_everything_else() {
+        let constraints = Constraints {
+            allowed_mnemonics: Some(vec!["mov".to_string(), "syscall".to_string()]),
+            ..Default::default()
+        };
+        let code = "mov rax, 60\nxor rdi, rdi\nsyscall\n";
+        let violations = check_static_constraints(code, &Syntax::Intel, &constraints).unwrap();
+        assert_eq!(
+            violations,
+            vec!["`xor` is not allowed in this level (line 2)".to_string()]
+        );
+    }
+
+    #[test]
+    fn reference_solutions_satisfy_their_constraints() {
+        for level in levels::get_levels() {
+            let Ok(code) = levels::reference_solution(&level.id) else {
+                continue;
+            };
+            let violations =
+                check_static_constraints(&code, &Syntax::Intel, &level.constraints).unwrap();
+            assert!(violations.is_empty(), "{}: {:?}", level.id, violations);
+        }
+    }
+
+    #[test]
+    fn reference_solution_passes_generated_cases() {
+        let level = levels::get_level("12_TheAccumulator").unwrap();
+        let code = levels::reference_solution(&level.id).unwrap();
+        let report =
+            check_property(&code, &Syntax::Intel, &level, 50, DEFAULT_SEED, 50_000).unwrap();
+        assert_eq!(report.cases_run, 50);
+        assert!(report.failure.is_none());
+    }
+
+    #[test]
+    fn reference_metrics_cover_each_approach() {
+        let level = levels::get_level("02_Addition").unwrap();
+        let metrics = reference_metrics(&level, MAX_INSTRUCTIONS).unwrap();
+        assert_eq!(metrics.len(), 2);
+        for m in &metrics {
+            assert!(m.instructions_executed > 0);
+            assert!(m.code_bytes > 0);
+            assert!(m.registers_used > 0);
+        }
+        let code = levels::reference_solution(&level.id).unwrap();
+        assert_eq!(
+            metrics[0].source_lines,
+            x86_asm::source_instructions(&code).len()
+        );
+        // Different programs, so the range is not a single point.
+        assert_ne!(metrics[0], metrics[1]);
+    }
+
+    #[test]
+    fn hardcoded_solution_is_caught_and_shrunk() {
+        // A plain echo is right for punctuation and wrong for letters and digits.
+        let code = r#"
+section .bss
+    buf resb 32
+
+section .text
+    global _start
+
+_start:
+    mov rax, 0
+    mov rdi, 0
+    mov rsi, buf
+    mov rdx, 32
+    syscall
+    mov rdx, rax
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+"#;
+        let level = levels::get_level("12_TheAccumulator").unwrap();
+        let report =
+            check_property(code, &Syntax::Intel, &level, 50, DEFAULT_SEED, 50_000).unwrap();
+        let failure = report.failure.expect("echo must not pass the accumulator");
+        assert_eq!(failure.minimal.input.len(), 1);
+        assert!(!failure.minimal.passed);
+    }
+}
@@ -1,4 +1,53 @@
+use crate::vm::Syntax;
 use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Random input shapes for property-based checks. Values are bytes in the
+/// sign-extended range `-128..=127`, the same way `sys_write` reports them.
+#[derive(Debug, Clone)]
+pub enum InputGenerator {
+    /// Random bytes, biased towards edge values ('A', 'Z', '0', '9', 0, -1, ...).
+    Bytes { min_len: usize, max_len: usize },
+    /// ASCII digits '0'..='9'.
+    Digits { len: usize },
+    /// Non-zero bytes followed by a terminating 0.
+    ZeroTerminated { min_len: usize, max_len: usize },
+}
+
+/// Where the expected output of a generated input comes from.
+#[derive(Debug, Clone)]
+pub enum Oracle {
+    /// A Rust model of the level.
+    Model(fn(&[i64]) -> Vec<i64>),
+    /// The stage's own `collect.asm`.
+    ReferenceSolution,
+}
+
+#[derive(Debug, Clone)]
+pub struct PropertyTest {
+    pub generator: InputGenerator,
+    pub oracle: Oracle,
+}
+
+/// Rules a solution must follow on top of producing the right output.
+/// Mnemonics are matched as written; in AT&T syntax a size suffix is also accepted
+/// (`xor` matches `xorq`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Constraints {
+    /// If set, only these mnemonics may appear.
+    pub allowed_mnemonics: Option<Vec<String>>,
+    pub denied_mnemonics: Vec<String>,
+    /// Each of these must be used at least once for real work: `xor rdi, rdi` or `push rax` right
+    /// before `pop rax` do not count.
+    pub required_mnemonics: Vec<String>,
+    /// Instruction lines in `.text`.
+    pub max_source_lines: Option<usize>,
+    /// Size of the assembled program.
+    pub max_code_bytes: Option<usize>,
+    /// Executed instructions per graded test case.
+    pub max_instructions_executed: Option<usize>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Level {
@@ -13,6 +62,149 @@ pub struct Level {
     // If level expects return, checking "RAX".
     // We can infer mode from expected data, or just check both.
     pub test_cases: Vec<(Vec<i64>, Vec<i64>)>,
+    // Graded like `test_cases`, but never sent to the frontend.
+    #[serde(skip)]
+    pub hidden_test_cases: Vec<(Vec<i64>, Vec<i64>)>,
+    pub constraints: Constraints,
+    // Seeded random cases checked on top of `test_cases` (backend only).
+    #[serde(skip)]
+    pub property: Option<PropertyTest>,
+}
+
+impl Level {
+    /// Visible cases followed by hidden ones; the flag is `true` for hidden cases.
+    pub fn graded_cases(&self) -> impl Iterator<Item = (&Vec<i64>, &Vec<i64>, bool)> {
+        self.test_cases
+            .iter()
+            .map(|(input, expected)| (input, expected, false))
+            .chain(
+                self.hidden_test_cases
+                    .iter()
+                    .map(|(input, expected)| (input, expected, true)),
+            )
+    }
+}
+
+fn bytes(min_len: usize, max_len: usize, model: fn(&[i64]) -> Vec<i64>) -> PropertyTest {
+    PropertyTest {
+        generator: InputGenerator::Bytes { min_len, max_len },
+        oracle: Oracle::Model(model),
+    }
+}
+
+fn zero_terminated(min_len: usize, max_len: usize, model: fn(&[i64]) -> Vec<i64>) -> PropertyTest {
+    PropertyTest {
+        generator: InputGenerator::ZeroTerminated { min_len, max_len },
+        oracle: Oracle::Model(model),
+    }
+}
+
+// Reference models. Inputs and outputs are bytes as seen through read/write,
+// so arithmetic wraps at 8 bits and results are sign-extended.
+
+fn byte(v: i64) -> i64 {
+    (v as i8) as i64
+}
+
+fn until_0(input: &[i64]) -> &[i64] {
+    let end = input.iter().position(|&v| v == 0).unwrap_or(input.len());
+    &input[..end]
+}
+
+fn echo(input: &[i64]) -> Vec<i64> {
+    input.iter().map(|&v| byte(v)).collect()
+}
+
+fn add_one(input: &[i64]) -> Vec<i64> {
+    input.iter().map(|&v| byte(v.wrapping_add(1))).collect()
+}
+
+fn sub_one(input: &[i64]) -> Vec<i64> {
+    input.iter().map(|&v| byte(v.wrapping_sub(1))).collect()
+}
+
+fn xor_0x20(input: &[i64]) -> Vec<i64> {
+    input.iter().map(|&v| byte(v ^ 0x20)).collect()
+}
+
+fn inc_dec(input: &[i64]) -> Vec<i64> {
+    input
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| byte(if i % 2 == 0 { v + 1 } else { v - 1 }))
+        .collect()
+}
+
+fn zero_to_space(input: &[i64]) -> Vec<i64> {
+    input
+        .iter()
+        .map(|&v| if byte(v) == 0 { 0x20 } else { byte(v) })
+        .collect()
+}
+
+fn sign_marker(input: &[i64]) -> Vec<i64> {
+    match input.first() {
+        Some(&v) if byte(v) < 0 => vec!['-' as i64],
+        _ => vec!['+' as i64],
+    }
+}
+
+fn countdown(input: &[i64]) -> Vec<i64> {
+    let top = byte(input.first().copied().unwrap_or('0' as i64));
+    (('0' as i64)..=top).rev().collect()
+}
+
+fn sum_first_3(input: &[i64]) -> Vec<i64> {
+    vec![byte(input.iter().take(3).sum())]
+}
+
+fn accumulator(input: &[i64]) -> Vec<i64> {
+    input
+        .iter()
+        .map(|&v| match byte(v) {
+            c @ 0x41..=0x5a => c + 0x20,
+            0x39 => 0x30,
+            c @ 0x30..=0x38 => c + 1,
+            c => c,
+        })
+        .collect()
+}
+
+fn duplicate(input: &[i64]) -> Vec<i64> {
+    let v = byte(input[0]);
+    vec![v, v]
+}
+
+fn reverse(input: &[i64]) -> Vec<i64> {
+    input.iter().rev().map(|&v| byte(v)).collect()
+}
+
+fn sort(input: &[i64]) -> Vec<i64> {
+    let mut out = echo(input);
+    out.sort();
+    out
+}
+
+fn rotate_left(input: &[i64]) -> Vec<i64> {
+    let mut out = echo(input);
+    out.rotate_left(1);
+    out
+}
+
+fn reverse_until_0(input: &[i64]) -> Vec<i64> {
+    reverse(until_0(input))
+}
+
+fn sum_until_0(input: &[i64]) -> Vec<i64> {
+    vec![byte(until_0(input).iter().sum())]
+}
+
+fn min_max_until_0(input: &[i64]) -> Vec<i64> {
+    let values = echo(until_0(input));
+    match (values.iter().min(), values.iter().max()) {
+        (Some(&min), Some(&max)) => vec![min, max],
+        _ => vec![],
+    }
 }
 
 pub fn get_levels() -> Vec<Level> {
@@ -27,6 +219,11 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![0], vec![0]),
                 (vec![-55], vec![-55]),
             ],
+            hidden_test_cases: vec![
+                (vec![127, -128, 65], vec![127, -128, 65]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 16, echo)),
         },
         Level {
             id: "02_Addition".to_string(),
@@ -37,6 +234,12 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![5, 5], vec![6, 6]),
                 (vec![-1, 0], vec![0, 1]),
             ],
+            hidden_test_cases: vec![
+                (vec![127], vec![-128]),
+                (vec![9, 0x39], vec![10, 0x3a]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 16, add_one)),
         },
         Level {
             id: "03_Subtraction".to_string(),
@@ -47,6 +250,11 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![0], vec![-1]),
                 (vec![-128], vec![127]),
             ],
+            hidden_test_cases: vec![
+                (vec![1, -127], vec![0, -128]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 16, sub_one)),
         },
         Level {
             id: "04_TheXORTrick".to_string(),
@@ -57,6 +265,15 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![('Z' as i64)], vec![('z' as i64)]),
                 (vec![0], vec![0x20]),
             ],
+            hidden_test_cases: vec![
+                (vec![0x40, 0x5b, -1], vec![0x60, 0x7b, -33]),
+            ],
+            constraints: Constraints {
+                required_mnemonics: vec!["xor".to_string()],
+                denied_mnemonics: vec!["add".to_string(), "sub".to_string()],
+                ..Default::default()
+            },
+            property: Some(bytes(1, 16, xor_0x20)),
         },
         Level {
             id: "05_Inc&Dec".to_string(),
@@ -67,6 +284,11 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![10, 10, 10, 10], vec![11, 9, 11, 9]),
                 (vec![0], vec![1]),
             ],
+            hidden_test_cases: vec![
+                (vec![0, 0, -128, -128, 127], vec![1, -1, -127, 127, -128]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 16, inc_dec)),
         },
         Level {
             id: "06_Unconditional".to_string(),
@@ -76,6 +298,14 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![1, 2, 3], vec![1, 2, 3]),
                 (vec![-55], vec![-55]),
             ],
+            hidden_test_cases: vec![
+                (vec![0, 127, -128], vec![0, 127, -128]),
+            ],
+            constraints: Constraints {
+                required_mnemonics: vec!["jmp".to_string()],
+                ..Default::default()
+            },
+            property: Some(bytes(1, 16, echo)),
         },
         Level {
             id: "07_ZeroFlag".to_string(),
@@ -85,6 +315,11 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![0, 1, 0], vec![0x20, 1, 0x20]),
                 (vec![5], vec![5]),
             ],
+            hidden_test_cases: vec![
+                (vec![0x20, 0, -1], vec![0x20, 0x20, -1]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 16, zero_to_space)),
         },
         Level {
             id: "08_SignFlag".to_string(),
@@ -94,6 +329,12 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![0], vec![('+' as i64)]),
                 (vec![-1], vec![('-' as i64)]),
             ],
+            hidden_test_cases: vec![
+                (vec![127, -1], vec![('+' as i64)]),
+                (vec![-128], vec![('-' as i64)]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 16, sign_marker)),
         },
         Level {
             id: "09_Comparison".to_string(),
@@ -108,6 +349,18 @@ pub fn get_levels() -> Vec<Level> {
                 ),
                 (vec![10], vec![]),
             ],
+            hidden_test_cases: vec![
+                (vec![5, 5], vec![('=' as i64)]),
+                (vec![-1, 1, 0], vec![('+' as i64), ('-' as i64)]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(PropertyTest {
+                generator: InputGenerator::Bytes {
+                    min_len: 1,
+                    max_len: 16,
+                },
+                oracle: Oracle::ReferenceSolution,
+            }),
         },
         Level {
             id: "10_Countdown".to_string(),
@@ -117,6 +370,29 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![('3' as i64)], vec![('3' as i64), ('2' as i64), ('1' as i64), ('0' as i64)]),
                 (vec![('0' as i64)], vec![('0' as i64)]),
             ],
+            hidden_test_cases: vec![
+                (vec![('1' as i64)], vec![('1' as i64), ('0' as i64)]),
+                (
+                    vec![('9' as i64)],
+                    vec![
+                        ('9' as i64),
+                        ('8' as i64),
+                        ('7' as i64),
+                        ('6' as i64),
+                        ('5' as i64),
+                        ('4' as i64),
+                        ('3' as i64),
+                        ('2' as i64),
+                        ('1' as i64),
+                        ('0' as i64),
+                    ],
+                ),
+            ],
+            constraints: Constraints::default(),
+            property: Some(PropertyTest {
+                generator: InputGenerator::Digits { len: 1 },
+                oracle: Oracle::Model(countdown),
+            }),
         },
         Level {
             id: "11_Accumulate3".to_string(),
@@ -126,6 +402,12 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![1, 2, 3], vec![6]),
                 (vec![10, 10, 10], vec![30]),
             ],
+            hidden_test_cases: vec![
+                (vec![100, 100, 100], vec![44]),
+                (vec![-1, 1, 0, 9], vec![0]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(3, 16, sum_first_3)),
         },
         Level {
             id: "12_TheAccumulator".to_string(),
@@ -140,6 +422,18 @@ pub fn get_levels() -> Vec<Level> {
                 ),
                 (vec![('B' as i64), ('0' as i64)], vec![('b' as i64), ('1' as i64)]),
             ],
+            hidden_test_cases: vec![
+                (
+                    vec![('Z' as i64), ('a' as i64), ('0' as i64), ('8' as i64), (':' as i64), -1],
+                    vec![('z' as i64), ('a' as i64), ('1' as i64), ('9' as i64), (':' as i64), -1],
+                ),
+                (
+                    vec![('@' as i64), ('[' as i64), ('/' as i64)],
+                    vec![('@' as i64), ('[' as i64), ('/' as i64)],
+                ),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 32, accumulator)),
         },
         // GRAND STAGE 02: The Stack
         Level {
@@ -147,6 +441,14 @@ pub fn get_levels() -> Vec<Level> {
             name: "Push & Pop".to_string(),
             description: "Read A, push/pop it, then output A (1 byte).".to_string(),
             test_cases: vec![(vec![42], vec![42]), (vec![-1], vec![-1]), (vec![0], vec![0])],
+            hidden_test_cases: vec![
+                (vec![127], vec![127]),
+            ],
+            constraints: Constraints {
+                required_mnemonics: vec!["push".to_string(), "pop".to_string()],
+                ..Default::default()
+            },
+            property: Some(bytes(1, 1, echo)),
         },
         Level {
             id: "14_SwapTwo".to_string(),
@@ -156,18 +458,33 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![1, 2], vec![2, 1]),
                 (vec![5, -1], vec![-1, 5]),
             ],
+            hidden_test_cases: vec![
+                (vec![-128, 127], vec![127, -128]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(2, 2, reverse)),
         },
         Level {
             id: "15_Duplicate".to_string(),
             name: "Duplicate".to_string(),
             description: "Read A and output A,A (2 bytes).".to_string(),
             test_cases: vec![(vec![7], vec![7, 7]), (vec![-1], vec![-1, -1])],
+            hidden_test_cases: vec![
+                (vec![127], vec![127, 127]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(1, 1, duplicate)),
         },
         Level {
             id: "16_Reverse3".to_string(),
             name: "Reverse 3".to_string(),
             description: "Read A,B,C and output C,B,A (3 bytes).".to_string(),
             test_cases: vec![(vec![1, 2, 3], vec![3, 2, 1]), (vec![-1, 0, 1], vec![1, 0, -1])],
+            hidden_test_cases: vec![
+                (vec![5, 5, -5], vec![-5, 5, 5]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(3, 3, reverse)),
         },
         Level {
             id: "17_ReverseUntil0".to_string(),
@@ -178,12 +495,22 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![-1, 0], vec![-1]),
                 (vec![0], vec![]),
             ],
+            hidden_test_cases: vec![
+                (vec![7, -7, 7, 0], vec![7, -7, 7]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(zero_terminated(0, 15, reverse_until_0)),
         },
         Level {
             id: "18_SumFromStack".to_string(),
             name: "Sum From Stack".to_string(),
             description: "Read values until 0, sum them, output 1 byte result.".to_string(),
             test_cases: vec![(vec![1, 2, 3, 0], vec![6]), (vec![10, 20, 0], vec![30]), (vec![-1, 1, 0], vec![0])],
+            hidden_test_cases: vec![
+                (vec![100, 100, 0], vec![-56]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(zero_terminated(0, 15, sum_until_0)),
         },
         Level {
             id: "19_SafePop".to_string(),
@@ -194,30 +521,62 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![-1, 0], vec![0]),
                 (vec![1, 1, -1, -1, 0], vec![0]),
             ],
+            hidden_test_cases: vec![
+                (vec![-1, -1, 1, 9, 0], vec![1]),
+                (vec![1, 0, 1, 0, 0], vec![2]),
+            ],
+            constraints: Constraints::default(),
+            property: None,
         },
         Level {
             id: "20_RPN_AddOnly".to_string(),
             name: "RPN (Add Only)".to_string(),
             description: "RPN evaluation: numbers push, -1 add, 0 end; output top.".to_string(),
             test_cases: vec![(vec![2, 3, -1, 0], vec![5]), (vec![5, 1, -1, 0], vec![6])],
+            hidden_test_cases: vec![
+                (vec![1, 2, 3, -1, -1, 0], vec![6]),
+                (vec![100, 100, -1, 0], vec![-56]),
+            ],
+            constraints: Constraints::default(),
+            property: None,
         },
         Level {
             id: "21_Sort3".to_string(),
             name: "Sort 3".to_string(),
             description: "Read 3 values and output them sorted ascending (3 bytes).".to_string(),
             test_cases: vec![(vec![3, 1, 2], vec![1, 2, 3]), (vec![-1, 0, 1], vec![-1, 0, 1])],
+            hidden_test_cases: vec![
+                (vec![2, 2, 1], vec![1, 2, 2]),
+                (vec![127, -128, 0], vec![-128, 0, 127]),
+            ],
+            constraints: Constraints {
+                required_mnemonics: vec!["push".to_string(), "pop".to_string()],
+                ..Default::default()
+            },
+            property: Some(bytes(3, 3, sort)),
         },
         Level {
             id: "22_Rotate3".to_string(),
             name: "Rotate 3".to_string(),
             description: "Read A,B,C and output B,C,A (3 bytes).".to_string(),
             test_cases: vec![(vec![1, 2, 3], vec![2, 3, 1]), (vec![-1, 5, 0], vec![5, 0, -1])],
+            hidden_test_cases: vec![
+                (vec![0, 0, 1], vec![0, 1, 0]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(bytes(3, 3, rotate_left)),
         },
         Level {
             id: "23_MinMaxFromStack".to_string(),
             name: "Min & Max From Stack".to_string(),
             description: "Read values until 0; output min then max (2 bytes).".to_string(),
             test_cases: vec![(vec![3, 1, 2, 0], vec![1, 3]), (vec![-1, -5, 2, 0], vec![-5, 2])],
+            hidden_test_cases: vec![
+                (vec![5, 0], vec![5, 5]),
+                (vec![-128, 127, 0], vec![-128, 127]),
+            ],
+            constraints: Constraints::default(),
+            property: Some(zero_terminated(1, 15, min_max_until_0)),
         },
         Level {
             id: "24_TheStackMachine".to_string(),
@@ -227,6 +586,12 @@ pub fn get_levels() -> Vec<Level> {
                 (vec![5, 3, -2, 0], vec![2]),
                 (vec![1, 2, -1, 3, -3, 0], vec![0]),
             ],
+            hidden_test_cases: vec![
+                (vec![9, 9, -3, 0], vec![0]),
+                (vec![1, 5, -2, 0], vec![-4]),
+            ],
+            constraints: Constraints::default(),
+            property: None,
         },
         Level {
             id: "Tutorial_Exit".to_string(),
@@ -235,6 +600,9 @@ pub fn get_levels() -> Vec<Level> {
             test_cases: vec![
                 (vec![], vec![]), // Success if it exits without error
             ],
+            hidden_test_cases: vec![],
+            constraints: Constraints::default(),
+            property: None,
         },
     ]
 }
@@ -243,6 +611,69 @@ pub fn get_level(id: &str) -> Option<Level> {
     get_levels().into_iter().find(|l| l.id == id)
 }
 
+pub fn level_dir_for_id(level_id: &str) -> Option<&'static str> {
+    match level_id {
+        "01_Mov&Call" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/01_Mov&Call"),
+        "02_Addition" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/02_Addition"),
+        "03_Subtraction" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/03_Subtraction"),
+        "04_TheXORTrick" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/04_TheXORTrick"),
+        "05_Inc&Dec" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/05_Inc&Dec"),
+        "06_Unconditional" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/06_Unconditional"),
+        "07_ZeroFlag" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/07_ZeroFlag"),
+        "08_SignFlag" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/08_SignFlag"),
+        "09_Comparison" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/09_Comparison"),
+        "10_Countdown" => Some("stages/1.The-Accumulator/Phase3-LoopStructures/10_Countdown"),
+        "11_Accumulate3" => Some("stages/1.The-Accumulator/Phase3-LoopStructures/11_Accumulate3"),
+        "12_TheAccumulator" => Some("stages/1.The-Accumulator/BOSS/12_TheAccumulator"),
+        // GRAND STAGE 02: The Stack
+        "13_Push&Pop" => Some("stages/2.The-Stack/Phase1-StackBasics/13_Push&Pop"),
+        "14_SwapTwo" => Some("stages/2.The-Stack/Phase1-StackBasics/14_SwapTwo"),
+        "15_Duplicate" => Some("stages/2.The-Stack/Phase1-StackBasics/15_Duplicate"),
+        "16_Reverse3" => Some("stages/2.The-Stack/Phase2-StackAsBuffer/16_Reverse3"),
+        "17_ReverseUntil0" => Some("stages/2.The-Stack/Phase2-StackAsBuffer/17_ReverseUntil0"),
+        "18_SumFromStack" => Some("stages/2.The-Stack/Phase2-StackAsBuffer/18_SumFromStack"),
+        "19_SafePop" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/19_SafePop"),
+        "20_RPN_AddOnly" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/20_RPN_AddOnly"),
+        "21_Sort3" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/21_Sort3"),
+        "22_Rotate3" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/22_Rotate3"),
+        "23_MinMaxFromStack" => {
+            Some("stages/2.The-Stack/Phase3-StackAlgorithms/23_MinMaxFromStack")
+        }
+        "24_TheStackMachine" => Some("stages/2.The-Stack/BOSS/24_TheStackMachine"),
+        _ => None,
+    }
+}
+
+pub fn read_stage_file(rel_path: &str) -> Result<String, String> {
+    // プロジェクトルートからの相対パスで読み込む
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.pop(); // src-tauri から出る
+    path.push(rel_path);
+    fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {} (path: {:?})", e, path))
+}
+
+/// The Intel-syntax `collect.asm` shipped with a stage.
+pub fn reference_solution(level_id: &str) -> Result<String, String> {
+    let dir = level_dir_for_id(level_id)
+        .ok_or_else(|| format!("Stage files not found for level: {}", level_id))?;
+    read_stage_file(&format!("{}/collect.asm", dir))
+}
+
+/// Every shipped solution of a stage: `collect.asm` (Intel), its AT&T translation
+/// `collect_Att.asm`, and `collect_alt.asm`, a different approach written in Intel syntax.
+pub fn reference_solutions(level_id: &str) -> Result<Vec<(Syntax, String)>, String> {
+    let dir = level_dir_for_id(level_id)
+        .ok_or_else(|| format!("Stage files not found for level: {}", level_id))?;
+    [
+        ("collect.asm", Syntax::Intel),
+        ("collect_Att.asm", Syntax::Att),
+        ("collect_alt.asm", Syntax::Intel),
+    ]
+    .into_iter()
+    .map(|(file, syntax)| Ok((syntax, read_stage_file(&format!("{}/{}", dir, file))?)))
+    .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -286,6 +717,52 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_models_agree_with_fixed_cases() {
+        for level in get_levels() {
+            let Some(PropertyTest {
+                oracle: Oracle::Model(model),
+                ..
+            }) = level.property
+            else {
+                continue;
+            };
+            for (input, expected, _) in level.graded_cases() {
+                assert_eq!(
+                    &model(input),
+                    expected,
+                    "level {} input {:?}",
+                    level.id,
+                    input
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_accumulator_model_edges() {
+        let input: Vec<i64> = "AZ@[09:/"
+            .bytes()
+            .map(|b| b as i64)
+            .chain([-1, -128])
+            .collect();
+        let expected: Vec<i64> = "az@[10:/"
+            .bytes()
+            .map(|b| b as i64)
+            .chain([-1, -128])
+            .collect();
+        assert_eq!(accumulator(&input), expected);
+    }
+
+    #[test]
+    fn test_hidden_cases_are_not_serialized() {
+        let level = get_level("12_TheAccumulator").unwrap();
+        assert!(!level.hidden_test_cases.is_empty());
+        let json = serde_json::to_value(&level).unwrap();
+        assert!(json.get("hidden_test_cases").is_none());
+        assert!(json.get("test_cases").is_some());
+    }
+
     #[test]
     fn test_all_levels_have_test_cases() {
         let levels = get_levels();
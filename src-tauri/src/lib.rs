@@ -1,11 +1,30 @@
+pub mod abi;
+pub mod condition;
+pub mod debugger;
+pub mod elf;
+pub mod encoding;
+pub mod gradebook;
+pub mod grader;
 pub mod levels;
+pub mod memory;
+pub mod report;
+pub mod stream;
+pub mod translate;
 pub mod vm;
 pub mod x86_asm;
 pub mod x86_runtime;
 
 use serde::Serialize;
-use std::fs;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use stream::StreamControl;
+use tauri::{Emitter, Manager};
+
+/// Wall-clock limit of a simulation when the frontend does not give one.
+const DEFAULT_TIMEOUT_MS: u64 = 10_000;
 
 #[derive(Serialize)]
 struct SimulationResult {
@@ -13,6 +32,12 @@ struct SimulationResult {
     success: bool,
     message: String,
     execution_log: Vec<String>, // 実行ログを追加
+    /// Set when the program passed every check of the requested level.
+    metrics: Option<grader::SolutionMetrics>,
+    /// The syntax the program was run as.
+    syntax: vm::Syntax,
+    /// Set when the caller asked for `Auto`.
+    syntax_detection: Option<x86_asm::SyntaxDetection>,
 }
 
 #[tauri::command]
@@ -20,153 +45,505 @@ fn get_levels() -> Vec<levels::Level> {
     levels::get_levels()
 }
 
-fn level_dir_for_id(level_id: &str) -> Option<&'static str> {
-    match level_id {
-        "01_Mov&Call" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/01_Mov&Call"),
-        "02_Addition" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/02_Addition"),
-        "03_Subtraction" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/03_Subtraction"),
-        "04_TheXORTrick" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/04_TheXORTrick"),
-        "05_Inc&Dec" => Some("stages/1.The-Accumulator/Phase1-Registers&ALU/05_Inc&Dec"),
-        "06_Unconditional" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/06_Unconditional"),
-        "07_ZeroFlag" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/07_ZeroFlag"),
-        "08_SignFlag" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/08_SignFlag"),
-        "09_Comparison" => Some("stages/1.The-Accumulator/Phase2-Flags&Jumps/09_Comparison"),
-        "10_Countdown" => Some("stages/1.The-Accumulator/Phase3-LoopStructures/10_Countdown"),
-        "11_Accumulate3" => Some("stages/1.The-Accumulator/Phase3-LoopStructures/11_Accumulate3"),
-        "12_TheAccumulator" => Some("stages/1.The-Accumulator/BOSS/12_TheAccumulator"),
-        // GRAND STAGE 02: The Stack
-        "13_Push&Pop" => Some("stages/2.The-Stack/Phase1-StackBasics/13_Push&Pop"),
-        "14_SwapTwo" => Some("stages/2.The-Stack/Phase1-StackBasics/14_SwapTwo"),
-        "15_Duplicate" => Some("stages/2.The-Stack/Phase1-StackBasics/15_Duplicate"),
-        "16_Reverse3" => Some("stages/2.The-Stack/Phase2-StackAsBuffer/16_Reverse3"),
-        "17_ReverseUntil0" => Some("stages/2.The-Stack/Phase2-StackAsBuffer/17_ReverseUntil0"),
-        "18_SumFromStack" => Some("stages/2.The-Stack/Phase2-StackAsBuffer/18_SumFromStack"),
-        "19_SafePop" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/19_SafePop"),
-        "20_RPN_AddOnly" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/20_RPN_AddOnly"),
-        "21_Sort3" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/21_Sort3"),
-        "22_Rotate3" => Some("stages/2.The-Stack/Phase3-StackAlgorithms/22_Rotate3"),
-        "23_MinMaxFromStack" => {
-            Some("stages/2.The-Stack/Phase3-StackAlgorithms/23_MinMaxFromStack")
-        }
-        "24_TheStackMachine" => Some("stages/2.The-Stack/BOSS/24_TheStackMachine"),
-        _ => None,
-    }
-}
-
-fn read_stage_file(rel_path: &str) -> Result<String, String> {
-    // プロジェクトルートからの相対パスで読み込む
-    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    path.pop(); // src-tauri から出る
-    path.push(rel_path);
-    fs::read_to_string(&path).map_err(|e| format!("Failed to read file: {} (path: {:?})", e, path))
-}
-
 #[tauri::command]
 fn get_level_explanation(level_id: String) -> Result<String, String> {
-    let dir = level_dir_for_id(level_id.as_str())
+    let dir = levels::level_dir_for_id(level_id.as_str())
         .ok_or_else(|| format!("Stage files not found for level: {}", level_id))?;
     let md = format!("{}/{}.md", dir, level_id);
-    read_stage_file(&md)
+    levels::read_stage_file(&md)
 }
 
 #[tauri::command]
 fn get_level_ini(level_id: String, syntax: String) -> Result<String, String> {
-    let dir = level_dir_for_id(level_id.as_str())
+    let dir = levels::level_dir_for_id(level_id.as_str())
         .ok_or_else(|| format!("Stage files not found for level: {}", level_id))?;
     let primary = match syntax.as_str() {
         "Att" => format!("{}/ini_Att.asm", dir),
         _ => format!("{}/ini.asm", dir),
     };
-    match read_stage_file(&primary) {
+    match levels::read_stage_file(&primary) {
         Ok(s) => Ok(s),
         Err(_) => {
             // Backward compatible fallback
             let fallback = format!("{}/ini.asm", dir);
-            read_stage_file(&fallback)
+            levels::read_stage_file(&fallback)
         }
     }
 }
 
 #[tauri::command]
 fn get_level_collect(level_id: String, syntax: String) -> Result<String, String> {
-    let dir = level_dir_for_id(level_id.as_str())
+    let dir = levels::level_dir_for_id(level_id.as_str())
         .ok_or_else(|| format!("Stage files not found for level: {}", level_id))?;
     let primary = match syntax.as_str() {
         "Att" => format!("{}/collect_Att.asm", dir),
         _ => format!("{}/collect.asm", dir),
     };
-    match read_stage_file(&primary) {
+    match levels::read_stage_file(&primary) {
         Ok(s) => Ok(s),
         Err(_) => {
             // Backward compatible fallback
             let fallback = format!("{}/collect.asm", dir);
-            read_stage_file(&fallback)
+            levels::read_stage_file(&fallback)
+        }
+    }
+}
+
+/// Metrics of the stage's reference solutions, for comparison with the player's own.
+#[tauri::command]
+fn get_reference_metrics(level_id: String) -> Result<Vec<grader::SolutionMetrics>, String> {
+    let level =
+        levels::get_level(&level_id).ok_or_else(|| format!("Unknown level: {}", level_id))?;
+    grader::reference_metrics(&level, grader::MAX_INSTRUCTIONS)
+}
+
+/// `Intel`, `Att`, or `Auto` to detect it from `code`.
+fn parse_syntax(name: &str, code: &str) -> Result<vm::Syntax, String> {
+    match name {
+        "Intel" => Ok(vm::Syntax::Intel),
+        "Att" => Ok(vm::Syntax::Att),
+        "Auto" => Ok(x86_asm::detect_syntax(code)),
+        _ => Err("Invalid syntax type".to_string()),
+    }
+}
+
+/// Translate a program between `Intel` and `Att`; `from` may be `Auto`.
+#[tauri::command]
+fn translate_code(code: &str, from: String, to: String) -> Result<String, String> {
+    translate::translate(code, &parse_syntax(&from, code)?, &parse_syntax(&to, code)?)
+}
+
+/// Address, bytes, source line and encoding breakdown of every instruction, for the machine code panel.
+#[tauri::command]
+fn get_machine_code(code: &str, syntax: String) -> Result<Vec<encoding::MachineCodeLine>, String> {
+    encoding::machine_code(code, parse_syntax(&syntax, code)?)
+}
+
+/// NASM-style `.lst` listing of a program, for printing.
+#[tauri::command]
+fn get_listing(code: &str, syntax: String) -> Result<String, String> {
+    let assembled = x86_runtime::assemble_program(code, parse_syntax(&syntax, code)?)?;
+    Ok(x86_asm::listing(code, &assembled))
+}
+
+/// A static x86-64 Linux executable of the program, for running it natively.
+#[tauri::command]
+fn build_executable(code: &str, syntax: String) -> Result<Vec<u8>, String> {
+    elf::build_executable(code, parse_syntax(&syntax, code)?)
+}
+
+/// A relocatable ELF object (`.o`) of the program, for linking its `global` functions with C.
+#[tauri::command]
+fn build_object(code: &str, syntax: String) -> Result<Vec<u8>, String> {
+    elf::build_object(code, parse_syntax(&syntax, code)?)
+}
+
+/// Run a static x86-64 Linux executable (e.g. built with `nasm` and `ld`) in the emulator.
+#[tauri::command]
+fn run_executable(
+    image: Vec<u8>,
+    args: Vec<String>,
+    input: Vec<i64>,
+) -> Result<vm::VmState, String> {
+    let config = x86_runtime::RunConfig {
+        max_instructions: 50_000,
+        ..x86_runtime::RunConfig::default()
+    };
+    Ok(x86_runtime::run_elf(&image, &args, &[], input, &config)?.state)
+}
+
+/// A page of the program's memory once it has run `steps` instructions (by default, until it
+/// stops), for the memory view. With `steps`, the page also lists what the last step changed.
+#[tauri::command]
+fn read_memory(
+    code: &str,
+    syntax: String,
+    input: Vec<i64>,
+    steps: Option<usize>,
+    request: memory::MemoryRequest,
+) -> Result<memory::MemoryPage, String> {
+    let mut session = x86_runtime::Session::new(code, parse_syntax(&syntax, code)?, input, false)?;
+    match steps {
+        Some(0) => {}
+        Some(n) => {
+            // `run(0)` would mean no limit.
+            if n > 1 {
+                session.run(n - 1);
+            }
+            session.track_memory_changes()?;
+            session.step();
         }
+        None => session.run(50_000),
     }
+    memory::read_page(&session, &request)
 }
 
+/// Places where the program's functions break the System V calling convention.
 #[tauri::command]
-fn run_simulation(
+fn check_calling_convention(
     code: &str,
     syntax: String,
     input: Vec<i64>,
+) -> Result<Vec<abi::AbiViolation>, String> {
+    abi::check_program(code, parse_syntax(&syntax, code)?, input, 50_000)
+}
+
+/// Sent as `simulation-progress` while `run_simulation` works through a level's cases.
+#[derive(Clone, Serialize)]
+struct SimulationProgress {
+    /// Steps finished so far, out of `total`.
+    done: usize,
+    total: usize,
+    /// What runs next, e.g. `Test Case #2`.
+    stage: String,
+}
+
+/// Cancel switches of the simulations in progress.
+#[derive(Default)]
+struct ActiveRuns(Mutex<Vec<Arc<AtomicBool>>>);
+
+impl ActiveRuns {
+    fn start(&self) -> Arc<AtomicBool> {
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.0.lock().unwrap().push(cancel.clone());
+        cancel
+    }
+
+    fn finish(&self, cancel: &Arc<AtomicBool>) {
+        self.0.lock().unwrap().retain(|c| !Arc::ptr_eq(c, cancel));
+    }
+}
+
+/// Run (and with a level, grade) a program on a worker thread, so the UI stays responsive.
+/// The whole simulation is stopped after `timeout_ms` or by `cancel_simulation`.
+#[tauri::command]
+async fn run_simulation(
+    app: tauri::AppHandle,
+    runs: tauri::State<'_, ActiveRuns>,
+    code: String,
+    syntax: String,
+    input: Vec<i64>,
     level_id: Option<String>,
+    timeout_ms: Option<u64>,
 ) -> Result<SimulationResult, String> {
-    let syntax_enum = match syntax.as_str() {
-        "Intel" => vm::Syntax::Intel,
-        "Att" => vm::Syntax::Att,
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let cancel = runs.start();
+    let limits = x86_runtime::RunLimits {
+        deadline: Some(Instant::now() + timeout),
+        cancel: cancel.clone(),
+        ..x86_runtime::RunLimits::default()
+    };
+    let worker = tauri::async_runtime::spawn_blocking(move || {
+        x86_runtime::with_limits(limits.clone(), || {
+            let progress = |p: SimulationProgress| {
+                let _ = app.emit("simulation-progress", p);
+            };
+            let result = simulate(&code, &syntax, input, level_id, &progress);
+            if limits.is_cancelled() {
+                Err("Simulation cancelled".to_string())
+            } else if limits.has_timed_out() {
+                Err(format!(
+                    "Simulation timed out after {} ms",
+                    timeout.as_millis()
+                ))
+            } else {
+                result
+            }
+        })
+    })
+    .await;
+    runs.finish(&cancel);
+    worker.map_err(|e| format!("Simulation worker failed: {}", e))?
+}
+
+/// Stop every simulation in progress; each returns a "Simulation cancelled" error.
+#[tauri::command]
+fn cancel_simulation(runs: tauri::State<'_, ActiveRuns>) {
+    for cancel in runs.0.lock().unwrap().iter() {
+        cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Controls of the streamed runs in progress, by id.
+#[derive(Default)]
+struct Streams {
+    next_id: AtomicU64,
+    controls: Mutex<HashMap<u64, Arc<StreamControl>>>,
+}
+
+impl Streams {
+    fn control(&self, id: u64) -> Result<Arc<StreamControl>, String> {
+        self.controls
+            .lock()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| format!("No running stream {}", id))
+    }
+}
+
+/// Sent as `execution-events`: the next events of stream `id`.
+#[derive(Clone, Serialize)]
+struct StreamBatch {
+    id: u64,
+    events: Vec<stream::ExecutionEvent>,
+}
+
+/// Sent as `execution-finished` when stream `id` ends, however it ends.
+#[derive(Clone, Serialize)]
+struct StreamFinished {
+    id: u64,
+    state: vm::VmState,
+}
+
+/// Load a program as a stream of `execution-events`, `speed` instructions per second (0 for as
+/// fast as possible), and return its id for `pause_stream` and friends. The stream starts
+/// paused, so no event arrives before the caller knows the id; `resume_stream` sets it going.
+#[tauri::command]
+fn start_stream(
+    app: tauri::AppHandle,
+    streams: tauri::State<'_, Streams>,
+    code: String,
+    syntax: String,
+    input: Vec<i64>,
+    speed: u32,
+) -> Result<u64, String> {
+    let syntax = parse_syntax(&syntax, &code)?;
+    let id = streams.next_id.fetch_add(1, Ordering::Relaxed);
+    let control = Arc::new(StreamControl::new(speed));
+    control.set_paused(true);
+    streams.controls.lock().unwrap().insert(id, control.clone());
+
+    // The emulator cannot leave the thread that created it, so the session is built there and
+    // only whether that worked comes back.
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let session = x86_runtime::Session::new(&code, syntax, input, false).and_then(|mut s| {
+            s.track_memory_changes()?;
+            Ok(s)
+        });
+        let ok = session.as_ref().map(|_| ()).map_err(String::clone);
+        let _ = tx.send(ok);
+        if let Ok(mut session) = session {
+            stream::run_stream(&mut session, 50_000, &control, |events| {
+                let _ = app.emit("execution-events", StreamBatch { id, events });
+            });
+            let state = session.state();
+            let _ = app.emit("execution-finished", StreamFinished { id, state });
+        }
+        app.state::<Streams>().controls.lock().unwrap().remove(&id);
+    });
+    rx.recv()
+        .map_err(|_| "Stream worker failed".to_string())?
+        .map(|_| id)
+}
+
+#[tauri::command]
+fn pause_stream(streams: tauri::State<'_, Streams>, id: u64) -> Result<(), String> {
+    streams.control(id)?.set_paused(true);
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_stream(streams: tauri::State<'_, Streams>, id: u64) -> Result<(), String> {
+    streams.control(id)?.set_paused(false);
+    Ok(())
+}
+
+/// Instructions per second of stream `id`; 0 for as fast as possible.
+#[tauri::command]
+fn set_stream_speed(streams: tauri::State<'_, Streams>, id: u64, speed: u32) -> Result<(), String> {
+    streams.control(id)?.set_speed(speed);
+    Ok(())
+}
+
+/// End stream `id` early; it still sends `execution-finished`.
+#[tauri::command]
+fn stop_stream(streams: tauri::State<'_, Streams>, id: u64) -> Result<(), String> {
+    streams.control(id)?.stop();
+    Ok(())
+}
+
+fn simulate(
+    code: &str,
+    syntax: &str,
+    input: Vec<i64>,
+    level_id: Option<String>,
+    progress: &dyn Fn(SimulationProgress),
+) -> Result<SimulationResult, String> {
+    let syntax_detection = (syntax == "Auto").then(|| x86_asm::analyze_syntax(code));
+    let syntax_enum = match (&syntax_detection, syntax) {
+        (Some(detection), _) => detection.syntax.clone(),
+        (None, "Intel") => vm::Syntax::Intel,
+        (None, "Att") => vm::Syntax::Att,
         _ => return Err("Invalid syntax type".to_string()),
     };
 
+    let mut metrics = None;
+
     // If level_id is provided, verify against ALL test cases
     if let Some(lid) = level_id {
         if let Some(level) = levels::get_level(&lid) {
-            for (idx, (test_in, expected)) in level.test_cases.iter().enumerate() {
-                println!("\n=== TEST CASE #{} ===", idx + 1);
+            let violations =
+                grader::check_static_constraints(code, &syntax_enum, &level.constraints)?;
+            if !violations.is_empty() {
+                let message = format!("Constraint violated: {}", violations.join("; "));
+                let run = x86_runtime::run_x86_64(code, syntax_enum.clone(), input, 50_000)?;
+                return Ok(SimulationResult {
+                    vm_state: run.state,
+                    success: false,
+                    message,
+                    execution_log: run.execution_log,
+                    metrics: None,
+                    syntax: syntax_enum.clone(),
+                    syntax_detection: syntax_detection.clone(),
+                });
+            }
+
+            let hardcoding_hint = || match grader::output_ignores_input(
+                code,
+                &syntax_enum,
+                &level,
+                grader::DEFAULT_SEED,
+                grader::MAX_INSTRUCTIONS,
+            ) {
+                Ok(true) => " (output is the same for every input; is it hard-coded?)",
+                _ => "",
+            };
+
+            // Every graded case, the generated cases, then the single run below.
+            let total = level.graded_cases().count() + 2;
+            let (mut visible_no, mut hidden_no) = (0usize, 0usize);
+            for (done, (test_in, expected, hidden)) in level.graded_cases().enumerate() {
+                let label = if hidden {
+                    hidden_no += 1;
+                    format!("Hidden Test Case #{}", hidden_no)
+                } else {
+                    visible_no += 1;
+                    format!("Test Case #{}", visible_no)
+                };
+                progress(SimulationProgress {
+                    done,
+                    total,
+                    stage: label.clone(),
+                });
+                println!("\n=== {} ===", label.to_uppercase());
                 println!("Input: {:?}", test_in);
                 println!("Expected: {:?}", expected);
-                let run =
-                    x86_runtime::run_x86_64(code, syntax_enum.clone(), test_in.clone(), 20_000)?;
+                let case = grader::grade_case(
+                    code,
+                    &syntax_enum,
+                    test_in,
+                    expected,
+                    grader::MAX_INSTRUCTIONS,
+                )?;
+                let over_limit = grader::instruction_limit_violation(&level.constraints, &case);
 
                 // Validation
-                let state = run.state;
-                let execution_log = run.execution_log;
+                let state = case.state;
                 let rax = *state.registers.get(&vm::Register::RAX).unwrap_or(&0);
 
                 println!("Final RAX: {}", rax);
                 println!("Final Output: {:?}", state.output);
-                println!("Expected: {:?}", expected);
 
-                let output_correct = if expected.is_empty() {
-                    state.output.is_empty()
-                } else if !state.output.is_empty() {
-                    // Prioritize Stream check if output was produced
-                    state.output.len() >= expected.len()
-                        && &state.output[0..expected.len()] == expected.as_slice()
-                } else if expected.len() == 1 {
-                    // Fallback to RAX if no stream output was produced but we expect 1 value
-                    if state.exited && rax == 60 && expected[0] != 60 {
-                        false
+                if !case.passed {
+                    // Hidden inputs must not reach the frontend.
+                    let message = if hidden {
+                        format!("Failed {}{}", label, hardcoding_hint())
                     } else {
-                        rax == expected[0]
-                    }
-                } else {
-                    false
-                };
-
-                if !output_correct {
-                    let message = format!("Failed Test Case #{}: Input {:?} -> Expected {:?}, Got (RAX={}, Stream={:?})", idx+1, test_in, expected, rax, state.output);
+                        format!(
+                            "Failed {}: Input {:?} -> Expected {:?}, Got (RAX={}, Stream={:?}){}",
+                            label,
+                            test_in,
+                            expected,
+                            rax,
+                            state.output,
+                            hardcoding_hint()
+                        )
+                    };
                     println!("TEST FAILED: {}", message);
+                    // A hidden case's registers, memory and trace would give its input away, so
+                    // show the visible run instead.
+                    let (vm_state, execution_log) = if hidden {
+                        let run =
+                            x86_runtime::run_x86_64(code, syntax_enum.clone(), input, 50_000)?;
+                        (run.state, Vec::new())
+                    } else {
+                        (state, case.execution_log)
+                    };
                     return Ok(SimulationResult {
-                        vm_state: state,
+                        vm_state,
                         success: false,
                         message,
                         execution_log,
+                        metrics: None,
+                        syntax: syntax_enum.clone(),
+                        syntax_detection: syntax_detection.clone(),
+                    });
+                } else if let Some(violation) = over_limit {
+                    let message = format!("Failed {}: {}", label, violation);
+                    println!("TEST FAILED: {}", message);
+                    return Ok(SimulationResult {
+                        vm_state: state,
+                        success: false,
+                        message,
+                        execution_log: case.execution_log,
+                        metrics: None,
+                        syntax: syntax_enum.clone(),
+                        syntax_detection: syntax_detection.clone(),
                     });
                 } else {
                     println!("TEST PASSED");
                 }
             }
+
+            // Seeded random cases catch solutions that only handle the listed inputs.
+            progress(SimulationProgress {
+                done: total - 2,
+                total,
+                stage: "Generated cases".to_string(),
+            });
+            let report = grader::check_property(
+                code,
+                &syntax_enum,
+                &level,
+                grader::DEFAULT_PROPERTY_CASES,
+                grader::DEFAULT_SEED,
+                grader::MAX_INSTRUCTIONS,
+            )?;
+            if let Some(failure) = report.failure {
+                let case = failure.minimal;
+                let message = format!(
+                    "Failed Generated Case #{} (seed 0x{:x}): Input {:?} -> Expected {:?}, Got {:?}{}",
+                    failure.case_index + 1,
+                    failure.seed,
+                    case.input,
+                    case.expected,
+                    case.got,
+                    hardcoding_hint()
+                );
+                return Ok(SimulationResult {
+                    vm_state: case.state,
+                    success: false,
+                    message,
+                    execution_log: case.execution_log,
+                    metrics: None,
+                    syntax: syntax_enum.clone(),
+                    syntax_detection: syntax_detection.clone(),
+                });
+            }
+
+            metrics = Some(grader::measure(
+                code,
+                &syntax_enum,
+                &level,
+                grader::MAX_INSTRUCTIONS,
+            )?);
+            progress(SimulationProgress {
+                done: total - 1,
+                total,
+                stage: "Single run".to_string(),
+            });
         }
     }
 
@@ -185,6 +562,9 @@ fn run_simulation(
         success: true,
         message: "Simulation Complete".to_string(),
         execution_log,
+        metrics,
+        syntax: syntax_enum,
+        syntax_detection,
     })
 }
 
@@ -192,13 +572,72 @@ fn run_simulation(
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .manage(ActiveRuns::default())
+        .manage(Streams::default())
         .invoke_handler(tauri::generate_handler![
             run_simulation,
+            cancel_simulation,
             get_levels,
             get_level_explanation,
             get_level_ini,
-            get_level_collect
+            get_level_collect,
+            get_reference_metrics,
+            translate_code,
+            get_machine_code,
+            get_listing,
+            build_executable,
+            build_object,
+            run_executable,
+            check_calling_convention,
+            read_memory,
+            start_stream,
+            pause_stream,
+            resume_stream,
+            set_stream_speed,
+            stop_stream
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hidden_case_failures_do_not_reveal_the_input() {
+        // Echoes one byte: passes every visible case of 01 but not the three-byte hidden one.
+        let code = "section .bss
+    buf resb 1
+
+section .text
+    global _start
+_start:
+    mov rax, 0
+    mov rdi, 0
+    lea rsi, [buf]
+    mov rdx, 1
+    syscall
+    mov rax, 1
+    mov rdi, 1
+    lea rsi, [buf]
+    mov rdx, 1
+    syscall
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+";
+        let level = levels::get_level("01_Mov&Call").unwrap();
+        let (hidden_input, _) = &level.hidden_test_cases[0];
+        let result = simulate(code, "Intel", vec![7], Some(level.id.clone()), &|_| {}).unwrap();
+        assert!(!result.success);
+        assert!(result.message.starts_with("Failed Hidden Test Case #1"));
+        assert!(result.execution_log.is_empty());
+        assert_eq!(result.vm_state.output, vec![7]);
+        for &value in hidden_input {
+            assert!(!result.vm_state.output.contains(&value));
+            assert!(!result.vm_state.memory.contains(&(value as u8)));
+            assert!(!result.message.contains(&value.to_string()));
+        }
+    }
+}
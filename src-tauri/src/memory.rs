@@ -0,0 +1,143 @@
+use crate::vm::MemoryChange;
+use crate::x86_runtime::Session;
+use serde::{Deserialize, Serialize};
+
+/// Most bytes one page of the memory view may hold.
+pub const MAX_PAGE: usize = 4096;
+
+/// How to show the bytes of a memory page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MemoryFormat {
+    Bytes,
+    /// 2-byte little-endian values.
+    Words,
+    Dwords,
+    Qwords,
+    /// One character per byte; `.` for the unprintable ones.
+    Ascii,
+}
+
+impl MemoryFormat {
+    fn unit(self) -> usize {
+        match self {
+            MemoryFormat::Bytes | MemoryFormat::Ascii => 1,
+            MemoryFormat::Words => 2,
+            MemoryFormat::Dwords => 4,
+            MemoryFormat::Qwords => 8,
+        }
+    }
+}
+
+/// Which bytes the memory view wants, and how to show them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryRequest {
+    pub address: u64,
+    pub length: usize,
+    pub format: MemoryFormat,
+    /// Show values as signed decimal rather than hex.
+    #[serde(default)]
+    pub signed: bool,
+}
+
+/// One value of a page, as shown.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryCell {
+    pub address: u64,
+    /// `0x2a`, `-3` or `A`.
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MemoryPage {
+    pub address: u64,
+    pub bytes: Vec<u8>,
+    /// `bytes` in the requested format; a trailing partial value is left out.
+    pub cells: Vec<MemoryCell>,
+    /// Bytes anywhere in memory that the session's latest step changed, if it tracks them.
+    pub changes: Vec<MemoryChange>,
+}
+
+/// Up to 8 little-endian bytes as a number.
+pub fn le_u64(bytes: &[u8]) -> u64 {
+    let mut raw = [0u8; 8];
+    raw[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(raw)
+}
+
+/// The low `bits` of `value` as a signed number.
+pub fn sign_extend(value: u64, bits: u32) -> i64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64) >> shift
+}
+
+/// `bytes` read at `address` as cells of `format`, signed decimal or unsigned hex.
+pub fn format_cells(
+    address: u64,
+    bytes: &[u8],
+    format: MemoryFormat,
+    signed: bool,
+) -> Vec<MemoryCell> {
+    let unit = format.unit();
+    bytes
+        .chunks_exact(unit)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let text = match format {
+                MemoryFormat::Ascii => match chunk[0] {
+                    b @ 0x20..=0x7e => (b as char).to_string(),
+                    _ => ".".to_string(),
+                },
+                _ if signed => sign_extend(le_u64(chunk), 8 * unit as u32).to_string(),
+                _ => format!("0x{:0width$x}", le_u64(chunk), width = unit * 2),
+            };
+            MemoryCell {
+                address: address + (i * unit) as u64,
+                text,
+            }
+        })
+        .collect()
+}
+
+/// The requested bytes of a session's memory, which must all be mapped.
+pub fn read_page(session: &Session, request: &MemoryRequest) -> Result<MemoryPage, String> {
+    if request.length > MAX_PAGE {
+        return Err(format!(
+            "Cannot read {} bytes at once; pages hold at most {}",
+            request.length, MAX_PAGE
+        ));
+    }
+    let bytes = session.read_memory(request.address, request.length)?;
+    Ok(MemoryPage {
+        address: request.address,
+        cells: format_cells(request.address, &bytes, request.format, request.signed),
+        bytes,
+        changes: session.memory_changes(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_cells_in_each_width() {
+        let bytes = [0x41, 0xff, 0x0a, 0x00];
+        let texts = |format, signed| -> Vec<String> {
+            format_cells(0x10, &bytes, format, signed)
+                .into_iter()
+                .map(|c| c.text)
+                .collect()
+        };
+        assert_eq!(
+            texts(MemoryFormat::Bytes, false),
+            ["0x41", "0xff", "0x0a", "0x00"]
+        );
+        assert_eq!(texts(MemoryFormat::Bytes, true), ["65", "-1", "10", "0"]);
+        assert_eq!(texts(MemoryFormat::Words, false), ["0xff41", "0x000a"]);
+        assert_eq!(texts(MemoryFormat::Words, true), ["-191", "10"]);
+        assert_eq!(texts(MemoryFormat::Ascii, false), ["A", ".", ".", "."]);
+        assert!(texts(MemoryFormat::Qwords, false).is_empty());
+        let cells = format_cells(0x10, &bytes, MemoryFormat::Words, false);
+        assert_eq!(cells[1].address, 0x12);
+    }
+}
@@ -0,0 +1,667 @@
+use crate::grader::{self, SolutionMetrics};
+use crate::levels::Level;
+use crate::vm::Syntax;
+use serde::Serialize;
+use std::time::Instant;
+
+/// Knobs shared by everything that grades a submission outside the app.
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub max_instructions: usize,
+    pub property_cases: usize,
+    pub seed: u64,
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        RunOptions {
+            max_instructions: 50_000,
+            property_cases: grader::DEFAULT_PROPERTY_CASES,
+            seed: grader::DEFAULT_SEED,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CaseReport {
+    /// `1`, `2`, ... for visible cases, `h1`, `h2`, ... for hidden ones, `gen` for a generated one.
+    pub name: String,
+    pub hidden: bool,
+    pub input: Vec<i64>,
+    pub expected: Vec<i64>,
+    pub actual: Vec<i64>,
+    pub passed: bool,
+    /// Assembly failure, VM fault or exceeded instruction limit.
+    pub error: Option<String>,
+    pub instructions_executed: usize,
+    pub duration_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedFailure {
+    /// 0-based index of the first generated case that failed.
+    pub case_index: usize,
+    pub original_input: Vec<i64>,
+    /// The shrunk failing case.
+    pub minimal: CaseReport,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedReport {
+    pub seed: u64,
+    pub cases_run: usize,
+    pub failure: Option<GeneratedFailure>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelReport {
+    pub level_id: String,
+    pub syntax: Syntax,
+    pub constraint_violations: Vec<String>,
+    pub cases: Vec<CaseReport>,
+    /// Only run once every graded case passes; `None` when skipped or the level has no generator.
+    pub generated: Option<GeneratedReport>,
+    /// Set when some case failed and the output does not depend on the input at all.
+    pub hardcoded: bool,
+    /// Only measured for passing solutions.
+    pub metrics: Option<SolutionMetrics>,
+}
+
+impl LevelReport {
+    pub fn failed_cases(&self) -> usize {
+        self.cases.iter().filter(|c| !c.passed).count()
+    }
+
+    pub fn passed(&self) -> bool {
+        self.constraint_violations.is_empty()
+            && self.failed_cases() == 0
+            && self
+                .generated
+                .as_ref()
+                .is_none_or(|g| g.failure.is_none() && g.error.is_none())
+    }
+}
+
+/// Grade one case; the caller fills in `name` and `hidden`.
+fn case_report(
+    code: &str,
+    syntax: &Syntax,
+    level: &Level,
+    input: &[i64],
+    expected: &[i64],
+    max_instructions: usize,
+) -> CaseReport {
+    let started = Instant::now();
+    let graded = grader::grade_case(code, syntax, input, expected, max_instructions);
+    let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    match graded {
+        Ok(case) => {
+            let error = case
+                .state
+                .error
+                .clone()
+                .or_else(|| grader::instruction_limit_violation(&level.constraints, &case));
+            CaseReport {
+                name: String::new(),
+                hidden: false,
+                input: case.input,
+                expected: case.expected,
+                actual: case.got,
+                passed: case.passed && error.is_none(),
+                error,
+                instructions_executed: case.state.instructions_executed,
+                duration_ms,
+            }
+        }
+        Err(e) => CaseReport {
+            name: String::new(),
+            hidden: false,
+            input: input.to_vec(),
+            expected: expected.to_vec(),
+            actual: vec![],
+            passed: false,
+            error: Some(e),
+            instructions_executed: 0,
+            duration_ms,
+        },
+    }
+}
+
+/// Grade `code` the same way `run_simulation` does, collecting every result instead of stopping
+/// at the first failure.
+pub fn evaluate(code: &str, syntax: &Syntax, level: &Level, options: &RunOptions) -> LevelReport {
+    let constraint_violations = grader::check_static_constraints(code, syntax, &level.constraints)
+        .unwrap_or_else(|e| vec![e]);
+
+    let (mut visible_no, mut hidden_no) = (0usize, 0usize);
+    let mut cases = Vec::new();
+    for (input, expected, hidden) in level.graded_cases() {
+        let name = if hidden {
+            hidden_no += 1;
+            format!("h{}", hidden_no)
+        } else {
+            visible_no += 1;
+            visible_no.to_string()
+        };
+        cases.push(CaseReport {
+            name,
+            hidden,
+            ..case_report(
+                code,
+                syntax,
+                level,
+                input,
+                expected,
+                options.max_instructions,
+            )
+        });
+    }
+
+    let mut report = LevelReport {
+        level_id: level.id.clone(),
+        syntax: syntax.clone(),
+        constraint_violations,
+        cases,
+        generated: None,
+        hardcoded: false,
+        metrics: None,
+    };
+
+    if report.failed_cases() != 0 {
+        report.hardcoded = matches!(
+            grader::output_ignores_input(
+                code,
+                syntax,
+                level,
+                options.seed,
+                options.max_instructions
+            ),
+            Ok(true)
+        );
+        return report;
+    }
+    if !report.constraint_violations.is_empty() {
+        return report;
+    }
+
+    report.generated = match grader::check_property(
+        code,
+        syntax,
+        level,
+        options.property_cases,
+        options.seed,
+        options.max_instructions,
+    ) {
+        Ok(property) if property.cases_run == 0 => None,
+        Ok(property) => Some(GeneratedReport {
+            seed: options.seed,
+            cases_run: property.cases_run,
+            failure: property.failure.map(|f| GeneratedFailure {
+                case_index: f.case_index,
+                original_input: f.original_input,
+                minimal: CaseReport {
+                    name: "gen".to_string(),
+                    hidden: false,
+                    input: f.minimal.input,
+                    expected: f.minimal.expected,
+                    actual: f.minimal.got,
+                    passed: false,
+                    error: f.minimal.state.error,
+                    instructions_executed: f.minimal.state.instructions_executed,
+                    duration_ms: 0.0,
+                },
+            }),
+            error: None,
+        }),
+        Err(e) => Some(GeneratedReport {
+            seed: options.seed,
+            cases_run: 0,
+            failure: None,
+            error: Some(e),
+        }),
+    };
+
+    if report.passed() {
+        report.metrics = grader::measure(code, syntax, level, options.max_instructions).ok();
+    }
+    report
+}
+
+fn generated_passed(report: &LevelReport) -> Option<bool> {
+    report
+        .generated
+        .as_ref()
+        .map(|g| g.failure.is_none() && g.error.is_none())
+}
+
+/// What changed between two runs of the same level, e.g. `case 3 now passes` or
+/// `instructions 412 → 388`. Empty when nothing observable changed.
+pub fn diff(prev: &LevelReport, next: &LevelReport) -> Vec<String> {
+    let mut changes = Vec::new();
+    let (before, after) = (
+        prev.constraint_violations.len(),
+        next.constraint_violations.len(),
+    );
+    if before != after {
+        changes.push(format!("constraint violations {} → {}", before, after));
+    }
+    for case in &next.cases {
+        match prev.cases.iter().find(|c| c.name == case.name) {
+            Some(old) if old.passed != case.passed => changes.push(format!(
+                "case {} now {}",
+                case.name,
+                if case.passed { "passes" } else { "fails" }
+            )),
+            _ => {}
+        }
+    }
+    let total =
+        |r: &LevelReport| -> usize { r.cases.iter().map(|c| c.instructions_executed).sum() };
+    if total(prev) != total(next) {
+        changes.push(format!("instructions {} → {}", total(prev), total(next)));
+    }
+    match (generated_passed(prev), generated_passed(next)) {
+        (Some(a), Some(b)) if a != b => changes.push(format!(
+            "generated cases now {}",
+            if b { "pass" } else { "fail" }
+        )),
+        (None, Some(b)) => changes.push(format!(
+            "generated cases {}",
+            if b { "pass" } else { "fail" }
+        )),
+        _ => {}
+    }
+    if let (Some(a), Some(b)) = (&prev.metrics, &next.metrics) {
+        if a.code_bytes != b.code_bytes {
+            changes.push(format!("bytes {} → {}", a.code_bytes, b.code_bytes));
+        }
+        if a.source_lines != b.source_lines {
+            changes.push(format!("lines {} → {}", a.source_lines, b.source_lines));
+        }
+        if a.registers_used != b.registers_used {
+            changes.push(format!(
+                "registers {} → {}",
+                a.registers_used, b.registers_used
+            ));
+        }
+    }
+    if prev.passed() != next.passed() {
+        changes.push(
+            if next.passed() {
+                "level now passes"
+            } else {
+                "level now fails"
+            }
+            .to_string(),
+        );
+    }
+    changes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Human,
+    Json,
+    Junit,
+    Tap,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Format> {
+        match s.to_lowercase().as_str() {
+            "human" => Some(Format::Human),
+            "json" => Some(Format::Json),
+            "junit" => Some(Format::Junit),
+            "tap" => Some(Format::Tap),
+            _ => None,
+        }
+    }
+
+    pub fn render(self, report: &LevelReport) -> String {
+        match self {
+            Format::Human => to_human(report),
+            Format::Json => to_json(report),
+            Format::Junit => to_junit(report),
+            Format::Tap => to_tap(report),
+        }
+    }
+}
+
+pub fn to_human(report: &LevelReport) -> String {
+    let mut out = String::new();
+    for v in &report.constraint_violations {
+        out.push_str(&format!("[constraint] FAIL: {}\n", v));
+    }
+    for case in &report.cases {
+        match (&case.error, case.passed) {
+            (_, true) => out.push_str(&format!("[{}] PASS\n", case.name)),
+            (Some(err), false) => out.push_str(&format!(
+                "[{}] FAIL: input {:?}: {}\n",
+                case.name, case.input, err
+            )),
+            (None, false) => out.push_str(&format!(
+                "[{}] FAIL: input {:?}\n  expected: {:?}\n  got:      {:?}\n",
+                case.name, case.input, case.expected, case.actual
+            )),
+        }
+    }
+
+    let failures = report.failed_cases();
+    if failures != 0 {
+        if report.hardcoded {
+            out.push_str(
+                "note: output is the same for every input; the solution may be hard-coded.\n",
+            );
+        }
+        out.push_str(&format!(
+            "FAILED {} / {} test case(s).\n",
+            failures,
+            report.cases.len()
+        ));
+        return out;
+    }
+    if !report.constraint_violations.is_empty() {
+        out.push_str(&format!(
+            "FAILED {} constraint(s).\n",
+            report.constraint_violations.len()
+        ));
+        return out;
+    }
+
+    if let Some(generated) = &report.generated {
+        if let Some(e) = &generated.error {
+            out.push_str(&format!("[gen] FAIL: {}\n", e));
+        } else if let Some(failure) = &generated.failure {
+            let case = &failure.minimal;
+            out.push_str(&format!(
+                "[gen] FAIL: generated case #{} (seed 0x{:x}), minimal input {:?}\n  original: {:?}\n  expected: {:?}\n  got:      {:?}\n",
+                failure.case_index + 1,
+                generated.seed,
+                case.input,
+                failure.original_input,
+                case.expected,
+                case.actual
+            ));
+            if let Some(err) = &case.error {
+                out.push_str(&format!("  vm error: {}\n", err));
+            }
+        } else {
+            out.push_str(&format!(
+                "[gen] PASS {} generated case(s) (seed 0x{:x})\n",
+                generated.cases_run, generated.seed
+            ));
+        }
+    }
+
+    if let Some(m) = &report.metrics {
+        out.push_str(&format!(
+            "[metrics] instructions={} bytes={} lines={} registers={}\n",
+            m.instructions_executed, m.code_bytes, m.source_lines, m.registers_used
+        ));
+    }
+    out
+}
+
+pub fn to_json(report: &LevelReport) -> String {
+    serde_json::to_string_pretty(report).unwrap_or_default()
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn failure_detail(case: &CaseReport) -> String {
+    let mut detail = format!(
+        "input: {:?}\nexpected: {:?}\nactual: {:?}",
+        case.input, case.expected, case.actual
+    );
+    if let Some(err) = &case.error {
+        detail.push_str(&format!("\nerror: {}", err));
+    }
+    detail
+}
+
+struct JunitCase {
+    name: String,
+    seconds: f64,
+    /// (message attribute, body)
+    failure: Option<(String, String)>,
+}
+
+pub fn to_junit(report: &LevelReport) -> String {
+    let mut testcases = Vec::new();
+    if !report.constraint_violations.is_empty() {
+        testcases.push(JunitCase {
+            name: "constraints".to_string(),
+            seconds: 0.0,
+            failure: Some((
+                format!(
+                    "{} constraint(s) violated",
+                    report.constraint_violations.len()
+                ),
+                report.constraint_violations.join("\n"),
+            )),
+        });
+    }
+    for case in &report.cases {
+        testcases.push(JunitCase {
+            name: format!("case {}", case.name),
+            seconds: case.duration_ms / 1000.0,
+            failure: (!case.passed).then(|| {
+                (
+                    case.error
+                        .clone()
+                        .unwrap_or_else(|| "unexpected output".to_string()),
+                    failure_detail(case),
+                )
+            }),
+        });
+    }
+    if let Some(generated) = &report.generated {
+        testcases.push(JunitCase {
+            name: format!("generated (seed 0x{:x})", generated.seed),
+            seconds: 0.0,
+            failure: match (&generated.error, &generated.failure) {
+                (Some(e), _) => Some((e.clone(), e.clone())),
+                (None, Some(f)) => Some((
+                    format!("generated case #{} failed", f.case_index + 1),
+                    failure_detail(&f.minimal),
+                )),
+                (None, None) => None,
+            },
+        });
+    }
+
+    let failures = testcases.iter().filter(|t| t.failure.is_some()).count();
+    let total_time: f64 = testcases.iter().map(|t| t.seconds).sum();
+    let classname = xml_escape(&format!("{}.{:?}", report.level_id, report.syntax));
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!(
+        "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\">\n",
+        xml_escape(&report.level_id),
+        testcases.len(),
+        failures,
+        total_time
+    ));
+    for case in testcases {
+        let open = format!(
+            "  <testcase classname=\"{}\" name=\"{}\" time=\"{:.6}\"",
+            classname,
+            xml_escape(&case.name),
+            case.seconds
+        );
+        match case.failure {
+            None => out.push_str(&format!("{}/>\n", open)),
+            Some((message, body)) => out.push_str(&format!(
+                "{}>\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                open,
+                xml_escape(&message),
+                xml_escape(&body)
+            )),
+        }
+    }
+    out.push_str("</testsuite>\n");
+    out
+}
+
+fn tap_yaml(case: &CaseReport) -> String {
+    let mut out = String::from("  ---\n");
+    out.push_str(&format!("  input: {:?}\n", case.input));
+    out.push_str(&format!("  expected: {:?}\n", case.expected));
+    out.push_str(&format!("  actual: {:?}\n", case.actual));
+    if let Some(err) = &case.error {
+        out.push_str(&format!("  error: {:?}\n", err));
+    }
+    out.push_str(&format!(
+        "  instructions_executed: {}\n",
+        case.instructions_executed
+    ));
+    out.push_str(&format!("  duration_ms: {:.3}\n", case.duration_ms));
+    out.push_str("  ...\n");
+    out
+}
+
+pub fn to_tap(report: &LevelReport) -> String {
+    // (ok, description, YAML diagnostics)
+    let mut points: Vec<(bool, String, Option<String>)> = Vec::new();
+    if !report.constraint_violations.is_empty() {
+        let mut yaml = String::from("  ---\n");
+        for v in &report.constraint_violations {
+            yaml.push_str(&format!("  - {:?}\n", v));
+        }
+        yaml.push_str("  ...\n");
+        points.push((false, "constraints".to_string(), Some(yaml)));
+    }
+    for case in &report.cases {
+        points.push((
+            case.passed,
+            format!("case {}", case.name),
+            (!case.passed).then(|| tap_yaml(case)),
+        ));
+    }
+    if let Some(generated) = &report.generated {
+        let title = format!(
+            "{} generated case(s) (seed 0x{:x})",
+            generated.cases_run, generated.seed
+        );
+        points.push(match (&generated.error, &generated.failure) {
+            (Some(e), _) => (
+                false,
+                title,
+                Some(format!("  ---\n  error: {:?}\n  ...\n", e)),
+            ),
+            (None, Some(f)) => (false, title, Some(tap_yaml(&f.minimal))),
+            (None, None) => (true, title, None),
+        });
+    }
+
+    let mut out = format!("TAP version 13\n1..{}\n", points.len());
+    for (i, (ok, description, yaml)) in points.iter().enumerate() {
+        let status = if *ok { "ok" } else { "not ok" };
+        out.push_str(&format!("{} {} - {}\n", status, i + 1, description));
+        if let Some(yaml) = yaml {
+            out.push_str(yaml);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> LevelReport {
+        LevelReport {
+            level_id: "02_Addition".to_string(),
+            syntax: Syntax::Intel,
+            constraint_violations: vec![],
+            cases: vec![
+                CaseReport {
+                    name: "1".to_string(),
+                    hidden: false,
+                    input: vec![1],
+                    expected: vec![2],
+                    actual: vec![2],
+                    passed: true,
+                    error: None,
+                    instructions_executed: 12,
+                    duration_ms: 0.5,
+                },
+                CaseReport {
+                    name: "h1".to_string(),
+                    hidden: true,
+                    input: vec![3],
+                    expected: vec![4],
+                    actual: vec![3],
+                    passed: false,
+                    error: None,
+                    instructions_executed: 12,
+                    duration_ms: 0.5,
+                },
+            ],
+            generated: None,
+            hardcoded: false,
+            metrics: None,
+        }
+    }
+
+    #[test]
+    fn parses_format_names() {
+        assert_eq!(Format::parse("JSON"), Some(Format::Json));
+        assert_eq!(Format::parse("tap"), Some(Format::Tap));
+        assert_eq!(Format::parse("xml"), None);
+    }
+
+    #[test]
+    fn json_carries_per_case_data() {
+        let value: serde_json::Value = serde_json::from_str(&to_json(&sample())).unwrap();
+        let case = &value["cases"][1];
+        assert_eq!(case["name"], "h1");
+        assert_eq!(case["actual"], serde_json::json!([3]));
+        assert_eq!(case["instructions_executed"], 12);
+        assert_eq!(value["syntax"], "Intel");
+    }
+
+    #[test]
+    fn junit_counts_failures() {
+        let xml = to_junit(&sample());
+        assert!(xml.contains("tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<failure message=\"unexpected output\">"));
+    }
+
+    #[test]
+    fn tap_numbers_each_result() {
+        let tap = to_tap(&sample());
+        assert!(tap.starts_with("TAP version 13\n1..2\nok 1 - case 1\nnot ok 2 - case h1\n"));
+        assert!(tap.contains("  actual: [3]\n"));
+    }
+
+    #[test]
+    fn human_summary_matches_stage_runner() {
+        let human = to_human(&sample());
+        assert!(human.contains("[1] PASS\n"));
+        assert!(human.ends_with("FAILED 1 / 2 test case(s).\n"));
+    }
+
+    #[test]
+    fn diff_reports_flipped_cases_and_instruction_totals() {
+        let prev = sample();
+        let mut next = sample();
+        next.cases[1].passed = true;
+        next.cases[1].actual = vec![4];
+        next.cases[0].instructions_executed = 10;
+        assert_eq!(
+            diff(&prev, &next),
+            vec![
+                "case h1 now passes".to_string(),
+                "instructions 24 → 22".to_string(),
+                "level now passes".to_string(),
+            ]
+        );
+        assert!(diff(&prev, &prev).is_empty());
+    }
+}
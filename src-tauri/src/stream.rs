@@ -0,0 +1,289 @@
+use crate::vm::Register;
+use crate::x86_runtime::Session;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// Events of an unthrottled stream are sent in batches at most this often.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// Something a streamed run did, for the "watch it run" animation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind")]
+pub enum ExecutionEvent {
+    /// An instruction ran; `count` is how many have run so far.
+    Instruction {
+        address: u64,
+        line: Option<usize>,
+        text: String,
+        count: usize,
+    },
+    RegisterChange {
+        register: Register,
+        old: u64,
+        new: u64,
+    },
+    /// A byte the program wrote, sign-extended like `VmState::output`.
+    Output { value: i64 },
+    /// A `syscall` with RAX = `number`, before it ran.
+    Syscall { number: u64, name: String },
+    /// Bytes the instruction changed, if the session tracks memory changes.
+    MemoryChange {
+        address: u64,
+        old: Vec<u8>,
+        new: Vec<u8>,
+    },
+}
+
+/// `read`, `write`, ... for the syscalls the runtime serves.
+fn syscall_name(number: u64) -> &'static str {
+    match number {
+        0 => "read",
+        1 => "write",
+        9 => "mmap",
+        11 => "munmap",
+        12 => "brk",
+        60 => "exit",
+        1000 => "in",
+        _ => "unknown",
+    }
+}
+
+#[derive(Default)]
+struct Flags {
+    paused: bool,
+    stopped: bool,
+}
+
+/// Pause, resume, speed and stop of a stream, shared with the thread running it.
+#[derive(Default)]
+pub struct StreamControl {
+    flags: Mutex<Flags>,
+    changed: Condvar,
+    /// Instructions per second; 0 runs as fast as the emulator goes.
+    speed: AtomicU32,
+}
+
+impl StreamControl {
+    pub fn new(speed: u32) -> StreamControl {
+        StreamControl {
+            speed: AtomicU32::new(speed),
+            ..StreamControl::default()
+        }
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.flags.lock().unwrap().paused = paused;
+        self.changed.notify_all();
+    }
+
+    pub fn stop(&self) {
+        self.flags.lock().unwrap().stopped = true;
+        self.changed.notify_all();
+    }
+
+    pub fn set_speed(&self, speed: u32) {
+        self.speed.store(speed, Ordering::Relaxed);
+    }
+
+    fn is_paused(&self) -> bool {
+        self.flags.lock().unwrap().paused
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.flags.lock().unwrap().stopped
+    }
+
+    /// Block while paused; false once stopped.
+    fn wait_while_paused(&self) -> bool {
+        let mut flags = self.flags.lock().unwrap();
+        while flags.paused && !flags.stopped {
+            flags = self.changed.wait(flags).unwrap();
+        }
+        !flags.stopped
+    }
+
+    /// Sleep for `delay` unless stopped or paused in the meantime.
+    fn sleep(&self, delay: Duration) {
+        let flags = self.flags.lock().unwrap();
+        let _ = self
+            .changed
+            .wait_timeout_while(flags, delay, |f| !f.paused && !f.stopped);
+    }
+
+    fn delay(&self) -> Duration {
+        match self.speed.load(Ordering::Relaxed) {
+            0 => Duration::ZERO,
+            speed => Duration::from_secs(1) / speed,
+        }
+    }
+}
+
+/// Run one instruction and describe what it did.
+pub fn step_events(session: &mut Session, count: usize) -> Vec<ExecutionEvent> {
+    let pc = session.rip();
+    let inst = session.instruction_at(pc).cloned();
+    let before = session.registers();
+    let output_len = session.output().len();
+    let is_syscall = session.read_memory(pc, 2).is_ok_and(|b| b == [0x0f, 0x05]);
+
+    let mut events = vec![ExecutionEvent::Instruction {
+        address: pc,
+        line: inst.as_ref().map(|i| i.line),
+        text: inst.map_or_else(String::new, |i| i.text),
+        count,
+    }];
+    if is_syscall {
+        let number = session.register(Register::RAX);
+        events.push(ExecutionEvent::Syscall {
+            number,
+            name: syscall_name(number).to_string(),
+        });
+    }
+    session.step();
+    events.extend(
+        session.output()[output_len..]
+            .iter()
+            .map(|&value| ExecutionEvent::Output { value }),
+    );
+    events.extend(
+        session
+            .memory_changes()
+            .into_iter()
+            .map(|c| ExecutionEvent::MemoryChange {
+                address: c.address,
+                old: c.old,
+                new: c.new,
+            }),
+    );
+    for ((register, old), (_, new)) in before.into_iter().zip(session.registers()) {
+        if old != new {
+            events.push(ExecutionEvent::RegisterChange { register, old, new });
+        }
+    }
+    events
+}
+
+/// Run `session` for up to `max_instructions`, handing its events to `emit` in batches: one per
+/// instruction at the control's speed, or one per frame when unthrottled. Returns when the
+/// program stops, the limit is reached or the stream is stopped; pausing blocks in between.
+pub fn run_stream(
+    session: &mut Session,
+    max_instructions: usize,
+    control: &StreamControl,
+    mut emit: impl FnMut(Vec<ExecutionEvent>),
+) {
+    let mut batch = Vec::new();
+    let mut last_emit = Instant::now();
+    let mut count = 0;
+    while count < max_instructions && !session.is_finished() && !session.is_past_end() {
+        if control.is_paused() {
+            if !batch.is_empty() {
+                emit(std::mem::take(&mut batch));
+            }
+            if !control.wait_while_paused() {
+                break;
+            }
+            continue;
+        }
+        if control.is_stopped() {
+            break;
+        }
+        count += 1;
+        batch.extend(step_events(session, count));
+        let delay = control.delay();
+        if !delay.is_zero() || last_emit.elapsed() >= FRAME {
+            emit(std::mem::take(&mut batch));
+            last_emit = Instant::now();
+        }
+        if !delay.is_zero() {
+            control.sleep(delay);
+        }
+    }
+    if !batch.is_empty() {
+        emit(batch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::Syntax;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn speed_sets_the_delay_between_instructions() {
+        let control = StreamControl::new(0);
+        assert_eq!(control.delay(), Duration::ZERO);
+        control.set_speed(4);
+        assert_eq!(control.delay(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn pause_blocks_until_resumed_or_stopped() {
+        let control = Arc::new(StreamControl::new(0));
+        control.set_paused(true);
+        let waiter = {
+            let control = control.clone();
+            thread::spawn(move || control.wait_while_paused())
+        };
+        control.set_paused(false);
+        assert!(waiter.join().unwrap());
+
+        control.set_paused(true);
+        let waiter = {
+            let control = control.clone();
+            thread::spawn(move || control.wait_while_paused())
+        };
+        control.stop();
+        assert!(!waiter.join().unwrap());
+    }
+
+    #[test]
+    fn streams_instructions_syscalls_and_output() {
+        let code = "section .bss
+    buf resb 1
+
+section .text
+_start:
+    mov byte [buf], 65
+    mov rax, 1
+    mov rdi, 1
+    lea rsi, [buf]
+    mov rdx, 1
+    syscall
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+";
+        let mut session = Session::new(code, Syntax::Intel, vec![], false).unwrap();
+        session.track_memory_changes().unwrap();
+        let buf = session.bss_labels()["buf"];
+        let mut events = Vec::new();
+        run_stream(&mut session, 100, &StreamControl::new(0), |batch| {
+            events.extend(batch)
+        });
+        let instructions = events
+            .iter()
+            .filter(|e| matches!(e, ExecutionEvent::Instruction { .. }))
+            .count();
+        assert_eq!(instructions, 9);
+        assert!(events.contains(&ExecutionEvent::Syscall {
+            number: 1,
+            name: "write".to_string()
+        }));
+        assert!(events.contains(&ExecutionEvent::Output { value: 65 }));
+        assert!(events.contains(&ExecutionEvent::MemoryChange {
+            address: buf,
+            old: vec![0],
+            new: vec![65]
+        }));
+        assert!(events.contains(&ExecutionEvent::RegisterChange {
+            register: Register::RAX,
+            old: 0,
+            new: 1
+        }));
+    }
+}
@@ -0,0 +1,656 @@
+//! Source-to-source translation between NASM-flavoured Intel syntax and AT&T syntax.
+//!
+//! Only `.text` instructions are rewritten; labels, section/global directives and `.bss`
+//! reservations are shared by both syntaxes here and pass through unchanged. Comments keep
+//! their column and switch between `;` and `#`.
+
+use crate::vm::Syntax;
+use crate::x86_asm::{register_family, split_operands, SUFFIXABLE};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operand {
+    /// Lowercase register name without `%`.
+    Reg(String),
+    /// Immediate as Intel writes it: a number, a `'c'` literal or a label.
+    Imm(String),
+    Mem {
+        /// Bits, from an Intel size keyword.
+        size: Option<u32>,
+        base: Option<String>,
+        index: Option<(String, u32)>,
+        /// Displacement expression, e.g. `buf+2` or `-8`; empty when there is none.
+        disp: String,
+    },
+    /// Branch target label.
+    Target(String),
+    /// `jmp rax` / `jmp *%rax`.
+    Indirect(Box<Operand>),
+}
+
+fn register_width(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    register_family(&name)?;
+    let numbered = name[1..].starts_with(|c: char| c.is_ascii_digit());
+    Some(match name.len() {
+        _ if numbered => match name.chars().last() {
+            Some('d') => 32,
+            Some('w') => 16,
+            Some('b') => 8,
+            _ => 64,
+        },
+        3 if name.starts_with('r') => 64,
+        3 if name.starts_with('e') => 32,
+        3 => 8,
+        _ if name.ends_with(['l', 'h']) => 8,
+        _ => 16,
+    })
+}
+
+fn suffix(bits: u32) -> char {
+    match bits {
+        8 => 'b',
+        16 => 'w',
+        32 => 'l',
+        _ => 'q',
+    }
+}
+
+fn size_keyword(bits: u32) -> &'static str {
+    match bits {
+        8 => "byte",
+        16 => "word",
+        32 => "dword",
+        _ => "qword",
+    }
+}
+
+fn is_branch(mnemonic: &str) -> bool {
+    mnemonic.starts_with('j') || mnemonic.starts_with("loop") || mnemonic.starts_with("call")
+}
+
+/// A number, a `'c'` literal, a label, or a `+`/`-` expression of those.
+fn is_plain_value(s: &str) -> bool {
+    let quoted = s.len() == 3 && s.starts_with('\'') && s.ends_with('\'');
+    !s.is_empty()
+        && (quoted
+            || s.split(['+', '-'])
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .all(|t| {
+                    t.chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+                }))
+}
+
+fn parse_intel_operand(op: &str, branch: bool) -> Result<Operand, String> {
+    let op = op.trim();
+    if register_family(op).is_some() {
+        let reg = Operand::Reg(op.to_lowercase());
+        return Ok(if branch {
+            Operand::Indirect(Box::new(reg))
+        } else {
+            reg
+        });
+    }
+    let Some(open) = op.find('[') else {
+        if !is_plain_value(op) {
+            return Err(format!("cannot translate operand `{}`", op));
+        }
+        return Ok(if branch {
+            Operand::Target(op.to_string())
+        } else {
+            Operand::Imm(op.to_string())
+        });
+    };
+    if !op.ends_with(']') {
+        return Err(format!("cannot translate operand `{}`", op));
+    }
+
+    let mut size = None;
+    for word in op[..open].split_whitespace() {
+        match word.to_lowercase().as_str() {
+            "byte" => size = Some(8),
+            "word" => size = Some(16),
+            "dword" => size = Some(32),
+            "qword" => size = Some(64),
+            "ptr" => {}
+            other => return Err(format!("`{}` has no AT&T equivalent", other)),
+        }
+    }
+
+    let (mut base, mut index, mut disp) = (None, None, String::new());
+    let inside = &op[open + 1..op.len() - 1];
+    let mut sign = '+';
+    let mut rest = inside.trim();
+    while !rest.is_empty() {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = rest[..end].trim();
+        if term.is_empty() && end == 0 {
+            // leading sign, as in `[-8]`
+        } else if let Some((a, b)) = term.split_once('*') {
+            let (reg, scale) = match (register_family(a.trim()), register_family(b.trim())) {
+                (Some(_), None) => (a.trim(), b.trim()),
+                (None, Some(_)) => (b.trim(), a.trim()),
+                _ => return Err(format!("cannot translate memory operand `{}`", op)),
+            };
+            match scale.parse::<u32>() {
+                Ok(s @ (1 | 2 | 4 | 8)) if sign == '+' && index.is_none() => {
+                    index = Some((reg.to_lowercase(), s))
+                }
+                _ => return Err(format!("cannot translate memory operand `{}`", op)),
+            }
+        } else if register_family(term).is_some() {
+            if sign == '-' {
+                return Err(format!("cannot translate memory operand `{}`", op));
+            }
+            if base.is_none() {
+                base = Some(term.to_lowercase());
+            } else if index.is_none() {
+                index = Some((term.to_lowercase(), 1));
+            } else {
+                return Err(format!("too many registers in `{}`", op));
+            }
+        } else if is_plain_value(term) {
+            if !disp.is_empty() || sign == '-' {
+                disp.push(sign);
+            }
+            disp.push_str(term);
+        } else {
+            return Err(format!("cannot translate memory operand `{}`", op));
+        }
+        if end == rest.len() {
+            break;
+        }
+        sign = rest[end..].chars().next().unwrap_or('+');
+        rest = rest[end + 1..].trim();
+    }
+
+    let mem = Operand::Mem {
+        size,
+        base,
+        index,
+        disp,
+    };
+    Ok(if branch {
+        Operand::Indirect(Box::new(mem))
+    } else {
+        mem
+    })
+}
+
+fn parse_att_operand(op: &str, branch: bool) -> Result<Operand, String> {
+    let op = op.trim();
+    if branch {
+        return match op.strip_prefix('*') {
+            Some(inner) => Ok(Operand::Indirect(Box::new(parse_att_operand(
+                inner, false,
+            )?))),
+            None if is_plain_value(op) => Ok(Operand::Target(op.to_string())),
+            None => Err(format!("cannot translate operand `{}`", op)),
+        };
+    }
+    if let Some(reg) = op.strip_prefix('%') {
+        if register_family(reg).is_none() {
+            return Err(format!("unknown register `{}`", op));
+        }
+        return Ok(Operand::Reg(reg.to_lowercase()));
+    }
+    if let Some(imm) = op.strip_prefix('$') {
+        if !is_plain_value(imm) {
+            return Err(format!("cannot translate operand `{}`", op));
+        }
+        return Ok(Operand::Imm(imm.to_string()));
+    }
+
+    let (disp, regs) = match op.find('(') {
+        Some(open) if op.ends_with(')') => (&op[..open], Some(&op[open + 1..op.len() - 1])),
+        Some(_) => return Err(format!("cannot translate memory operand `{}`", op)),
+        None => (op, None),
+    };
+    let disp = disp.trim();
+    if !disp.is_empty() && !is_plain_value(disp) {
+        return Err(format!("cannot translate memory operand `{}`", op));
+    }
+    let (mut base, mut index) = (None, None);
+    if let Some(regs) = regs {
+        let parts: Vec<&str> = regs.split(',').map(str::trim).collect();
+        let reg = |s: &str| -> Result<Option<String>, String> {
+            if s.is_empty() {
+                return Ok(None);
+            }
+            match s.strip_prefix('%') {
+                Some(r) if register_family(r).is_some() => Ok(Some(r.to_lowercase())),
+                _ => Err(format!("cannot translate memory operand `{}`", op)),
+            }
+        };
+        base = reg(parts[0])?;
+        if let Some(i) = parts.get(1) {
+            let scale = match parts.get(2) {
+                Some(s) => match s.parse::<u32>() {
+                    Ok(s @ (1 | 2 | 4 | 8)) => s,
+                    _ => return Err(format!("bad scale in `{}`", op)),
+                },
+                None => 1,
+            };
+            index = reg(i)?.map(|r| (r, scale));
+        }
+        if parts.len() > 3 {
+            return Err(format!("cannot translate memory operand `{}`", op));
+        }
+    } else if disp.is_empty() {
+        return Err(format!("cannot translate operand `{}`", op));
+    }
+    Ok(Operand::Mem {
+        size: None,
+        base,
+        index,
+        disp: disp.to_string(),
+    })
+}
+
+fn render_att(op: &Operand) -> String {
+    match op {
+        Operand::Reg(r) => format!("%{}", r),
+        Operand::Imm(v) => match v.strip_prefix('\'') {
+            // Character literals are not portable between the two front ends; use the code.
+            Some(c) => format!("$0x{:x}", c.chars().next().map_or(0, |c| c as u32)),
+            None => format!("${}", v),
+        },
+        Operand::Mem {
+            base, index, disp, ..
+        } => {
+            let mut out = disp.clone();
+            if base.is_some() || index.is_some() {
+                out.push('(');
+                if let Some(b) = base {
+                    out.push_str(&format!("%{}", b));
+                }
+                if let Some((i, scale)) = index {
+                    out.push_str(&format!(",%{},{}", i, scale));
+                }
+                out.push(')');
+            }
+            out
+        }
+        Operand::Target(t) => t.clone(),
+        Operand::Indirect(inner) => format!("*{}", render_att(inner)),
+    }
+}
+
+fn render_intel(op: &Operand, keyword: Option<u32>) -> String {
+    match op {
+        Operand::Reg(r) => r.clone(),
+        Operand::Imm(v) | Operand::Target(v) => v.clone(),
+        Operand::Mem {
+            base, index, disp, ..
+        } => {
+            let mut terms: Vec<String> = Vec::new();
+            if let Some(b) = base {
+                terms.push(b.clone());
+            }
+            if let Some((i, scale)) = index {
+                terms.push(match scale {
+                    1 => i.clone(),
+                    s => format!("{}*{}", i, s),
+                });
+            }
+            let mut inside = terms.join(" + ");
+            if !disp.is_empty() {
+                match (inside.is_empty(), disp.strip_prefix('-')) {
+                    (true, _) => inside.push_str(disp),
+                    (false, Some(neg)) => inside.push_str(&format!(" - {}", neg)),
+                    (false, None) => inside.push_str(&format!(" + {}", disp)),
+                }
+            }
+            match keyword {
+                Some(bits) => format!("{} [{}]", size_keyword(bits), inside),
+                None => format!("[{}]", inside),
+            }
+        }
+        Operand::Indirect(inner) => render_intel(inner, keyword),
+    }
+}
+
+fn split_mnemonic(inst: &str) -> (&str, &str) {
+    let inst = inst.trim();
+    match inst.find(char::is_whitespace) {
+        Some(i) => (&inst[..i], inst[i..].trim()),
+        None => (inst, ""),
+    }
+}
+
+fn intel_to_att(inst: &str) -> Result<String, String> {
+    let (mnemonic, rest) = split_mnemonic(inst);
+    let m = mnemonic.to_lowercase();
+    let branch = is_branch(&m);
+    let ops = split_operands(rest)
+        .into_iter()
+        .map(|op| parse_intel_operand(op, branch))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let reg_width = |op: &Operand| match op {
+        Operand::Reg(r) => register_width(r),
+        _ => None,
+    };
+    let mem_size = ops.iter().find_map(|op| match op {
+        Operand::Mem { size, .. } => *size,
+        _ => None,
+    });
+
+    let att_mnemonic = match m.as_str() {
+        "movzx" | "movsx" | "movsxd" => {
+            let (Some(dst), Some(src)) = (
+                ops.first().and_then(reg_width),
+                ops.get(1).and_then(|op| reg_width(op).or(mem_size)),
+            ) else {
+                return Err(format!("operand size of `{}` is ambiguous", inst.trim()));
+            };
+            let kind = if m == "movzx" { 'z' } else { 's' };
+            format!("mov{}{}{}", kind, suffix(src), suffix(dst))
+        }
+        _ if SUFFIXABLE.contains(&m.as_str()) => {
+            let size = ops
+                .first()
+                .and_then(reg_width)
+                .or(mem_size)
+                .or_else(|| ops.iter().find_map(reg_width))
+                .or(matches!(m.as_str(), "push" | "pop").then_some(64));
+            match size {
+                Some(bits) => format!("{}{}", m, suffix(bits)),
+                None => {
+                    return Err(format!(
+                        "operand size of `{}` is ambiguous; add byte/word/dword/qword",
+                        inst.trim()
+                    ))
+                }
+            }
+        }
+        _ => m.clone(),
+    };
+
+    let operands: Vec<String> = ops.iter().rev().map(render_att).collect();
+    Ok(if operands.is_empty() {
+        att_mnemonic
+    } else {
+        format!("{} {}", att_mnemonic, operands.join(", "))
+    })
+}
+
+fn att_to_intel(inst: &str) -> Result<String, String> {
+    let (mnemonic, rest) = split_mnemonic(inst);
+    let m = mnemonic.to_lowercase();
+    let bits = |c: char| match c {
+        'b' => Some(8),
+        'w' => Some(16),
+        'l' => Some(32),
+        'q' => Some(64),
+        _ => None,
+    };
+
+    // (Intel mnemonic, operand size, size of a memory source for movzx/movsx)
+    let chars: Vec<char> = m.chars().collect();
+    let (stem, size, source_size) = match m.as_str() {
+        "movslq" => ("movsxd".to_string(), Some(64), Some(32)),
+        "movabsq" | "movabs" => ("mov".to_string(), Some(64), None),
+        "callq" | "jmpq" | "retq" => (m[..m.len() - 1].to_string(), None, None),
+        _ if chars.len() == 6
+            && (m.starts_with("movz") || m.starts_with("movs"))
+            && bits(chars[4]).is_some()
+            && bits(chars[5]).is_some() =>
+        {
+            let name = if chars[3] == 'z' { "movzx" } else { "movsx" };
+            (name.to_string(), bits(chars[5]), bits(chars[4]))
+        }
+        _ => match m.strip_suffix(['b', 'w', 'l', 'q']) {
+            Some(stem) if SUFFIXABLE.contains(&stem) => {
+                (stem.to_string(), bits(chars[chars.len() - 1]), None)
+            }
+            _ => (m.clone(), None, None),
+        },
+    };
+
+    let branch = is_branch(&stem);
+    let ops = split_operands(rest)
+        .into_iter()
+        .map(|op| parse_att_operand(op, branch))
+        .collect::<Result<Vec<_>, _>>()?;
+    let has_reg = ops.iter().any(|op| matches!(op, Operand::Reg(_)));
+    let has_mem = ops.iter().any(|op| matches!(op, Operand::Mem { .. }));
+
+    // Intel needs a size keyword where no register operand implies the size.
+    let keyword = if source_size.is_some() {
+        source_size
+    } else if has_mem && !has_reg && SUFFIXABLE.contains(&stem.as_str()) {
+        match size {
+            Some(bits) => Some(bits),
+            None => {
+                return Err(format!(
+                    "operand size of `{}` is ambiguous; add a b/w/l/q suffix",
+                    inst.trim()
+                ))
+            }
+        }
+    } else {
+        None
+    };
+
+    let operands: Vec<String> = ops
+        .iter()
+        .rev()
+        .map(|op| render_intel(op, keyword))
+        .collect();
+    Ok(if operands.is_empty() {
+        stem
+    } else {
+        format!("{} {}", stem, operands.join(", "))
+    })
+}
+
+/// Translate one instruction, or reject it when the other syntax cannot say the same thing.
+fn translate_instruction(inst: &str, to: &Syntax) -> Result<String, String> {
+    let lower = inst.trim().to_lowercase();
+    let first = lower.split_whitespace().next().unwrap_or("");
+    if first.starts_with(['.', '%'])
+        || ["db", "dw", "dd", "dq", "times", "equ", "align", "resb"]
+            .iter()
+            .any(|d| lower.split_whitespace().take(2).any(|w| w == *d))
+    {
+        return Err(format!("directive `{}` cannot be translated", inst.trim()));
+    }
+    match to {
+        Syntax::Att => intel_to_att(inst),
+        Syntax::Intel => att_to_intel(inst),
+    }
+}
+
+/// Split off a comment, returning the code and the byte offset of the comment marker.
+/// Markers inside quotes, as in `cmp al, ';'`, are part of the code.
+fn split_comment(line: &str) -> (&str, Option<usize>) {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == ';' || c == '#' => return (&line[..i], Some(i)),
+            None => {}
+        }
+    }
+    (line, None)
+}
+
+fn translate_line(raw: &str, in_text: &mut bool, to: &Syntax) -> Result<String, String> {
+    let (code, comment_at) = split_comment(raw);
+    let trimmed = code.trim();
+    let indent = &code[..code.len() - code.trim_start().len()];
+    let lower = trimmed.to_lowercase();
+
+    let translated = if lower.starts_with("section ") {
+        *in_text = lower.contains(".text");
+        trimmed.to_string()
+    } else if trimmed.is_empty()
+        || !*in_text
+        || ["global", "extern", "default", "bits"]
+            .iter()
+            .any(|k| lower.starts_with(k))
+    {
+        trimmed.to_string()
+    } else {
+        match trimmed.split_once(':') {
+            Some((label, rest)) if !label.contains(char::is_whitespace) => {
+                if rest.trim().is_empty() {
+                    trimmed.to_string()
+                } else {
+                    format!("{}: {}", label, translate_instruction(rest, to)?)
+                }
+            }
+            _ => translate_instruction(trimmed, to)?,
+        }
+    };
+
+    let mut out = format!("{}{}", indent, translated);
+    if let Some(at) = comment_at {
+        let marker = match to {
+            Syntax::Intel => ';',
+            Syntax::Att => '#',
+        };
+        if !translated.is_empty() {
+            // Keep the comment's column when the code still fits before it.
+            let gap = code.len() - code.trim_end().len();
+            let pad = if out.len() < at {
+                at - out.len()
+            } else {
+                gap.max(1)
+            };
+            out.push_str(&" ".repeat(pad));
+        } else {
+            out = code.to_string();
+        }
+        out.push(marker);
+        out.push_str(&raw[at + 1..]);
+    }
+    Ok(out)
+}
+
+/// Translate a program between syntaxes. Every construct that cannot be translated faithfully
+/// is reported, one `line N: ...` per line.
+pub fn translate(code: &str, from: &Syntax, to: &Syntax) -> Result<String, String> {
+    if std::mem::discriminant(from) == std::mem::discriminant(to) {
+        return Ok(code.to_string());
+    }
+    let mut in_text = !code.lines().any(|l| {
+        split_comment(l)
+            .0
+            .trim()
+            .to_lowercase()
+            .starts_with("section ")
+    });
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, raw) in code.lines().enumerate() {
+        match translate_line(raw, &mut in_text, to) {
+            Ok(line) => lines.push(line),
+            Err(e) => errors.push(format!("line {}: {}", idx + 1, e)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    let mut out = lines.join("\n");
+    if code.ends_with('\n') {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_att(code: &str) -> String {
+        translate(code, &Syntax::Intel, &Syntax::Att).unwrap()
+    }
+
+    fn to_intel(code: &str) -> String {
+        translate(code, &Syntax::Att, &Syntax::Intel).unwrap()
+    }
+
+    #[test]
+    fn translates_intel_instructions() {
+        assert_eq!(to_att("mov rax, 60"), "movq $60, %rax");
+        assert_eq!(to_att("mov rsi, buf"), "movq $buf, %rsi");
+        assert_eq!(to_att("mov al, byte [buf + r8]"), "movb buf(%r8), %al");
+        assert_eq!(to_att("mov byte [out + r9], '-'"), "movb $0x2d, out(%r9)");
+        assert_eq!(to_att("mov [rsi], rax"), "movq %rax, (%rsi)");
+        assert_eq!(
+            to_att("add rax, [rbx + rcx*8 - 16]"),
+            "addq -16(%rbx,%rcx,8), %rax"
+        );
+        assert_eq!(to_att("movzx eax, byte [rsi]"), "movzbl (%rsi), %eax");
+        assert_eq!(to_att("jmp rax"), "jmp *%rax");
+        assert_eq!(to_att("in rbx"), "in %rbx");
+        assert_eq!(to_att("push 5"), "pushq $5");
+    }
+
+    #[test]
+    fn translates_att_instructions() {
+        assert_eq!(to_intel("movq $60, %rax"), "mov rax, 60");
+        assert_eq!(to_intel("movb buf(%r8), %al"), "mov al, [r8 + buf]");
+        assert_eq!(
+            to_intel("movb $0x2d, (%r14,%r9,1)"),
+            "mov byte [r14 + r9], 0x2d"
+        );
+        assert_eq!(to_intel("movq -8(%rbp), %rax"), "mov rax, [rbp - 8]");
+        assert_eq!(to_att(&to_intel("movq -8, %rax")), "movq -8, %rax");
+        assert_eq!(
+            to_intel("movzbq (%rsi,%rcx,4), %rax"),
+            "movzx rax, byte [rsi + rcx*4]"
+        );
+        assert_eq!(to_intel("call *%rax"), "call rax");
+        assert_eq!(to_intel("jge .done"), "jge .done");
+        assert_eq!(to_intel("popq %rbx"), "pop rbx");
+    }
+
+    #[test]
+    fn keeps_labels_sections_and_comment_columns() {
+        let intel = "section .bss\n    buf resb 16 ; scratch\n\nsection .text\n    global _start\n_start:\n    mov rax, 0          ; syscall: read\n.loop: inc r8\n    ; done\n";
+        let att = to_att(intel);
+        assert_eq!(
+            att,
+            "section .bss\n    buf resb 16 # scratch\n\nsection .text\n    global _start\n_start:\n    movq $0, %rax       # syscall: read\n.loop: incq %r8\n    # done\n"
+        );
+        assert_eq!(to_intel(&att), intel);
+
+        assert_eq!(
+            to_att("    cmp al, ';'  ; end of field"),
+            "    cmpb $0x3b, %al  # end of field"
+        );
+        assert_eq!(
+            split_comment("mov al, '#' # hash"),
+            ("mov al, '#' ", Some(12))
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_translate_faithfully() {
+        let err = translate(
+            "_start:\n    mov [rsi], 5\n    mov rax, fs:[0]\n    db 1\n",
+            &Syntax::Intel,
+            &Syntax::Att,
+        )
+        .unwrap_err();
+        let lines: Vec<&str> = err.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", err);
+        assert!(lines[0].starts_with("line 2: operand size"));
+        assert!(lines[1].starts_with("line 3:"));
+        assert!(lines[2].starts_with("line 4: directive"));
+
+        assert!(translate(
+            "    movl $1, (%rsi)\n    mov $1, (%rsi)\n",
+            &Syntax::Att,
+            &Syntax::Intel
+        )
+        .unwrap_err()
+        .starts_with("line 2: operand size"));
+    }
+}
@@ -58,6 +58,65 @@ pub enum Instruction {
     RET,
 }
 
+/// An active `call` in the x86 runtime.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CallFrame {
+    /// Label of the called code, or its address in hex.
+    pub function: String,
+    /// Source line of the `call` (none in a loaded executable).
+    pub call_line: Option<usize>,
+    pub return_address: u64,
+    /// Where `call` pushed the return address: the top of the frame.
+    pub entry_rsp: u64,
+    /// RSP and RBP now for the innermost frame, or when the frame made its own call.
+    pub rsp: u64,
+    pub rbp: u64,
+}
+
+/// A `ret` that does not match its `call`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CallIssue {
+    /// Source line of the offending instruction, if known.
+    pub line: Option<usize>,
+    pub address: u64,
+    /// The function it happened in; `None` outside any `call`.
+    pub function: Option<String>,
+    pub message: String,
+}
+
+/// What a mapping of the x86 runtime's address space holds.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryOrigin {
+    Code,
+    /// `.bss` of an assembled program.
+    Bss,
+    /// A non-executable segment of a loaded executable.
+    Data,
+    Stack,
+    /// Grown and shrunk by `brk`.
+    Heap,
+    /// An anonymous `mmap`.
+    Mmap,
+}
+
+/// One mapping of the x86 runtime's address space.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MemoryRegion {
+    pub start: u64,
+    pub size: u64,
+    /// `r`, `w` and `x` or `-`, as in `/proc/<pid>/maps`.
+    pub perms: String,
+    pub origin: MemoryOrigin,
+}
+
+/// Bytes from `address` that a step changed from `old` to `new`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MemoryChange {
+    pub address: u64,
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VmState {
     pub registers: HashMap<Register, i64>,
@@ -71,6 +130,19 @@ pub struct VmState {
     pub finished: bool,
     pub exited: bool, // sys_exit で終了したかどうか
     pub error: Option<String>,
+    #[serde(default)]
+    pub instructions_executed: usize,
+    /// Active calls of the x86 runtime, outermost first.
+    #[serde(default)]
+    pub call_stack: Vec<CallFrame>,
+    #[serde(default)]
+    pub call_issues: Vec<CallIssue>,
+    /// Mappings of the x86 runtime, by address.
+    #[serde(default)]
+    pub memory_map: Vec<MemoryRegion>,
+    /// Bytes changed by the latest step of a session that tracks them, by address.
+    #[serde(default)]
+    pub memory_changes: Vec<MemoryChange>,
 }
 
 pub struct VM {
@@ -89,6 +161,7 @@ pub struct VM {
     finished: bool,
     exited: bool,               // sys_exit で終了したかどうか
     execution_log: Vec<String>, // 実行ログを保存
+    steps: usize,
 }
 
 const MEMORY_SIZE: usize = 65536; // 64KB
@@ -138,6 +211,7 @@ impl VM {
             finished: false,
             exited: false,
             execution_log: Vec::new(),
+            steps: 0,
         }
     }
 
@@ -162,6 +236,11 @@ impl VM {
             finished: self.finished || self.pc >= self.program.len() || self.error.is_some(),
             exited: self.exited,
             error: self.error.clone(),
+            instructions_executed: self.steps,
+            call_stack: Vec::new(),
+            call_issues: Vec::new(),
+            memory_map: Vec::new(),
+            memory_changes: Vec::new(),
         }
     }
 
@@ -342,6 +421,7 @@ impl VM {
 
         let inst = self.program[self.pc].clone();
         let mut next_pc = self.pc + 1;
+        self.steps += 1;
 
         // 実行前の状態をログ
         let inst_str = self.format_instruction(&inst);
@@ -1,17 +1,42 @@
-use crate::vm::Syntax;
+use crate::vm::{Register, Syntax};
 
 use keystone_engine::{Arch, Keystone, KeystoneOutput, Mode, OptionType, OptionValue};
+use serde::Serialize;
 use std::collections::HashMap;
 
 pub struct AssembleResult {
+    /// Address of `bytes[0]`.
+    pub base_addr: u64,
     pub bytes: Vec<u8>,
     pub labels: HashMap<String, u64>,
+    /// Every encoded instruction, in address order.
+    pub instructions: Vec<AssembledInstruction>,
+}
+
+impl AssembleResult {
+    /// The encoded bytes of one of `instructions`.
+    pub fn bytes_of(&self, inst: &AssembledInstruction) -> &[u8] {
+        let start = (inst.address - self.base_addr) as usize;
+        &self.bytes[start..start + inst.size]
+    }
+}
+
+/// One encoded instruction and where it came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssembledInstruction {
+    pub address: u64,
+    pub size: usize,
+    /// 1-based line of the assembled text.
+    pub line: usize,
+    /// The instruction as handed to Keystone, labels already resolved.
+    pub text: String,
 }
 
 #[derive(Debug, Clone)]
 enum Entry {
     Label(String),
-    Inst(String),
+    /// 1-based source line and instruction text.
+    Inst(usize, String),
 }
 
 fn strip_comment(line: &str) -> &str {
@@ -36,7 +61,7 @@ fn is_ignorable_directive(line: &str) -> bool {
 
 fn parse_entries(code: &str) -> Vec<Entry> {
     let mut out = Vec::new();
-    for raw in code.lines() {
+    for (idx, raw) in code.lines().enumerate() {
         let line = strip_comment(raw);
         if line.is_empty() {
             continue;
@@ -48,11 +73,303 @@ fn parse_entries(code: &str) -> Vec<Entry> {
             out.push(Entry::Label(lbl.to_string()));
             continue;
         }
-        out.push(Entry::Inst(line.to_string()));
+        out.push(Entry::Inst(idx + 1, line.to_string()));
     }
     out
 }
 
+/// An instruction line of a program as the learner wrote it (before pseudo-instruction rewriting).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceInstruction {
+    /// 1-based line number in the original source.
+    pub line: usize,
+    /// Lowercased mnemonic as written (e.g. `movq`, `in`, `loop`).
+    pub mnemonic: String,
+    /// Operand text after the mnemonic, comment stripped.
+    pub operands: String,
+}
+
+/// List the instruction lines of `.text`, skipping comments, labels, directives and data.
+pub fn source_instructions(code: &str) -> Vec<SourceInstruction> {
+    let has_sections = code
+        .lines()
+        .any(|l| strip_comment(l).to_lowercase().starts_with("section "));
+    let mut in_text = !has_sections;
+    let mut out = Vec::new();
+
+    for (idx, raw) in code.lines().enumerate() {
+        let line = strip_comment(raw);
+        if line.is_empty() {
+            continue;
+        }
+        let lower = line.to_lowercase();
+        if lower.starts_with("section ") {
+            in_text = lower.contains(".text");
+            continue;
+        }
+        if !in_text || is_ignorable_directive(line) || is_label_def(line).is_some() {
+            continue;
+        }
+        // `label: inst` on one line
+        let inst = match line.split_once(':') {
+            Some((label, rest)) if !label.contains(char::is_whitespace) => rest.trim(),
+            _ => line,
+        };
+        let mut tokens = inst.split_whitespace();
+        let Some(mnemonic) = tokens.next() else {
+            continue;
+        };
+        let operands = inst[mnemonic.len()..].trim();
+        // `buf resb 16` outside of an explicit .bss
+        if tokens
+            .next()
+            .is_some_and(|t| t.eq_ignore_ascii_case("resb"))
+        {
+            continue;
+        }
+        out.push(SourceInstruction {
+            line: idx + 1,
+            mnemonic: mnemonic.to_lowercase(),
+            operands: operands.to_string(),
+        });
+    }
+    out
+}
+
+/// Split an operand list on top-level commas, leaving `buf(%r8,%rcx,1)` and `[a, b]` intact.
+pub fn split_operands(operands: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, c) in operands.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(operands[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = operands[start..].trim();
+    if !last.is_empty() || !out.is_empty() {
+        out.push(last);
+    }
+    out
+}
+
+/// Map any spelling of a general-purpose register (`eax`, `%r8b`, `sil`, ...) to its 64-bit register.
+pub fn register_family(name: &str) -> Option<Register> {
+    let name = name.trim_start_matches('%').to_lowercase();
+    const NAMED: [(Register, [&str; 5]); 8] = [
+        (Register::RAX, ["rax", "eax", "ax", "al", "ah"]),
+        (Register::RBX, ["rbx", "ebx", "bx", "bl", "bh"]),
+        (Register::RCX, ["rcx", "ecx", "cx", "cl", "ch"]),
+        (Register::RDX, ["rdx", "edx", "dx", "dl", "dh"]),
+        (Register::RSI, ["rsi", "esi", "si", "sil", "sil"]),
+        (Register::RDI, ["rdi", "edi", "di", "dil", "dil"]),
+        (Register::RSP, ["rsp", "esp", "sp", "spl", "spl"]),
+        (Register::RBP, ["rbp", "ebp", "bp", "bpl", "bpl"]),
+    ];
+    if let Some((reg, _)) = NAMED
+        .iter()
+        .find(|(_, names)| names.contains(&name.as_str()))
+    {
+        return Some(*reg);
+    }
+    let numbered = [
+        Register::R8,
+        Register::R9,
+        Register::R10,
+        Register::R11,
+        Register::R12,
+        Register::R13,
+        Register::R14,
+        Register::R15,
+    ];
+    let rest = name.strip_prefix('r')?;
+    let digits = rest.trim_end_matches(['d', 'w', 'b']);
+    if rest.len() - digits.len() > 1 {
+        return None;
+    }
+    let n: usize = digits.parse().ok()?;
+    numbered.get(n.checked_sub(8)?).copied()
+}
+
+/// Distinct general-purpose registers named in the program's instructions, in first-use order.
+/// Registers touched only implicitly (e.g. RSP by `push`, RAX by the `in` pseudo-instruction) are not counted.
+pub fn registers_used(code: &str) -> Vec<Register> {
+    let mut used = Vec::new();
+    for inst in source_instructions(code) {
+        for token in inst
+            .operands
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '%'))
+        {
+            if let Some(reg) = register_family(token) {
+                if !used.contains(&reg) {
+                    used.push(reg);
+                }
+            }
+        }
+    }
+    used
+}
+
+fn is_immediate(operand: &str) -> bool {
+    let digits = operand.strip_prefix('-').unwrap_or(operand);
+    operand.starts_with('$')
+        || digits.starts_with(|c: char| c.is_ascii_digit())
+        || operand.starts_with('\'')
+}
+
+/// Mnemonics that take an AT&T size suffix in this course (`movq`, `addl`, `pushq`, ...).
+pub(crate) const SUFFIXABLE: [&str; 28] = [
+    "mov", "add", "sub", "adc", "sbb", "and", "or", "xor", "cmp", "test", "inc", "dec", "neg",
+    "not", "push", "pop", "lea", "mul", "imul", "div", "idiv", "shl", "shr", "sal", "sar", "rol",
+    "ror", "xchg",
+];
+
+fn has_size_suffix(mnemonic: &str) -> bool {
+    mnemonic
+        .strip_suffix(['b', 'w', 'l', 'q'])
+        .is_some_and(|stem| SUFFIXABLE.contains(&stem))
+}
+
+/// One kind of evidence for a syntax, e.g. `%` register sigils.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyntaxSignal {
+    pub syntax: Syntax,
+    pub description: String,
+    /// Number of instructions showing it.
+    pub count: usize,
+    pub first_line: usize,
+}
+
+/// The outcome of [`analyze_syntax`].
+#[derive(Debug, Clone, Serialize)]
+pub struct SyntaxDetection {
+    /// The syntax with more evidence; Intel on a tie.
+    pub syntax: Syntax,
+    /// Evidence points both ways, or nowhere at all.
+    pub ambiguous: bool,
+    pub signals: Vec<SyntaxSignal>,
+}
+
+impl SyntaxDetection {
+    /// e.g. `AT&T: % register sigils (12, first on line 9); Intel: [ ] memory operands (1, first on line 4)`
+    pub fn describe(&self) -> String {
+        if self.signals.is_empty() {
+            return "no syntax-specific operands; assuming Intel".to_string();
+        }
+        let mut parts = Vec::new();
+        for (syntax, name) in [(Syntax::Att, "AT&T"), (Syntax::Intel, "Intel")] {
+            let found: Vec<String> = self
+                .signals
+                .iter()
+                .filter(|s| std::mem::discriminant(&s.syntax) == std::mem::discriminant(&syntax))
+                .map(|s| {
+                    format!(
+                        "{} ({}, first on line {})",
+                        s.description, s.count, s.first_line
+                    )
+                })
+                .collect();
+            if !found.is_empty() {
+                parts.push(format!("{}: {}", name, found.join(", ")));
+            }
+        }
+        parts.join("; ")
+    }
+}
+
+/// Weigh the evidence for each syntax: `%` sigils, `$` immediates and `q`/`l` size suffixes for
+/// AT&T; bare register names, `[ ]` memory operands and `qword`-style size keywords for Intel;
+/// and on which side of the destination an immediate sits.
+pub fn analyze_syntax(code: &str) -> SyntaxDetection {
+    let mut signals: Vec<SyntaxSignal> = Vec::new();
+    let mut note = |syntax: Syntax, description: &str, line: usize| match signals
+        .iter_mut()
+        .find(|s| s.description == description)
+    {
+        Some(signal) => signal.count += 1,
+        None => signals.push(SyntaxSignal {
+            syntax,
+            description: description.to_string(),
+            count: 1,
+            first_line: line,
+        }),
+    };
+
+    for inst in source_instructions(code) {
+        let operands = split_operands(&inst.operands);
+        let tokens: Vec<&str> = inst
+            .operands
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '%' || c == '_'))
+            .filter(|t| !t.is_empty())
+            .collect();
+
+        if tokens.iter().any(|t| t.starts_with('%')) {
+            note(Syntax::Att, "% register sigils", inst.line);
+        }
+        if operands.iter().any(|op| op.starts_with('$')) {
+            note(Syntax::Att, "$ immediates", inst.line);
+        }
+        if has_size_suffix(&inst.mnemonic) {
+            note(Syntax::Att, "size suffixes", inst.line);
+        }
+        if tokens
+            .iter()
+            .any(|t| !t.starts_with('%') && register_family(t).is_some())
+        {
+            note(Syntax::Intel, "bare register names", inst.line);
+        }
+        if inst.operands.contains('[') {
+            note(Syntax::Intel, "[ ] memory operands", inst.line);
+        }
+        if tokens
+            .iter()
+            .any(|t| ["byte", "word", "dword", "qword", "ptr"].contains(&t.to_lowercase().as_str()))
+        {
+            note(Syntax::Intel, "size keywords", inst.line);
+        }
+        // `out imm, al` and `enter imm, imm` put the immediate first in both syntaxes.
+        if let [first, second] = operands[..] {
+            if !matches!(inst.mnemonic.as_str(), "out" | "enter") {
+                match (is_immediate(first), is_immediate(second)) {
+                    (true, false) => note(Syntax::Att, "source-first operand order", inst.line),
+                    (false, true) => {
+                        note(Syntax::Intel, "destination-first operand order", inst.line)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let score = |want: Syntax| -> usize {
+        signals
+            .iter()
+            .filter(|s| std::mem::discriminant(&s.syntax) == std::mem::discriminant(&want))
+            .map(|s| s.count)
+            .sum()
+    };
+    let (att, intel) = (score(Syntax::Att), score(Syntax::Intel));
+    SyntaxDetection {
+        syntax: if att > intel {
+            Syntax::Att
+        } else {
+            Syntax::Intel
+        },
+        ambiguous: (att > 0) == (intel > 0),
+        signals,
+    }
+}
+
+/// The likelier syntax of a program; see [`analyze_syntax`].
+pub fn detect_syntax(code: &str) -> Syntax {
+    analyze_syntax(code).syntax
+}
+
 fn init_engine(syntax: Syntax) -> Result<Keystone, String> {
     let engine =
         Keystone::new(Arch::X86, Mode::MODE_64).map_err(|e| format!("Keystone init: {e:?}"))?;
@@ -70,22 +387,15 @@ fn init_engine(syntax: Syntax) -> Result<Keystone, String> {
     Ok(engine)
 }
 
-fn replace_symbols_with_addrs(
-    line: &str,
-    labels: &HashMap<String, u64>,
-    known_labels: &HashMap<String, ()>,
-) -> String {
-    // Replace occurrences of label identifiers with hex immediates.
-    // This is a minimal, boundary-aware replacer to avoid clobbering registers/keywords.
+/// Byte ranges of the identifiers in `line` (`buf`, `.loop`, `rax`, `x10` of `0x10`).
+fn ident_spans(line: &str) -> Vec<(usize, usize)> {
     let bytes = line.as_bytes();
+    let mut spans = Vec::new();
     let mut i = 0usize;
-    let mut out = String::with_capacity(line.len());
-
     while i < bytes.len() {
         let c = bytes[i] as char;
         let is_ident_start = c.is_ascii_alphabetic() || c == '_' || c == '.';
         if !is_ident_start {
-            out.push(c);
             i += 1;
             continue;
         }
@@ -100,7 +410,23 @@ fn replace_symbols_with_addrs(
                 break;
             }
         }
-        let ident = &line[start..i];
+        spans.push((start, i));
+    }
+    spans
+}
+
+fn replace_symbols_with_addrs(
+    line: &str,
+    labels: &HashMap<String, u64>,
+    known_labels: &HashMap<String, ()>,
+) -> String {
+    // Replace occurrences of label identifiers with hex immediates.
+    // This is a minimal, boundary-aware replacer to avoid clobbering registers/keywords.
+    let mut out = String::with_capacity(line.len());
+    let mut last = 0usize;
+    for (start, end) in ident_spans(line) {
+        out.push_str(&line[last..start]);
+        let ident = &line[start..end];
         if let Some(addr) = labels.get(ident) {
             out.push_str(&format!("0x{:x}", addr));
         } else if known_labels.contains_key(ident) {
@@ -109,8 +435,9 @@ fn replace_symbols_with_addrs(
         } else {
             out.push_str(ident);
         }
+        last = end;
     }
-
+    out.push_str(&line[last..]);
     out
 }
 
@@ -154,7 +481,7 @@ pub fn assemble_x86_64(
                 Entry::Label(name) => {
                     labels.insert(name.clone(), pc);
                 }
-                Entry::Inst(raw) => {
+                Entry::Inst(_, raw) => {
                     let inst = replace_symbols_with_addrs(raw, &last_labels, &known_labels);
                     let out = asm_one(&engine, &inst, pc)?;
                     pc = pc.wrapping_add(out.bytes.len() as u64);
@@ -172,20 +499,757 @@ pub fn assemble_x86_64(
 
     // Final assembly
     let mut bytes = Vec::new();
+    let mut instructions = Vec::new();
     let mut pc = base_addr;
     for e in &entries {
         match e {
             Entry::Label(_) => {}
-            Entry::Inst(raw) => {
+            Entry::Inst(line, raw) => {
                 let inst = replace_symbols_with_addrs(raw, &labels, &known_labels);
                 let out = asm_one(&engine, &inst, pc)?;
+                instructions.push(AssembledInstruction {
+                    address: pc,
+                    size: out.bytes.len(),
+                    line: *line,
+                    text: inst,
+                });
                 pc = pc.wrapping_add(out.bytes.len() as u64);
                 bytes.extend_from_slice(&out.bytes);
             }
         }
     }
 
-    Ok(AssembleResult { bytes, labels })
+    Ok(AssembleResult {
+        base_addr,
+        bytes,
+        labels,
+        instructions,
+    })
+}
+
+/// A section of a relocatable object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectSection {
+    Text,
+    Data,
+    Bss,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectSymbol {
+    pub name: String,
+    /// `None` for an `extern` symbol the linker has to supply.
+    pub section: Option<ObjectSection>,
+    pub offset: u64,
+    /// Listed with `global`/`.globl` (externs are always global).
+    pub global: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// `R_X86_64_64`: a 64-bit absolute address (`mov rax, buf`).
+    Abs64,
+    /// `R_X86_64_32S`: a sign-extended 32-bit absolute address (`[buf]`, `$buf`).
+    Abs32S,
+    /// `R_X86_64_PC32`: RIP-relative (`[rel buf]`, `buf(%rip)`).
+    Pc32,
+    /// `R_X86_64_PLT32`: `call`/`jmp` to a function in another object.
+    Plt32,
+}
+
+/// A field of `.text` the linker fills in with `symbol + addend` (minus its own address for
+/// the RIP-relative kinds).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    /// Offset of the field in `.text`.
+    pub offset: u64,
+    pub symbol: String,
+    pub kind: RelocationKind,
+    pub addend: i64,
+}
+
+/// A program assembled for linking (e.g. with a C `main`) rather than for running here.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectCode {
+    pub text: Vec<u8>,
+    pub data: Vec<u8>,
+    pub bss_size: u64,
+    /// Labels of every section and externs, in definition order.
+    pub symbols: Vec<ObjectSymbol>,
+    /// Relocations of `.text`; data never refers to symbols.
+    pub relocations: Vec<Relocation>,
+}
+
+/// The parts of an object's source: `.text` still to assemble, everything else laid out.
+#[derive(Debug, Default)]
+struct ObjectSource {
+    text: Vec<Entry>,
+    data: Vec<u8>,
+    bss_size: u64,
+    /// `.data` and `.bss` labels with their offsets.
+    labels: Vec<(String, ObjectSection, u64)>,
+    globals: Vec<String>,
+    externs: Vec<String>,
+    /// NASM `default rel`: register-free `[sym]` operands are RIP-relative.
+    default_rel: bool,
+}
+
+fn section_named(name: &str) -> Option<ObjectSection> {
+    let name = name.to_lowercase();
+    if name.contains(".text") {
+        Some(ObjectSection::Text)
+    } else if name.contains(".data") || name.contains(".rodata") {
+        Some(ObjectSection::Data)
+    } else if name.contains(".bss") {
+        Some(ObjectSection::Bss)
+    } else {
+        None
+    }
+}
+
+/// Split a data directive's arguments on commas outside quotes.
+fn split_data_items(args: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let (mut quote, mut escaped, mut start) = (None, false, 0);
+    for (i, c) in args.char_indices() {
+        match (quote, c) {
+            (Some(_), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(q), c) if c == q && !escaped => quote = None,
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, ',') => {
+                out.push(args[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        escaped = false;
+    }
+    out.push(args[start..].trim());
+    out
+}
+
+/// The bytes of a quoted string; backslash escapes apply in AT&T strings and NASM backquotes.
+fn string_bytes(item: &str, escapes: bool) -> Option<Vec<u8>> {
+    let quote = item.chars().next()?;
+    let inner = item.strip_prefix(quote)?.strip_suffix(quote)?;
+    if !escapes && quote != '`' {
+        return Some(inner.as_bytes().to_vec());
+    }
+    let mut out = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                other => other,
+            },
+            c => c,
+        };
+        let mut buf = [0u8; 4];
+        out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+    Some(out)
+}
+
+/// `42`, `-1`, `0x2a`, `2ah`, `0b101` or `'*'`.
+fn parse_int(item: &str) -> Option<i64> {
+    let (negative, digits) = match item.strip_prefix('-') {
+        Some(rest) => (true, rest.trim()),
+        None => (false, item),
+    };
+    let lower = digits.to_lowercase();
+    let value = if let Some(hex) = lower.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        i64::from_str_radix(bin, 2).ok()?
+    } else if let Some(hex) = lower
+        .strip_suffix('h')
+        .filter(|h| h.starts_with(|c: char| c.is_ascii_digit()))
+    {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if digits.len() == 3 && digits.starts_with('\'') && digits.ends_with('\'') {
+        digits.as_bytes()[1] as i64
+    } else {
+        lower.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// Encode the items of `db`/`.byte`-style directives, `width` bytes per number.
+fn data_items(args: &str, width: usize, escapes: bool) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    for item in split_data_items(args) {
+        if item.starts_with(['"', '\'', '`']) {
+            let mut bytes = string_bytes(item, escapes)
+                .ok_or_else(|| format!("Unterminated string: {}", item))?;
+            // NASM pads a string in `dw`/`dd`/`dq` to a whole number of units.
+            bytes.resize(bytes.len().next_multiple_of(width), 0);
+            out.extend(bytes);
+            continue;
+        }
+        let value = parse_int(item).ok_or_else(|| {
+            format!(
+                "`{}` is not a number or string (data cannot refer to labels)",
+                item
+            )
+        })?;
+        out.extend_from_slice(&value.to_le_bytes()[..width]);
+    }
+    Ok(out)
+}
+
+/// Bytes per unit of `db`/`resb`-style directives (NASM and AT&T spellings).
+fn directive_width(directive: &str) -> Option<(usize, bool)> {
+    let reserve = directive.starts_with("res");
+    let width = match directive {
+        "db" | "resb" | ".byte" | ".ascii" => 1,
+        "dw" | "resw" | ".word" | ".short" | ".value" => 2,
+        "dd" | "resd" | ".long" | ".int" => 4,
+        "dq" | "resq" | ".quad" => 8,
+        _ => return None,
+    };
+    Some((width, reserve))
+}
+
+fn is_layout_directive(word: &str) -> bool {
+    let word = word.to_lowercase();
+    directive_width(&word).is_some()
+        || matches!(
+            word.as_str(),
+            ".asciz" | ".string" | ".skip" | ".space" | ".zero" | "align" | ".align" | ".balign"
+        )
+}
+
+/// Lay out one `.data`/`.bss` line (`msg db "hi", 10`, `buf: .skip 16`, `align 8`).
+fn layout_line(
+    source: &mut ObjectSource,
+    section: ObjectSection,
+    line: &str,
+) -> Result<(), String> {
+    let mut rest = line;
+    let first = line.split_whitespace().next().unwrap_or("");
+    let second = line.split_whitespace().nth(1).unwrap_or("");
+    let label = if let Some(name) = first.strip_suffix(':') {
+        Some(name)
+    } else if !is_layout_directive(first) && is_layout_directive(second) {
+        Some(first)
+    } else {
+        None
+    };
+    if let Some(name) = label {
+        let offset = match section {
+            ObjectSection::Bss => source.bss_size,
+            _ => source.data.len() as u64,
+        };
+        source.labels.push((name.to_string(), section, offset));
+        rest = line[line.find(first).unwrap_or(0) + first.len()..].trim();
+    }
+    if rest.is_empty() {
+        return Ok(());
+    }
+
+    let directive = rest.split_whitespace().next().unwrap_or("").to_lowercase();
+    let args = rest[directive.len()..].trim();
+    let count = || {
+        parse_int(args)
+            .and_then(|n| u64::try_from(n).ok())
+            .ok_or_else(|| format!("`{}` needs a non-negative count", directive))
+    };
+    let bytes: Vec<u8> = match directive.as_str() {
+        "align" | ".align" | ".balign" => {
+            let align = count()?.max(1);
+            match section {
+                ObjectSection::Bss => source.bss_size = source.bss_size.next_multiple_of(align),
+                _ => {
+                    let len = (source.data.len() as u64).next_multiple_of(align);
+                    source.data.resize(len as usize, 0);
+                }
+            }
+            return Ok(());
+        }
+        ".skip" | ".space" | ".zero" => vec![0; count()? as usize],
+        ".asciz" | ".string" => {
+            let mut bytes = data_items(args, 1, true)?;
+            bytes.push(0);
+            bytes
+        }
+        d => match directive_width(d) {
+            Some((width, true)) => vec![0; width * count()? as usize],
+            Some((width, false)) => data_items(args, width, d.starts_with('.'))?,
+            None => return Err(format!("unsupported directive `{}`", directive)),
+        },
+    };
+    match section {
+        ObjectSection::Bss if bytes.iter().any(|b| *b != 0) => {
+            Err("`.bss` can only reserve space (resb, .skip, ...)".to_string())
+        }
+        ObjectSection::Bss => {
+            source.bss_size += bytes.len() as u64;
+            Ok(())
+        }
+        _ => {
+            source.data.extend(bytes);
+            Ok(())
+        }
+    }
+}
+
+fn parse_object_source(code: &str) -> Result<ObjectSource, String> {
+    let mut source = ObjectSource::default();
+    let mut section = ObjectSection::Text;
+    let mut errors = Vec::new();
+
+    for (idx, raw) in code.lines().enumerate() {
+        let line = strip_comment(raw);
+        let Some(first) = line.split_whitespace().next() else {
+            continue;
+        };
+        let args = line[first.len()..].trim();
+        let names = || {
+            args.split(',')
+                .map(|n| n.split(':').next().unwrap_or("").trim().to_string())
+                .filter(|n| !n.is_empty())
+        };
+        let result = match first.to_lowercase().as_str() {
+            "section" | "segment" | ".section" => match section_named(args) {
+                Some(s) => {
+                    section = s;
+                    Ok(())
+                }
+                None => Err(format!("unsupported section `{}`", args)),
+            },
+            ".text" => {
+                section = ObjectSection::Text;
+                Ok(())
+            }
+            ".data" => {
+                section = ObjectSection::Data;
+                Ok(())
+            }
+            ".bss" => {
+                section = ObjectSection::Bss;
+                Ok(())
+            }
+            "global" | ".globl" | ".global" => {
+                source.globals.extend(names());
+                Ok(())
+            }
+            "extern" | ".extern" => {
+                source.externs.extend(names());
+                Ok(())
+            }
+            "default" => {
+                source.default_rel = args.eq_ignore_ascii_case("rel");
+                Ok(())
+            }
+            "bits" => Ok(()),
+            _ if section != ObjectSection::Text => layout_line(&mut source, section, line),
+            _ => {
+                // `label:` or `label: inst`
+                let inst = match line.split_once(':') {
+                    Some((label, rest)) if !label.trim().contains(char::is_whitespace) => {
+                        source.text.push(Entry::Label(label.trim().to_string()));
+                        rest.trim()
+                    }
+                    _ => line,
+                };
+                if inst.starts_with('.') || inst.to_lowercase().starts_with("align") {
+                    Err(format!("unsupported directive in `.text`: `{}`", inst))
+                } else {
+                    if !inst.is_empty() {
+                        source.text.push(Entry::Inst(idx + 1, inst.to_string()));
+                    }
+                    Ok(())
+                }
+            }
+        };
+        if let Err(e) = result {
+            errors.push(format!("line {}: {}", idx + 1, e));
+        }
+    }
+    if errors.is_empty() {
+        Ok(source)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+/// NASM `[rel x]` (and register-free `[x]` under `default rel`) as Keystone's `[rip + x]`.
+fn rip_relative(inst: &str, default_rel: bool) -> String {
+    let mut out = String::with_capacity(inst.len());
+    let mut rest = inst;
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find(']').map(|c| open + c) else {
+            break;
+        };
+        out.push_str(&rest[..=open]);
+        let inner = rest[open + 1..close].trim();
+        let lower = inner.to_lowercase();
+        let words: Vec<&str> = ident_spans(&lower)
+            .into_iter()
+            .map(|(s, e)| &lower[s..e])
+            .collect();
+        if lower.starts_with("rel ") {
+            out.push_str(&format!("rip + {}", inner[4..].trim()));
+        } else if lower.starts_with("abs ") {
+            out.push_str(inner[4..].trim());
+        } else if default_rel
+            && !words.is_empty()
+            && !words
+                .iter()
+                .any(|w| *w == "rip" || register_family(w).is_some())
+        {
+            out.push_str(&format!("rip + {}", inner));
+        } else {
+            out.push_str(inner);
+        }
+        rest = &rest[close..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Encodes `.text` of an object, turning symbol references into relocations.
+struct Relocator<'a> {
+    engine: &'a Keystone,
+    syntax: Syntax,
+    /// `.text` labels; branches to them are resolved like in `assemble_x86_64`.
+    text_labels: HashMap<String, ()>,
+    /// Data, bss and extern symbols: every reference to them is relocated.
+    others: HashMap<String, ()>,
+    externs: Vec<String>,
+    default_rel: bool,
+}
+
+impl Relocator<'_> {
+    /// Whether the identifier at `pos` is (part of) a memory operand rather than an immediate
+    /// or a branch target.
+    fn in_memory(&self, inst: &str, pos: usize, branch: bool) -> bool {
+        let before = &inst[..pos];
+        match self.syntax {
+            Syntax::Intel => before.matches('[').count() > before.matches(']').count(),
+            Syntax::Att if branch => before.contains('*'),
+            Syntax::Att => !before.trim_end().ends_with('$'),
+        }
+    }
+
+    fn encode(
+        &self,
+        raw: &str,
+        pc: u64,
+        labels: &HashMap<String, u64>,
+    ) -> Result<(Vec<u8>, Option<Relocation>), String> {
+        let inst = match self.syntax {
+            Syntax::Intel => rip_relative(raw, self.default_rel),
+            Syntax::Att => raw.to_string(),
+        };
+        let mnemonic = inst.split_whitespace().next().unwrap_or("").to_lowercase();
+        let branch = mnemonic.starts_with('j')
+            || mnemonic.starts_with("call")
+            || mnemonic.starts_with("loop");
+        let refs: Vec<(usize, usize)> = ident_spans(&inst)
+            .into_iter()
+            .filter(|(s, e)| {
+                let ident = &inst[*s..*e];
+                *s >= mnemonic.len()
+                    && (self.others.contains_key(ident)
+                        || (self.text_labels.contains_key(ident)
+                            && (!branch || self.in_memory(&inst, *s, branch))))
+            })
+            .collect();
+        let assemble = |with: &str| -> Result<Vec<u8>, String> {
+            let text = replace_symbols_with_addrs(with, labels, &self.text_labels);
+            Ok(asm_one(self.engine, &text, pc)?.bytes)
+        };
+        let &[(start, end)] = refs.as_slice() else {
+            if refs.is_empty() {
+                return Ok((assemble(&inst)?, None));
+            }
+            return Err(format!(
+                "`{}` refers to more than one symbol; split it into two instructions",
+                raw
+            ));
+        };
+        let symbol = inst[start..end].to_string();
+        let with_value = |value: u64| format!("{}0x{:x}{}", &inst[..start], value, &inst[end..]);
+
+        if branch && !self.in_memory(&inst, start, branch) {
+            // Aim far away so the assembler picks a rel32 field, which then ends the instruction.
+            let mut bytes = assemble(&with_value(pc + 0x1000_0000))?;
+            let at = bytes.len().saturating_sub(4);
+            bytes[at..].fill(0);
+            let kind = if self.externs.contains(&symbol) {
+                RelocationKind::Plt32
+            } else {
+                RelocationKind::Pc32
+            };
+            let relocation = Relocation {
+                offset: pc + at as u64,
+                symbol,
+                kind,
+                addend: -4,
+            };
+            return Ok((bytes, Some(relocation)));
+        }
+
+        // Assemble with two different stand-in addresses; the bytes that change are the field.
+        let dest = split_operands(inst[mnemonic.len()..].trim())
+            .first()
+            .map(|d| d.to_lowercase())
+            .unwrap_or_default();
+        let wide = match self.syntax {
+            Syntax::Intel => {
+                mnemonic == "mov"
+                    && !self.in_memory(&inst, start, branch)
+                    && dest.starts_with('r')
+                    && !dest.ends_with(['d', 'w', 'b'])
+                    && register_family(&dest).is_some()
+            }
+            Syntax::Att => mnemonic.starts_with("movabs"),
+        };
+        let (width, first, second) = if wide {
+            (8, 0x1111_1111_1111_1111u64, 0x2222_2222_2222_2222u64)
+        } else {
+            (4, 0x1111_1111, 0x2222_2222)
+        };
+        let mut bytes = assemble(&with_value(first))?;
+        let other = assemble(&with_value(second))?;
+        let changed: Vec<usize> = (0..bytes.len().min(other.len()))
+            .filter(|i| bytes[*i] != other[*i])
+            .collect();
+        let at = changed.first().copied().unwrap_or(0);
+        if bytes.len() != other.len()
+            || changed.len() != width
+            || changed[width - 1] != at + width - 1
+        {
+            return Err(format!("Cannot relocate `{}`", raw));
+        }
+        let mut field = [0u8; 8];
+        field[..width].copy_from_slice(&bytes[at..at + width]);
+        let offset_from_symbol = if wide {
+            u64::from_le_bytes(field).wrapping_sub(first) as i64
+        } else {
+            i64::from(i32::from_le_bytes(
+                field[..4].try_into().unwrap_or_default(),
+            )) - first as i64
+        };
+        bytes[at..at + width].fill(0);
+
+        let rip = ident_spans(&inst)
+            .iter()
+            .any(|(s, e)| inst[*s..*e].eq_ignore_ascii_case("rip"));
+        let (kind, addend) = if rip {
+            // The CPU adds the address of the next instruction, not of the field.
+            let to_end = (bytes.len() - at) as i64;
+            (RelocationKind::Pc32, offset_from_symbol - to_end)
+        } else if wide {
+            (RelocationKind::Abs64, offset_from_symbol)
+        } else {
+            (RelocationKind::Abs32S, offset_from_symbol)
+        };
+        let relocation = Relocation {
+            offset: pc + at as u64,
+            symbol,
+            kind,
+            addend,
+        };
+        Ok((bytes, Some(relocation)))
+    }
+}
+
+/// Assemble a program into relocatable object code, for linking into an executable with other
+/// objects, e.g. a C program calling a `global` function written here.
+///
+/// `.text` is assembled at offset 0. Branches within it are resolved here; every other symbol
+/// reference becomes a relocation. Write `[rel buf]` (or `default rel`) / `buf(%rip)` to get
+/// position-independent code, which is what `gcc` links by default.
+pub fn assemble_object(code: &str, syntax: Syntax) -> Result<ObjectCode, String> {
+    let source = parse_object_source(code)?;
+    let engine = init_engine(syntax.clone())?;
+
+    let mut text_labels = HashMap::new();
+    for e in &source.text {
+        if let Entry::Label(name) = e {
+            text_labels.insert(name.clone(), ());
+        }
+    }
+    let others: HashMap<String, ()> = source
+        .labels
+        .iter()
+        .map(|(name, _, _)| name)
+        .chain(&source.externs)
+        .map(|name| (name.clone(), ()))
+        .collect();
+    let relocator = Relocator {
+        engine: &engine,
+        syntax,
+        text_labels,
+        others,
+        externs: source.externs.clone(),
+        default_rel: source.default_rel,
+    };
+
+    // Settle label offsets as `assemble_x86_64` does, then encode for real.
+    let mut labels: HashMap<String, u64> = HashMap::new();
+    let mut last_labels: HashMap<String, u64> = HashMap::new();
+    for _ in 0..6 {
+        labels.clear();
+        let mut pc = 0u64;
+        for e in &source.text {
+            match e {
+                Entry::Label(name) => {
+                    labels.insert(name.clone(), pc);
+                }
+                Entry::Inst(line, raw) => {
+                    let (bytes, _) = relocator
+                        .encode(raw, pc, &last_labels)
+                        .map_err(|e| format!("line {}: {}", line, e))?;
+                    pc += bytes.len() as u64;
+                }
+            }
+        }
+        if labels == last_labels {
+            break;
+        }
+        last_labels = labels.clone();
+    }
+
+    let mut object = ObjectCode {
+        data: source.data,
+        bss_size: source.bss_size,
+        ..ObjectCode::default()
+    };
+    let mut errors = Vec::new();
+    for e in &source.text {
+        if let Entry::Inst(line, raw) = e {
+            let pc = object.text.len() as u64;
+            match relocator.encode(raw, pc, &labels) {
+                Ok((bytes, relocation)) => {
+                    object.text.extend(bytes);
+                    object.relocations.extend(relocation);
+                }
+                Err(e) => errors.push(format!("line {}: {}", line, e)),
+            }
+        }
+    }
+
+    let mut text_symbols: Vec<(&String, &u64)> = labels.iter().collect();
+    text_symbols.sort_by_key(|(name, offset)| (**offset, name.as_str()));
+    let defined = text_symbols
+        .into_iter()
+        .map(|(name, offset)| (name.clone(), ObjectSection::Text, *offset))
+        .chain(source.labels);
+    for (name, section, offset) in defined {
+        let global = source.globals.contains(&name);
+        object.symbols.push(ObjectSymbol {
+            name,
+            section: Some(section),
+            offset,
+            global,
+        });
+    }
+    for name in &source.globals {
+        if !object.symbols.iter().any(|s| &s.name == name) {
+            errors.push(format!("`global {}` has no label", name));
+        }
+    }
+    for name in source.externs {
+        if !object.symbols.iter().any(|s| s.name == name) {
+            object.symbols.push(ObjectSymbol {
+                name,
+                section: None,
+                offset: 0,
+                global: true,
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(object)
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+fn listing_rows(
+    out: &mut String,
+    line: Option<usize>,
+    address: Option<u64>,
+    bytes: &[u8],
+    text: &str,
+) {
+    // 10 bytes per row; longer encodings continue on the next row, marked with `-` as NASM does.
+    let chunks: Vec<&[u8]> = if bytes.is_empty() {
+        vec![&[]]
+    } else {
+        bytes.chunks(10).collect()
+    };
+    for (i, chunk) in chunks.iter().enumerate() {
+        let mut hex: String = chunk.iter().map(|b| format!("{:02X}", b)).collect();
+        if i + 1 < chunks.len() {
+            hex.push('-');
+        }
+        let row = format!(
+            "{:>6} {:8} {:<21} {}",
+            line.filter(|_| i == 0)
+                .map(|l| l.to_string())
+                .unwrap_or_default(),
+            address
+                .filter(|_| i == 0)
+                .map(|a| format!("{:08X}", a))
+                .unwrap_or_default(),
+            hex,
+            if i == 0 { text } else { "" }
+        );
+        out.push_str(row.trim_end());
+        out.push('\n');
+    }
+}
+
+/// A NASM-style `.lst` listing: line number, address, hex bytes and source text for every line
+/// of `code`. A line that assembled to several instructions (a pseudo-instruction expansion, or
+/// a label operand loaded through r15) lists each of them indented under it.
+pub fn listing(code: &str, assembled: &AssembleResult) -> String {
+    let mut by_line: HashMap<usize, Vec<&AssembledInstruction>> = HashMap::new();
+    for inst in &assembled.instructions {
+        by_line.entry(inst.line).or_default().push(inst);
+    }
+
+    let mut out = String::new();
+    for (idx, raw) in code.lines().enumerate() {
+        let raw = raw.trim_end();
+        match by_line.get(&(idx + 1)).map(Vec::as_slice) {
+            Some([only]) => listing_rows(
+                &mut out,
+                Some(idx + 1),
+                Some(only.address),
+                assembled.bytes_of(only),
+                raw,
+            ),
+            Some(expansion) => {
+                listing_rows(&mut out, Some(idx + 1), None, &[], raw);
+                let indent = &raw[..raw.len() - raw.trim_start().len()];
+                for inst in expansion {
+                    let text = format!("{}    {}", indent, inst.text.trim());
+                    listing_rows(
+                        &mut out,
+                        None,
+                        Some(inst.address),
+                        assembled.bytes_of(inst),
+                        &text,
+                    );
+                }
+            }
+            None => listing_rows(&mut out, Some(idx + 1), None, &[], raw),
+        }
+    }
+    out
 }
 
 #[cfg(test)]
@@ -217,4 +1281,220 @@ mod tests {
         let res = assemble_x86_64(code, Syntax::Att, 0x1000).unwrap();
         assert!(!res.bytes.is_empty());
     }
+
+    #[test]
+    fn lists_source_instructions_of_text_section() {
+        let code = r#"
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    in rax          ; pseudo-instruction
+.loop: dec rax
+    jnz .loop
+    # exit
+    movq $60, %rax
+"#;
+        let insts = source_instructions(code);
+        let mnemonics: Vec<&str> = insts.iter().map(|i| i.mnemonic.as_str()).collect();
+        assert_eq!(mnemonics, vec!["in", "dec", "jnz", "movq"]);
+        assert_eq!(insts[0].line, 9);
+    }
+
+    #[test]
+    fn maps_register_aliases_to_their_family() {
+        assert_eq!(register_family("al"), Some(Register::RAX));
+        assert_eq!(register_family("%ecx"), Some(Register::RCX));
+        assert_eq!(register_family("sil"), Some(Register::RSI));
+        assert_eq!(register_family("r8b"), Some(Register::R8));
+        assert_eq!(register_family("R15D"), Some(Register::R15));
+        assert_eq!(register_family("r7"), None);
+        assert_eq!(register_family("r16"), None);
+        assert_eq!(register_family("buf"), None);
+    }
+
+    #[test]
+    fn counts_registers_named_in_operands() {
+        let code = r#"
+_start:
+    mov rax, 0
+    mov dl, [buf+rcx]   ; rdx via dl
+    xor r9d, r9d
+    movq %rax, %rbx
+"#;
+        assert_eq!(
+            registers_used(code),
+            vec![
+                Register::RAX,
+                Register::RDX,
+                Register::RCX,
+                Register::R9,
+                Register::RBX
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_syntax_from_operands() {
+        assert!(matches!(
+            detect_syntax("_start:\n    movq $60, %rax\n    syscall\n"),
+            Syntax::Att
+        ));
+        assert!(matches!(
+            detect_syntax("_start:\n    mov rax, 60\n    syscall\n"),
+            Syntax::Intel
+        ));
+    }
+
+    #[test]
+    fn splits_operands_outside_brackets() {
+        assert_eq!(
+            split_operands("buf(%r8,%rcx,1), %al"),
+            vec!["buf(%r8,%rcx,1)", "%al"]
+        );
+        assert_eq!(
+            split_operands("byte [buf + r8], al"),
+            vec!["byte [buf + r8]", "al"]
+        );
+        assert!(split_operands("").is_empty());
+    }
+
+    #[test]
+    fn reports_signals_and_ambiguity() {
+        let att = analyze_syntax("_start:\n    movq $60, %rax\n    incq %rdi\n    syscall\n");
+        assert!(matches!(att.syntax, Syntax::Att));
+        assert!(!att.ambiguous);
+        let suffix = att
+            .signals
+            .iter()
+            .find(|s| s.description == "size suffixes")
+            .unwrap();
+        assert_eq!((suffix.count, suffix.first_line), (2, 2));
+
+        // Intel operands with an AT&T suffix.
+        let mixed = analyze_syntax("_start:\n    movq rax, 60\n");
+        assert!(mixed.ambiguous);
+        assert!(matches!(mixed.syntax, Syntax::Intel));
+        assert!(mixed
+            .describe()
+            .contains("AT&T: size suffixes (1, first on line 2)"));
+
+        let bare = analyze_syntax("_start:\n    syscall\n");
+        assert!(bare.ambiguous && bare.signals.is_empty());
+    }
+
+    #[test]
+    fn listing_indents_expansions_under_their_line() {
+        let code = "_start:\n    mov rax, 1\n    in rbx ; read\n";
+        let inst = |address: u64, size: usize, line: usize, text: &str| AssembledInstruction {
+            address,
+            size,
+            line,
+            text: text.to_string(),
+        };
+        let assembled = AssembleResult {
+            base_addr: 0x100000,
+            bytes: vec![
+                0x48, 0xc7, 0xc0, 0x01, 0x00, 0x00, 0x00, // mov rax, 1
+                0x50, // push rax
+                // 11 bytes, to wrap onto a second row
+                0x66, 0x48, 0xb8, 0xe8, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ],
+            labels: HashMap::new(),
+            instructions: vec![
+                inst(0x100000, 7, 2, "mov rax, 1"),
+                inst(0x100007, 1, 3, "push rax"),
+                inst(0x100008, 11, 3, "mov rax, 0x3e8"),
+            ],
+        };
+        let lst = listing(code, &assembled);
+        let lines: Vec<&str> = lst.lines().collect();
+        assert_eq!(lines[0], "     1                                _start:");
+        assert_eq!(
+            lines[1],
+            "     2 00100000 48C7C001000000            mov rax, 1"
+        );
+        assert_eq!(
+            lines[2],
+            "     3                                    in rbx ; read"
+        );
+        assert_eq!(
+            lines[3],
+            "       00100007 50                            push rax"
+        );
+        assert_eq!(
+            lines[4],
+            "       00100008 6648B8E8030000000000-         mov rax, 0x3e8"
+        );
+        assert_eq!(lines[5], "                00");
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn lays_out_object_data_and_bss() {
+        let code = "section .data
+    msg db \"hi\", 10
+    nums: dw 1, -1
+    align 8
+    big dq 0x1122
+    s: .asciz \"a\\n\"
+section .bss
+    buf resb 3
+    count: resq 1
+section .text
+    global f
+f:  ret
+";
+        let source = parse_object_source(code).unwrap();
+        let mut data = b"hi\n\x01\x00\xff\xff\x00".to_vec();
+        data.extend(0x1122u64.to_le_bytes());
+        data.extend(b"a\n\0");
+        assert_eq!(source.data, data);
+        assert_eq!(source.bss_size, 11);
+        let labels: Vec<(&str, ObjectSection, u64)> = source
+            .labels
+            .iter()
+            .map(|(n, s, o)| (n.as_str(), *s, *o))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                ("msg", ObjectSection::Data, 0),
+                ("nums", ObjectSection::Data, 3),
+                ("big", ObjectSection::Data, 8),
+                ("s", ObjectSection::Data, 16),
+                ("buf", ObjectSection::Bss, 0),
+                ("count", ObjectSection::Bss, 3),
+            ]
+        );
+        assert_eq!(source.globals, vec!["f"]);
+        assert_eq!(source.text.len(), 2);
+
+        let err = parse_object_source("section .bss\n    x db 1\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn rewrites_nasm_rel_operands_for_keystone() {
+        assert_eq!(
+            rip_relative("lea rdi, [rel msg]", false),
+            "lea rdi, [rip + msg]"
+        );
+        assert_eq!(
+            rip_relative("mov eax, [count]", true),
+            "mov eax, [rip + count]"
+        );
+        assert_eq!(
+            rip_relative("mov eax, [abs count]", true),
+            "mov eax, [count]"
+        );
+        assert_eq!(
+            rip_relative("mov eax, [rbx + 8]", true),
+            "mov eax, [rbx + 8]"
+        );
+        assert_eq!(rip_relative("mov eax, [count]", false), "mov eax, [count]");
+    }
 }
@@ -1,18 +1,61 @@
-use crate::vm::{Register, Syntax, VmState};
-use crate::x86_asm::assemble_x86_64;
-
-use std::collections::{HashMap, VecDeque};
-
-use unicorn_engine::unicorn_const::{Arch, Mode, Prot, RegisterX86, X86Insn};
+use crate::elf;
+use crate::vm::{
+    CallFrame, CallIssue, MemoryChange, MemoryOrigin, MemoryRegion, Register, Syntax, VmState,
+};
+use crate::x86_asm::{assemble_x86_64, AssembleResult, AssembledInstruction};
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use unicorn_engine::unicorn_const::{
+    uc_error, Arch, HookType, MemType, Mode, Prot, RegisterX86, X86Insn,
+};
 use unicorn_engine::Unicorn;
 
-const CODE_BASE: u64 = 0x0010_0000;
-const BSS_BASE: u64 = 0x0020_0000;
+/// Load address of the assembled `.text`.
+pub const CODE_BASE: u64 = 0x0010_0000;
+/// Load address of the first `.bss` label.
+pub const BSS_BASE: u64 = 0x0020_0000;
 const STACK_BASE: u64 = 0x0030_0000;
 const STACK_SIZE: u64 = 0x0020_0000; // 2MB
+/// Initial RSP; the stack grows down from here.
+pub const STACK_TOP: u64 = STACK_BASE + STACK_SIZE - 8;
+
+/// Top of the stack of a loaded ELF; `ld` links at 0x400000, right in our usual stack.
+const ELF_STACK_TOP: u64 = 0x7fff_0000_0000;
 
 const PAGE_SIZE: u64 = 0x1000;
 
+/// Where `brk` starts for an assembled program, well clear of `.bss` and the stack.
+const HEAP_BASE: u64 = 0x0100_0000;
+/// Most the heap may grow to through `brk`.
+const HEAP_LIMIT: u64 = 0x0100_0000; // 16MB
+/// Anonymous `mmap`s are placed upwards from here.
+const MMAP_BASE: u64 = 0x7f00_0000_0000;
+/// Most memory the live anonymous mappings may hold together.
+const MMAP_LIMIT: u64 = 0x0400_0000; // 64MB
+
+// Linux errno values, returned negated in RAX.
+const EBADF: i64 = 9;
+const ENOMEM: i64 = 12;
+const EINVAL: i64 = 22;
+
+// `mmap` flags.
+const MAP_FIXED: u64 = 0x10;
+const MAP_ANONYMOUS: u64 = 0x20;
+
+// Auxiliary vector keys from <elf.h>.
+const AT_NULL: u64 = 0;
+const AT_PHDR: u64 = 3;
+const AT_PHENT: u64 = 4;
+const AT_PHNUM: u64 = 5;
+const AT_PAGESZ: u64 = 6;
+const AT_ENTRY: u64 = 9;
+const AT_RANDOM: u64 = 25;
+
 fn align_up(x: u64, align: u64) -> u64 {
     if x.is_multiple_of(align) {
         x
@@ -27,20 +70,125 @@ struct RuntimeData {
     output: Vec<i64>,
     exited: bool,
     error: Option<String>,
+    instructions: usize,
+    trace: Vec<String>,
+    /// Shadow stack of active calls, innermost last.
+    calls: Vec<ShadowFrame>,
+    call_issues: Vec<CallIssue>,
+    /// Mappings of the address space, by address.
+    memory_map: Vec<MemoryRegion>,
+    /// First address of the heap and the current program break.
+    heap_start: u64,
+    brk: u64,
+    /// Where the next anonymous `mmap` goes.
+    mmap_next: u64,
+    watchpoints: Vec<Watchpoint>,
+    /// Watched accesses not yet collected by `take_watch_hits`.
+    watch_hits: Vec<WatchHit>,
+    /// Record every write in `writes`, as address, old bytes and new bytes.
+    track_writes: bool,
+    /// Writes of the latest `Session::run`, oldest first.
+    writes: Vec<(u64, Vec<u8>, Vec<u8>)>,
+    limits: RunLimits,
+}
+
+/// A wall-clock deadline and a cancel switch for every session created inside `with_limits`,
+/// so a whole simulation (all its test cases) can be bounded or stopped from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct RunLimits {
+    pub deadline: Option<Instant>,
+    pub cancel: Arc<AtomicBool>,
+    /// Set once one of the sessions stopped at the deadline.
+    pub timed_out: Arc<AtomicBool>,
+}
+
+impl RunLimits {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Whether the deadline cut a run short, as opposed to passing after the work was done.
+    pub fn has_timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    pub fn is_past_deadline(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+thread_local! {
+    static LIMITS: RefCell<RunLimits> = RefCell::new(RunLimits::default());
+}
+
+/// Run `f` with `limits` applying to the sessions it creates on this thread.
+pub fn with_limits<T>(limits: RunLimits, f: impl FnOnce() -> T) -> T {
+    let outer = LIMITS.with(|l| l.replace(limits));
+    let result = f();
+    LIMITS.with(|l| *l.borrow_mut() = outer);
+    result
+}
+
+/// Which accesses a watchpoint reacts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    /// Reads and writes.
+    Access,
+}
+
+/// `len` bytes from `address` to watch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub address: u64,
+    pub len: u64,
+    pub kind: WatchKind,
+}
+
+/// An access to watched memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Index into the watchpoints given to `set_watchpoints`.
+    pub watchpoint: usize,
+    /// `Read` or `Write`.
+    pub access: WatchKind,
+    pub address: u64,
+    pub size: usize,
+    /// The whole watched range before and after the access (equal for a read).
+    pub old: Vec<u8>,
+    pub new: Vec<u8>,
+}
+
+struct ShadowFrame {
+    frame: CallFrame,
+    /// The callee has not run its first instruction yet, so its label is unknown.
+    entered: bool,
 }
 
 pub struct RunResult {
     pub state: VmState,
     pub execution_log: Vec<String>,
+    /// Final RFLAGS; `state` only carries ZF and SF.
+    pub rflags: u64,
 }
 
-#[derive(Default)]
-struct BssLayout {
-    total_size: u64,
-    labels: HashMap<String, u64>,
+/// How to run a program. `run_x86_64` is the common case of a plain instruction limit.
+#[derive(Debug, Clone, Default)]
+pub struct RunConfig {
+    pub max_instructions: usize,
+    /// Append one `execution_log` line per executed instruction (address, source line, text).
+    pub trace: bool,
+}
+
+/// `resb` reservations of `.bss`, laid out from `BSS_BASE` in source order.
+#[derive(Debug, Default)]
+pub struct BssLayout {
+    pub total_size: u64,
+    pub labels: HashMap<String, u64>,
 }
 
-fn parse_bss_layout(code: &str) -> BssLayout {
+pub fn parse_bss_layout(code: &str) -> BssLayout {
     let mut layout = BssLayout::default();
     let mut in_bss = false;
     let mut offset = 0u64;
@@ -84,11 +232,12 @@ fn parse_bss_layout(code: &str) -> BssLayout {
     layout
 }
 
-fn extract_text_section(code: &str) -> String {
+/// Text-section lines with their 1-based line numbers in `code`.
+fn extract_text_section(code: &str) -> Vec<(usize, String)> {
     let mut in_text = false;
-    let mut out = String::new();
+    let mut out = Vec::new();
 
-    for raw in code.lines() {
+    for (idx, raw) in code.lines().enumerate() {
         let line = raw.split(&[';', '#'][..]).next().unwrap_or("").trim_end();
         if line.trim().is_empty() {
             continue;
@@ -108,13 +257,12 @@ fn extract_text_section(code: &str) -> String {
             continue;
         }
 
-        out.push_str(line);
-        out.push('\n');
+        out.push((idx + 1, line.to_string()));
     }
 
     // If there was no explicit .text, fall back to all non-.bss lines
-    if out.trim().is_empty() {
-        for raw in code.lines() {
+    if out.is_empty() {
+        for (idx, raw) in code.lines().enumerate() {
             let line = raw.split(&[';', '#'][..]).next().unwrap_or("").trim_end();
             if line.trim().is_empty() {
                 continue;
@@ -133,127 +281,140 @@ fn extract_text_section(code: &str) -> String {
             {
                 continue;
             }
-            out.push_str(line);
-            out.push('\n');
+            out.push((idx + 1, line.to_string()));
         }
     }
 
     out
 }
 
-fn preprocess_text(code: &str, syntax: &Syntax, bss_labels: &HashMap<String, u64>) -> String {
+/// Rewritten text-section lines, each tagged with the source line it came from.
+fn preprocess_text(
+    code: &str,
+    syntax: &Syntax,
+    bss_labels: &HashMap<String, u64>,
+) -> Vec<(usize, String)> {
     // Minimal source-to-source transforms to support existing tutorial syntax:
     // - Replace immediate usage: `mov rsi, buf` -> `mov rsi, 0xADDR`
     // - Replace memory label usage in Intel style: `[buf + r8]` -> `mov r15, 0xADDR` + `[r15 + r8]`
-    // - Replace AT&T displacement usage: `buf(%rsi)` -> `0xADDR(%rsi)` and `$buf` -> `$0xADDR`
+    // - Replace AT&T label usage: `buf(%rsi)` -> `0xADDR(%rsi)`, `$buf` -> `$0xADDR`, `buf` -> `0xADDR`
     //
     // NOTE: This uses r15 as a scratch register for Intel memory operands.
-    let mut out = String::new();
-    let text = extract_text_section(code);
-
-    for raw in text.lines() {
+    let mut out = Vec::new();
+    for (line_no, raw) in extract_text_section(code) {
         let line = raw.trim();
         if line.is_empty() {
             continue;
         }
+        let mut chunk = String::new();
+        preprocess_line(line, syntax, bss_labels, &mut chunk);
+        out.extend(chunk.lines().map(|l| (line_no, l.to_string())));
+    }
+    out
+}
 
-        let line = normalize_decimal_literals(line);
-
-        // Pseudo-IO: support tutorial `in <reg>` by rewriting it into a custom syscall.
-        // This keeps tutorial content readable while still using Keystone+Unicorn.
-        //
-        // `in rax`  -> `mov rax, 0x3e8; syscall`
-        // `in rbx`  -> `mov rax, 0x3e8; syscall; mov rbx, rax`
-        let lower = line.trim_start().to_lowercase();
-        if lower.starts_with("in ") {
-            let dst_raw = line.trim_start()[2..].trim();
-            let dst = dst_raw.trim_start_matches('%').to_lowercase();
-            match syntax {
-                Syntax::Intel => {
-                    if dst == "rax" {
-                        out.push_str("mov rax, 0x3e8\nsyscall\n");
-                    } else {
-                        // Preserve RAX across pseudo-IN, because some stages use `in rbx`/`in rcx`
-                        // after having loaded a meaningful value into RAX.
-                        out.push_str("push rax\n");
-                        out.push_str("mov rax, 0x3e8\nsyscall\n");
-                        out.push_str(&format!("mov {}, rax\n", dst));
-                        out.push_str("pop rax\n");
-                    }
-                }
-                Syntax::Att => {
-                    if dst == "rax" {
-                        out.push_str("movq $0x3e8, %rax\nsyscall\n");
-                    } else {
-                        out.push_str("pushq %rax\n");
-                        out.push_str("movq $0x3e8, %rax\nsyscall\n");
-                        out.push_str(&format!("movq %rax, %{}\n", dst));
-                        out.push_str("popq %rax\n");
-                    }
-                }
-            }
-            continue;
-        }
+fn preprocess_line(
+    line: &str,
+    syntax: &Syntax,
+    bss_labels: &HashMap<String, u64>,
+    out: &mut String,
+) {
+    let line = normalize_decimal_literals(line);
 
-        // Keystone label fixups do not handle `loop <label>` well when we rewrite labels
-        // into absolute immediates. Rewrite it into `dec rcx; jnz <label>`.
-        if lower.starts_with("loop ") {
-            let target = line.trim_start()[4..].trim();
-            match syntax {
-                Syntax::Intel => {
-                    out.push_str("dec rcx\n");
-                    out.push_str(&format!("jnz {}\n", target));
+    // Pseudo-IO: support tutorial `in <reg>` by rewriting it into a custom syscall.
+    // This keeps tutorial content readable while still using Keystone+Unicorn.
+    //
+    // `in rax`  -> `mov rax, 0x3e8; syscall`
+    // `in rbx`  -> `mov rax, 0x3e8; syscall; mov rbx, rax`
+    let lower = line.trim_start().to_lowercase();
+    if lower.starts_with("in ") {
+        let dst_raw = line.trim_start()[2..].trim();
+        let dst = dst_raw.trim_start_matches('%').to_lowercase();
+        match syntax {
+            Syntax::Intel => {
+                if dst == "rax" {
+                    out.push_str("mov rax, 0x3e8\nsyscall\n");
+                } else {
+                    // Preserve RAX across pseudo-IN, because some stages use `in rbx`/`in rcx`
+                    // after having loaded a meaningful value into RAX.
+                    out.push_str("push rax\n");
+                    out.push_str("mov rax, 0x3e8\nsyscall\n");
+                    out.push_str(&format!("mov {}, rax\n", dst));
+                    out.push_str("pop rax\n");
                 }
-                Syntax::Att => {
-                    out.push_str("decq %rcx\n");
-                    out.push_str(&format!("jnz {}\n", target));
+            }
+            Syntax::Att => {
+                if dst == "rax" {
+                    out.push_str("movq $0x3e8, %rax\nsyscall\n");
+                } else {
+                    out.push_str("pushq %rax\n");
+                    out.push_str("movq $0x3e8, %rax\nsyscall\n");
+                    out.push_str(&format!("movq %rax, %{}\n", dst));
+                    out.push_str("popq %rax\n");
                 }
             }
-            continue;
         }
+        return;
+    }
 
+    // Keystone label fixups do not handle `loop <label>` well when we rewrite labels
+    // into absolute immediates. Rewrite it into `dec rcx; jnz <label>`.
+    if lower.starts_with("loop ") {
+        let target = line.trim_start()[4..].trim();
         match syntax {
             Syntax::Intel => {
-                if let Some((before, inside, after)) = split_first_bracket(&line) {
-                    if let Some((label, rest)) = take_leading_ident(inside) {
-                        if let Some(addr) = bss_labels.get(label) {
-                            // Insert scratch-load and rewrite [label ...] -> [r15 ...]
-                            out.push_str(&format!("mov r15, 0x{:x}\n", addr));
-                            out.push_str(before);
-                            out.push('[');
-                            out.push_str("r15");
-                            out.push_str(rest);
-                            out.push(']');
-                            out.push_str(after);
-                            out.push('\n');
-                            continue;
-                        }
+                out.push_str("dec rcx\n");
+                out.push_str(&format!("jnz {}\n", target));
+            }
+            Syntax::Att => {
+                out.push_str("decq %rcx\n");
+                out.push_str(&format!("jnz {}\n", target));
+            }
+        }
+        return;
+    }
+
+    match syntax {
+        Syntax::Intel => {
+            if let Some((before, inside, after)) = split_first_bracket(&line) {
+                if let Some((label, rest)) = take_leading_ident(inside) {
+                    if let Some(addr) = bss_labels.get(label) {
+                        // Insert scratch-load and rewrite [label ...] -> [r15 ...]
+                        out.push_str(&format!("mov r15, 0x{:x}\n", addr));
+                        out.push_str(before);
+                        out.push('[');
+                        out.push_str("r15");
+                        out.push_str(rest);
+                        out.push(']');
+                        out.push_str(after);
+                        out.push('\n');
+                        return;
                     }
                 }
+            }
 
-                // Immediate substitution for bss labels used outside brackets
-                let mut replaced = line.to_string();
-                for (name, addr) in bss_labels {
-                    replaced = replace_ident(&replaced, name, &format!("0x{:x}", addr));
-                }
-                out.push_str(&replaced);
-                out.push('\n');
+            // Immediate substitution for bss labels used outside brackets
+            let mut replaced = line.to_string();
+            for (name, addr) in bss_labels {
+                replaced = replace_ident(&replaced, name, &format!("0x{:x}", addr));
             }
-            Syntax::Att => {
-                let mut replaced = line.to_string();
-                for (name, addr) in bss_labels {
-                    // `$buf` -> `$0x...`
-                    replaced = replaced.replace(&format!("${}", name), &format!("$0x{:x}", addr));
-                    // `buf(` -> `0x...(`  (displacement before parens)
-                    replaced = replaced.replace(&format!("{}(", name), &format!("0x{:x}(", addr));
-                }
-                out.push_str(&replaced);
-                out.push('\n');
+            out.push_str(&replaced);
+            out.push('\n');
+        }
+        Syntax::Att => {
+            let mut replaced = line.to_string();
+            for (name, addr) in bss_labels {
+                // `$buf` -> `$0x...`
+                replaced = replaced.replace(&format!("${}", name), &format!("$0x{:x}", addr));
+                // `buf(` -> `0x...(`  (displacement before parens)
+                replaced = replaced.replace(&format!("{}(", name), &format!("0x{:x}(", addr));
+                // Any other use: `buf+2(%r8)`, absolute `buf`
+                replaced = replace_ident(&replaced, name, &format!("0x{:x}", addr));
             }
+            out.push_str(&replaced);
+            out.push('\n');
         }
     }
-
-    out
 }
 
 fn normalize_decimal_literals(line: &str) -> String {
@@ -469,184 +630,1068 @@ fn replace_ident(input: &str, ident: &str, replacement: &str) -> String {
     out
 }
 
+/// Assemble a program exactly as `run_x86_64` loads it (after pseudo-instruction rewriting).
+/// `instructions[].line` refers to the original source, so an expansion shares its line.
+pub fn assemble_program(code: &str, syntax: Syntax) -> Result<AssembleResult, String> {
+    let bss = parse_bss_layout(code);
+    let preprocessed = preprocess_text(code, &syntax, &bss.labels);
+    let text: Vec<&str> = preprocessed.iter().map(|(_, l)| l.as_str()).collect();
+    let mut assembled = assemble_x86_64(&text.join("\n"), syntax, CODE_BASE)?;
+    // Point every instruction back at the line the learner wrote.
+    for inst in &mut assembled.instructions {
+        inst.line = preprocessed[inst.line - 1].0;
+    }
+    Ok(assembled)
+}
+
 pub fn run_x86_64(
     code: &str,
     syntax: Syntax,
     input: Vec<i64>,
     max_instructions: usize,
 ) -> Result<RunResult, String> {
-    let mut execution_log: Vec<String> = Vec::new();
-
-    let bss = parse_bss_layout(code);
-    let bss_size = align_up(bss.total_size.max(512), PAGE_SIZE);
+    let config = RunConfig {
+        max_instructions,
+        ..RunConfig::default()
+    };
+    run_x86_64_with(code, syntax, input, &config)
+}
 
-    let preprocessed = preprocess_text(code, &syntax, &bss.labels);
-    execution_log.push("Assembling...".to_string());
+pub fn run_x86_64_with(
+    code: &str,
+    syntax: Syntax,
+    input: Vec<i64>,
+    config: &RunConfig,
+) -> Result<RunResult, String> {
+    let mut session = Session::new(code, syntax, input, config.trace)?;
+    session.run(config.max_instructions);
+    if !session.is_finished() {
+        // If we stopped without exit and without explicit error, we likely hit instruction limit.
+        session.emu.get_data_mut().error = Some("Instruction limit reached".to_string());
+    }
+    Ok(session.finish())
+}
 
-    let assembled = assemble_x86_64(&preprocessed, syntax.clone(), CODE_BASE)?;
-    let code_size = align_up(assembled.bytes.len().max(1) as u64, PAGE_SIZE);
+/// Load a static executable and run it like `run_x86_64_with`.
+pub fn run_elf(
+    image: &[u8],
+    args: &[String],
+    env: &[String],
+    input: Vec<i64>,
+    config: &RunConfig,
+) -> Result<RunResult, String> {
+    let mut session = Session::from_elf(image, args, env, input, config.trace)?;
+    session.run(config.max_instructions);
+    if !session.is_finished() {
+        session.emu.get_data_mut().error = Some("Instruction limit reached".to_string());
+    }
+    Ok(session.finish())
+}
 
-    // Set up Unicorn
+fn new_emulator(input: Vec<i64>) -> Result<Unicorn<'static, RuntimeData>, String> {
     let data = RuntimeData {
         input: VecDeque::from(input),
-        output: Vec::new(),
-        exited: false,
-        error: None,
+        limits: LIMITS.with(|l| l.borrow().clone()),
+        ..RuntimeData::default()
     };
+    Unicorn::new_with_data(Arch::X86, Mode::MODE_64, data).map_err(|e| format!("{e:?}"))
+}
+
+/// The stack Linux hands a new process, ending below `top`: the string bytes at the top, and at
+/// the returned (16-byte aligned) RSP argc, argv, NULL, envp, NULL, then auxv pairs up to AT_NULL.
+fn initial_stack(
+    top: u64,
+    exe: &elf::Executable,
+    args: &[String],
+    env: &[String],
+) -> (u64, Vec<u8>) {
+    let mut strings: Vec<u8> = Vec::new();
+    let mut offsets = Vec::new();
+    for s in args.iter().chain(env) {
+        offsets.push(strings.len() as u64);
+        strings.extend_from_slice(s.as_bytes());
+        strings.push(0);
+    }
+    // AT_RANDOM seeds stack protectors; keep it fixed so runs are reproducible.
+    let random = strings.len() as u64;
+    strings.extend((0..16u8).map(|i| i.wrapping_mul(0x9d) ^ 0x5a));
+    let strings_base = (top - strings.len() as u64) & !0xf;
+
+    let mut auxv = vec![
+        (AT_PAGESZ, PAGE_SIZE),
+        (AT_ENTRY, exe.entry),
+        (AT_PHENT, 56),
+        (AT_PHNUM, exe.phnum as u64),
+        (AT_RANDOM, strings_base + random),
+    ];
+    if let Some(phdr) = exe.phdr_addr {
+        auxv.push((AT_PHDR, phdr));
+    }
+    auxv.push((AT_NULL, 0));
+
+    let pointer = |i: usize| strings_base + offsets[i];
+    let mut words = vec![args.len() as u64];
+    words.extend((0..args.len()).map(pointer));
+    words.push(0);
+    words.extend((args.len()..args.len() + env.len()).map(pointer));
+    words.push(0);
+    for (key, value) in auxv {
+        words.extend([key, value]);
+    }
+
+    let rsp = (strings_base - 8 * words.len() as u64) & !0xf;
+    let mut frame: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    frame.resize((strings_base - rsp) as usize, 0);
+    frame.extend(strings);
+    (rsp, frame)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Control {
+    Call,
+    Ret,
+    Other,
+}
+
+/// Whether the instruction encoded in `bytes` is a `call` (direct or indirect) or a `ret`.
+fn control_kind(bytes: &[u8]) -> Control {
+    let opcode = bytes
+        .iter()
+        .position(|b| !matches!(b, 0x66 | 0xf2 | 0xf3 | 0x40..=0x4f))
+        .unwrap_or(bytes.len());
+    match bytes.get(opcode) {
+        Some(0xe8) => Control::Call,
+        Some(0xff) if bytes.get(opcode + 1).is_some_and(|m| (m >> 3) & 7 == 2) => Control::Call,
+        Some(0xc3 | 0xc2) => Control::Ret,
+        _ => Control::Other,
+    }
+}
+
+/// [`control_kind`] of the instruction in emulator memory at `addr`.
+fn read_control(uc: &Unicorn<'_, RuntimeData>, addr: u64, size: u32) -> Control {
+    let mut bytes = [0u8; 16];
+    let len = (size as usize).min(bytes.len());
+    match uc.mem_read(addr, &mut bytes[..len]) {
+        Ok(()) => control_kind(&bytes[..len]),
+        Err(_) => Control::Other,
+    }
+}
+
+/// `rwx` / `-` flags of a protection, as in `/proc/<pid>/maps`.
+fn perms(prot: Prot) -> String {
+    [(Prot::READ, 'r'), (Prot::WRITE, 'w'), (Prot::EXEC, 'x')]
+        .iter()
+        .map(|(p, c)| if prot.contains(*p) { *c } else { '-' })
+        .collect()
+}
+
+/// Map memory and record it in the memory map.
+fn map_region(
+    uc: &mut Unicorn<'_, RuntimeData>,
+    start: u64,
+    size: u64,
+    prot: Prot,
+    origin: MemoryOrigin,
+) -> Result<(), uc_error> {
+    uc.mem_map(start, size, prot)?;
+    let map = &mut uc.get_data_mut().memory_map;
+    let at = map.partition_point(|r| r.start < start);
+    map.insert(
+        at,
+        MemoryRegion {
+            start,
+            size,
+            perms: perms(prot),
+            origin,
+        },
+    );
+    Ok(())
+}
+
+/// What is left of `region` once `start..end` is cut out of it.
+fn cut_region(region: &MemoryRegion, start: u64, end: u64) -> Vec<MemoryRegion> {
+    let region_end = region.start + region.size;
+    if end <= region.start || region_end <= start {
+        return vec![region.clone()];
+    }
+    let mut left = Vec::new();
+    if region.start < start {
+        left.push(MemoryRegion {
+            size: start - region.start,
+            ..region.clone()
+        });
+    }
+    if end < region_end {
+        left.push(MemoryRegion {
+            start: end,
+            size: region_end - end,
+            ..region.clone()
+        });
+    }
+    left
+}
+
+/// Record a hit for every watchpoint that `bytes` accessed at `addr` (read, or about to be
+/// written) touches, and the write itself if writes are tracked.
+fn note_access(uc: &mut Unicorn<'_, RuntimeData>, access: WatchKind, addr: u64, bytes: &[u8]) {
+    if access == WatchKind::Write && uc.get_data().track_writes {
+        let mut old = vec![0u8; bytes.len()];
+        let _ = uc.mem_read(addr, &mut old);
+        uc.get_data_mut().writes.push((addr, old, bytes.to_vec()));
+    }
+    let end = addr + bytes.len() as u64;
+    let touched: Vec<(usize, Watchpoint)> = uc
+        .get_data()
+        .watchpoints
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| w.kind == access || w.kind == WatchKind::Access)
+        .filter(|(_, w)| w.address < end && addr < w.address + w.len)
+        .map(|(i, w)| (i, w.clone()))
+        .collect();
+    for (i, w) in touched {
+        let mut old = vec![0u8; w.len as usize];
+        let _ = uc.mem_read(w.address, &mut old);
+        let mut new = old.clone();
+        if access == WatchKind::Write {
+            for (k, b) in bytes.iter().enumerate() {
+                let at = addr + k as u64;
+                if (w.address..w.address + w.len).contains(&at) {
+                    new[(at - w.address) as usize] = *b;
+                }
+            }
+        }
+        uc.get_data_mut().watch_hits.push(WatchHit {
+            watchpoint: i,
+            access,
+            address: addr,
+            size: bytes.len(),
+            old,
+            new,
+        });
+    }
+}
 
-    let mut emu: Unicorn<RuntimeData> =
-        Unicorn::new_with_data(Arch::X86, Mode::MODE_64, data).map_err(|e| format!("{e:?}"))?;
+/// Bytes whose value differs after `writes` (applied in order), merged into runs of adjacent
+/// addresses.
+fn compact_changes(writes: &[(u64, Vec<u8>, Vec<u8>)]) -> Vec<MemoryChange> {
+    // address -> (value before the first write, value after the last)
+    let mut bytes: BTreeMap<u64, (u8, u8)> = BTreeMap::new();
+    for (addr, old, new) in writes {
+        for (i, (o, n)) in old.iter().zip(new).enumerate() {
+            bytes.entry(addr + i as u64).or_insert((*o, *n)).1 = *n;
+        }
+    }
+    let mut changes: Vec<MemoryChange> = Vec::new();
+    for (addr, (old, new)) in bytes.into_iter().filter(|(_, (o, n))| o != n) {
+        match changes.last_mut() {
+            Some(last) if last.address + last.new.len() as u64 == addr => {
+                last.old.push(old);
+                last.new.push(new);
+            }
+            _ => changes.push(MemoryChange {
+                address: addr,
+                old: vec![old],
+                new: vec![new],
+            }),
+        }
+    }
+    changes
+}
 
-    emu.mem_map(CODE_BASE, code_size, Prot::ALL)
+/// `-errno` as the kernel returns it in RAX.
+fn errno(code: i64) -> u64 {
+    (-code) as u64
+}
+
+/// brk(addr): move the program break within `HEAP_LIMIT` of the heap's start and return the
+/// new break, or the old one if `addr` is out of range (`brk(0)` asks for the current one).
+fn sys_brk(uc: &mut Unicorn<'_, RuntimeData>) -> u64 {
+    let want = uc.reg_read(RegisterX86::RDI).unwrap_or(0);
+    let (start, brk) = (uc.get_data().heap_start, uc.get_data().brk);
+    if want < start || want > start + HEAP_LIMIT {
+        return brk;
+    }
+    let (old_end, new_end) = (align_up(brk, PAGE_SIZE), align_up(want, PAGE_SIZE));
+    if new_end > old_end {
+        if uc
+            .mem_map(old_end, new_end - old_end, Prot::READ | Prot::WRITE)
+            .is_err()
+        {
+            return brk;
+        }
+    } else if new_end < old_end && uc.mem_unmap(new_end, old_end - new_end).is_err() {
+        return brk;
+    }
+
+    let data = uc.get_data_mut();
+    data.brk = want;
+    data.memory_map.retain(|r| r.origin != MemoryOrigin::Heap);
+    if new_end > start {
+        let at = data.memory_map.partition_point(|r| r.start < start);
+        data.memory_map.insert(
+            at,
+            MemoryRegion {
+                start,
+                size: new_end - start,
+                perms: perms(Prot::READ | Prot::WRITE),
+                origin: MemoryOrigin::Heap,
+            },
+        );
+    }
+    want
+}
+
+/// mmap(addr, len, prot, flags, fd, off): only private or shared anonymous memory, placed by
+/// the emulator (`addr` is a hint it ignores, `MAP_FIXED` is refused).
+fn sys_mmap(uc: &mut Unicorn<'_, RuntimeData>) -> u64 {
+    let len = uc.reg_read(RegisterX86::RSI).unwrap_or(0);
+    let prot = uc.reg_read(RegisterX86::RDX).unwrap_or(0);
+    let flags = uc.reg_read(RegisterX86::R10).unwrap_or(0);
+    if flags & MAP_ANONYMOUS == 0 {
+        // There are no files to map.
+        return errno(EBADF);
+    }
+    if len == 0 || flags & MAP_FIXED != 0 || prot & !7 != 0 {
+        return errno(EINVAL);
+    }
+    let size = align_up(len, PAGE_SIZE);
+    let in_use: u64 = uc
+        .get_data()
+        .memory_map
+        .iter()
+        .filter(|r| r.origin == MemoryOrigin::Mmap)
+        .map(|r| r.size)
+        .sum();
+    if size > MMAP_LIMIT || in_use + size > MMAP_LIMIT {
+        return errno(ENOMEM);
+    }
+    let addr = uc.get_data().mmap_next.max(MMAP_BASE);
+    let prot = Prot::from_bits_truncate(prot as u32);
+    if map_region(uc, addr, size, prot, MemoryOrigin::Mmap).is_err() {
+        return errno(ENOMEM);
+    }
+    // Addresses are not reused, so a stale pointer faults instead of aliasing a new mapping.
+    uc.get_data_mut().mmap_next = addr + size;
+    addr
+}
+
+/// munmap(addr, len): unmap the pages of anonymous mappings in the range; other memory is
+/// left alone.
+fn sys_munmap(uc: &mut Unicorn<'_, RuntimeData>) -> u64 {
+    let addr = uc.reg_read(RegisterX86::RDI).unwrap_or(0);
+    let len = uc.reg_read(RegisterX86::RSI).unwrap_or(0);
+    if len == 0 || !addr.is_multiple_of(PAGE_SIZE) {
+        return errno(EINVAL);
+    }
+    let end = addr.saturating_add(align_up(len, PAGE_SIZE));
+    let hit: Vec<(u64, u64)> = uc
+        .get_data()
+        .memory_map
+        .iter()
+        .filter(|r| r.origin == MemoryOrigin::Mmap)
+        .map(|r| (r.start.max(addr), (r.start + r.size).min(end)))
+        .filter(|(from, to)| from < to)
+        .collect();
+    for (from, to) in hit {
+        if uc.mem_unmap(from, to - from).is_err() {
+            return errno(EINVAL);
+        }
+    }
+    let map = &mut uc.get_data_mut().memory_map;
+    *map = map
+        .iter()
+        .flat_map(|r| match r.origin {
+            MemoryOrigin::Mmap => cut_region(r, addr, end),
+            _ => vec![r.clone()],
+        })
+        .collect();
+    0
+}
+
+/// Update the shadow call stack before the instruction at `addr`, of kind `kind`, runs,
+/// recording a `ret` that does not go back to its `call`.
+fn track_calls(
+    uc: &mut Unicorn<'_, RuntimeData>,
+    addr: u64,
+    size: u32,
+    kind: Control,
+    line: Option<usize>,
+    labels: &HashMap<u64, String>,
+) {
+    if let Some(top) = uc.get_data_mut().calls.last_mut().filter(|f| !f.entered) {
+        // First instruction of the callee: now we know where the call went.
+        top.entered = true;
+        top.frame.function = labels
+            .get(&addr)
+            .cloned()
+            .unwrap_or_else(|| format!("0x{:x}", addr));
+    }
+
+    match kind {
+        Control::Call => {
+            let rsp = uc.reg_read(RegisterX86::RSP).unwrap_or(0);
+            let rbp = uc.reg_read(RegisterX86::RBP).unwrap_or(0);
+            let frame = CallFrame {
+                function: String::new(),
+                call_line: line,
+                return_address: addr + u64::from(size),
+                entry_rsp: rsp.wrapping_sub(8),
+                rsp,
+                rbp,
+            };
+            uc.get_data_mut().calls.push(ShadowFrame {
+                frame,
+                entered: false,
+            });
+        }
+        Control::Ret => {
+            let rsp = uc.reg_read(RegisterX86::RSP).unwrap_or(0);
+            let mut raw = [0u8; 8];
+            let target = uc
+                .mem_read(rsp, &mut raw)
+                .ok()
+                .map(|_| u64::from_le_bytes(raw));
+            let data = uc.get_data_mut();
+            let message = match data.calls.last().map(|f| &f.frame) {
+                None => Some("`ret` without a matching `call`".to_string()),
+                Some(top) if rsp != top.entry_rsp => {
+                    let diff = rsp as i64 - top.entry_rsp as i64;
+                    Some(format!(
+                        "stack imbalance at `ret`: RSP is {} bytes {} the return address `call` pushed",
+                        diff.abs(),
+                        if diff < 0 { "below" } else { "above" }
+                    ))
+                }
+                Some(top) if target != Some(top.return_address) => Some(format!(
+                    "`ret` goes to {} but `call` pushed 0x{:x}; the return address was overwritten",
+                    target.map_or("?".to_string(), |t| format!("0x{:x}", t)),
+                    top.return_address
+                )),
+                Some(_) => None,
+            };
+            if let Some(message) = message {
+                let function = data.calls.last().map(|f| f.frame.function.clone());
+                data.call_issues.push(CallIssue {
+                    line,
+                    address: addr,
+                    function,
+                    message,
+                });
+            }
+            // Unwind to the frame `ret` really returns to, if it is on the stack at all.
+            match data
+                .calls
+                .iter()
+                .rposition(|f| Some(f.frame.return_address) == target)
+            {
+                Some(i) => data.calls.truncate(i),
+                None => {
+                    data.calls.pop();
+                }
+            }
+        }
+        Control::Other => {}
+    }
+}
+
+const REG_MAP: &[(Register, RegisterX86)] = &[
+    (Register::RAX, RegisterX86::RAX),
+    (Register::RBX, RegisterX86::RBX),
+    (Register::RCX, RegisterX86::RCX),
+    (Register::RDX, RegisterX86::RDX),
+    (Register::RSI, RegisterX86::RSI),
+    (Register::RDI, RegisterX86::RDI),
+    (Register::RSP, RegisterX86::RSP),
+    (Register::RBP, RegisterX86::RBP),
+    (Register::R8, RegisterX86::R8),
+    (Register::R9, RegisterX86::R9),
+    (Register::R10, RegisterX86::R10),
+    (Register::R11, RegisterX86::R11),
+    (Register::R12, RegisterX86::R12),
+    (Register::R13, RegisterX86::R13),
+    (Register::R14, RegisterX86::R14),
+    (Register::R15, RegisterX86::R15),
+];
+
+/// A loaded program that can be run to completion or one instruction at a time.
+/// `run_x86_64` is a session run once up to its instruction limit.
+pub struct Session {
+    emu: Unicorn<'static, RuntimeData>,
+    assembled: AssembleResult,
+    bss_labels: HashMap<String, u64>,
+    code_end: u64,
+    /// Start of the memory `state()` reports: `.bss`, or a loaded ELF's first writable segment.
+    data_base: u64,
+    stack_top: u64,
+    execution_log: Vec<String>,
+    /// The memory hook behind watchpoints and write tracking is installed (on first use, as it
+    /// slows every access).
+    memory_hook: bool,
+}
+
+impl Session {
+    pub fn new(
+        code: &str,
+        syntax: Syntax,
+        input: Vec<i64>,
+        trace: bool,
+    ) -> Result<Session, String> {
+        let mut execution_log: Vec<String> = Vec::new();
+
+        let bss = parse_bss_layout(code);
+        let bss_size = align_up(bss.total_size.max(512), PAGE_SIZE);
+
+        execution_log.push("Assembling...".to_string());
+
+        let assembled = assemble_program(code, syntax.clone())?;
+        let code_size = align_up(assembled.bytes.len().max(1) as u64, PAGE_SIZE);
+        let code_end = CODE_BASE + code_size;
+
+        let mut emu = new_emulator(input)?;
+
+        map_region(
+            &mut emu,
+            CODE_BASE,
+            code_size,
+            Prot::ALL,
+            MemoryOrigin::Code,
+        )
         .map_err(|e| format!("mem_map code failed: {e:?}"))?;
-    emu.mem_map(BSS_BASE, bss_size, Prot::ALL)
-        .map_err(|e| format!("mem_map bss failed: {e:?}"))?;
-    emu.mem_map(STACK_BASE, STACK_SIZE, Prot::ALL)
+        map_region(&mut emu, BSS_BASE, bss_size, Prot::ALL, MemoryOrigin::Bss)
+            .map_err(|e| format!("mem_map bss failed: {e:?}"))?;
+        map_region(
+            &mut emu,
+            STACK_BASE,
+            STACK_SIZE,
+            Prot::ALL,
+            MemoryOrigin::Stack,
+        )
         .map_err(|e| format!("mem_map stack failed: {e:?}"))?;
+        let data = emu.get_data_mut();
+        data.heap_start = HEAP_BASE;
+        data.brk = HEAP_BASE;
+
+        emu.mem_write(CODE_BASE, &assembled.bytes)
+            .map_err(|e| format!("mem_write code failed: {e:?}"))?;
+
+        // Initialize registers
+        emu.reg_write(RegisterX86::RSP, STACK_TOP)
+            .map_err(|e| format!("reg_write RSP failed: {e:?}"))?;
+
+        let entry = assembled.labels.get("_start").copied().unwrap_or(CODE_BASE);
+        emu.reg_write(RegisterX86::RIP, entry)
+            .map_err(|e| format!("reg_write RIP failed: {e:?}"))?;
+
+        let mut session = Session {
+            emu,
+            assembled,
+            bss_labels: bss.labels,
+            code_end,
+            data_base: BSS_BASE,
+            stack_top: STACK_TOP,
+            execution_log,
+            memory_hook: false,
+        };
+        session.install_hooks(CODE_BASE, trace)?;
+        Ok(session)
+    }
 
-    emu.mem_write(CODE_BASE, &assembled.bytes)
-        .map_err(|e| format!("mem_write code failed: {e:?}"))?;
-
-    // Initialize registers
-    emu.reg_write(RegisterX86::RSP, STACK_BASE + STACK_SIZE - 8)
-        .map_err(|e| format!("reg_write RSP failed: {e:?}"))?;
-
-    let entry = assembled.labels.get("_start").copied().unwrap_or(CODE_BASE);
-    emu.reg_write(RegisterX86::RIP, entry)
-        .map_err(|e| format!("reg_write RIP failed: {e:?}"))?;
-
-    // Syscall hook
-    emu.add_insn_sys_hook(X86Insn::SYSCALL, CODE_BASE, CODE_BASE + code_size, |uc| {
-        let rax = uc.reg_read(RegisterX86::RAX).unwrap_or(0);
-        match rax {
-            0 => {
-                // read(fd=rdi, buf=rsi, count=rdx) - we ignore fd and always read from input queue
-                let count = uc.reg_read(RegisterX86::RDX).unwrap_or(0) as usize;
-                let addr = uc.reg_read(RegisterX86::RSI).unwrap_or(0);
-                let mut read = 0usize;
-                for i in 0..count {
-                    if let Some(v) = uc.get_data_mut().input.pop_front() {
-                        let b = (v & 0xff) as u8;
-                        let _ = uc.mem_write(addr + i as u64, &[b]);
-                        read += 1;
-                    } else {
-                        break;
-                    }
-                }
-                let _ = uc.reg_write(RegisterX86::RAX, read as u64);
+    /// Load a static executable (e.g. from `nasm` and `ld`) the way Linux would: map its
+    /// `PT_LOAD` segments, push argc/argv/envp/auxv and start at its entry point.
+    ///
+    /// There is no source, so `assembled()` holds the bytes of the segment containing the entry
+    /// point with the binary's symbols as labels, and no instructions.
+    pub fn from_elf(
+        image: &[u8],
+        args: &[String],
+        env: &[String],
+        input: Vec<i64>,
+        trace: bool,
+    ) -> Result<Session, String> {
+        let exe = elf::parse_executable(image)?;
+        let mut emu = new_emulator(input)?;
+
+        let mut pages: Vec<(u64, u64)> = exe
+            .segments
+            .iter()
+            .map(|s| {
+                (
+                    s.vaddr & !(PAGE_SIZE - 1),
+                    align_up(s.vaddr + s.memsz, PAGE_SIZE),
+                )
+            })
+            .collect();
+        pages.sort();
+        let mut merged: Vec<(u64, u64)> = Vec::new();
+        for (start, end) in pages {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
             }
-            1 => {
-                // write(fd=rdi, buf=rsi, count=rdx)
-                let count = uc.reg_read(RegisterX86::RDX).unwrap_or(0) as usize;
-                let addr = uc.reg_read(RegisterX86::RSI).unwrap_or(0);
-                let mut buf = vec![0u8; count];
-                if uc.mem_read(addr, &mut buf).is_ok() {
-                    for b in buf {
-                        let val = if (b & 0x80) != 0 {
-                            (b as i8) as i64
+        }
+        let stack_base = ELF_STACK_TOP - STACK_SIZE;
+        for (start, end) in merged {
+            if start < ELF_STACK_TOP && stack_base < end {
+                return Err(format!(
+                    "Segment at 0x{:x} overlaps the stack at 0x{:x}",
+                    start, stack_base
+                ));
+            }
+            let origin = if exe
+                .segments
+                .iter()
+                .any(|s| s.is_executable() && s.vaddr < end && start < s.vaddr + s.memsz)
+            {
+                MemoryOrigin::Code
+            } else {
+                MemoryOrigin::Data
+            };
+            map_region(&mut emu, start, end - start, Prot::ALL, origin)
+                .map_err(|e| format!("mem_map segment at 0x{:x} failed: {e:?}", start))?;
+        }
+        // Like Linux, the program break starts at the page after the highest segment.
+        let image_end = exe.segments.iter().map(|s| s.vaddr + s.memsz).max();
+        let data = emu.get_data_mut();
+        data.heap_start = align_up(image_end.unwrap_or(HEAP_BASE), PAGE_SIZE);
+        data.brk = data.heap_start;
+        for seg in &exe.segments {
+            emu.mem_write(seg.vaddr, &seg.data)
+                .map_err(|e| format!("mem_write segment at 0x{:x} failed: {e:?}", seg.vaddr))?;
+        }
+
+        map_region(
+            &mut emu,
+            stack_base,
+            STACK_SIZE,
+            Prot::ALL,
+            MemoryOrigin::Stack,
+        )
+        .map_err(|e| format!("mem_map stack failed: {e:?}"))?;
+        let (rsp, frame) = initial_stack(ELF_STACK_TOP, &exe, args, env);
+        emu.mem_write(rsp, &frame)
+            .map_err(|e| format!("mem_write stack failed: {e:?}"))?;
+        emu.reg_write(RegisterX86::RSP, rsp)
+            .map_err(|e| format!("reg_write RSP failed: {e:?}"))?;
+        emu.reg_write(RegisterX86::RIP, exe.entry)
+            .map_err(|e| format!("reg_write RIP failed: {e:?}"))?;
+
+        let text = exe
+            .segments
+            .iter()
+            .find(|s| s.is_executable() && s.contains(exe.entry))
+            .ok_or("No executable segment contains the entry point")?;
+        let code_start = exe
+            .segments
+            .iter()
+            .filter(|s| s.is_executable())
+            .map(|s| s.vaddr)
+            .min()
+            .unwrap_or(text.vaddr);
+        let code_end = exe
+            .segments
+            .iter()
+            .filter(|s| s.is_executable())
+            .map(|s| s.vaddr + s.memsz)
+            .max()
+            .unwrap_or(text.vaddr + text.memsz);
+        let writable: Vec<&elf::Segment> =
+            exe.segments.iter().filter(|s| s.is_writable()).collect();
+        let bss_labels = exe
+            .symbols
+            .iter()
+            .filter(|(_, addr)| writable.iter().any(|s| s.contains(**addr)))
+            .map(|(name, addr)| (name.clone(), *addr))
+            .collect();
+
+        let mut session = Session {
+            emu,
+            assembled: AssembleResult {
+                base_addr: text.vaddr,
+                bytes: text.data.clone(),
+                labels: exe.symbols.clone(),
+                instructions: Vec::new(),
+            },
+            bss_labels,
+            code_end,
+            data_base: writable.first().map_or(0, |s| s.vaddr),
+            stack_top: ELF_STACK_TOP,
+            execution_log: vec!["Loading ELF...".to_string()],
+            memory_hook: false,
+        };
+        session.install_hooks(code_start, trace)?;
+        Ok(session)
+    }
+
+    /// Count (and optionally trace) instructions in `code_start..code_end` and serve syscalls.
+    fn install_hooks(&mut self, code_start: u64, trace: bool) -> Result<(), String> {
+        let code_end = self.code_end;
+        // Count every executed instruction (pseudo-instructions count as their expansion).
+        let by_addr: HashMap<u64, (usize, String)> = self
+            .assembled
+            .instructions
+            .iter()
+            .map(|i| (i.address, (i.line, i.text.clone())))
+            .collect();
+        // Call or ret, decoded once per address; a loaded executable fills this in as it runs.
+        let mut controls: HashMap<u64, Control> = self
+            .assembled
+            .instructions
+            .iter()
+            .map(|i| (i.address, control_kind(self.assembled.bytes_of(i))))
+            .collect();
+        // Name a called address by its label, preferring `func` over `.local`.
+        let mut labels: HashMap<u64, String> = HashMap::new();
+        for (name, addr) in &self.assembled.labels {
+            let better = labels
+                .get(addr)
+                .is_none_or(|old| (name.starts_with('.'), name) < (old.starts_with('.'), old));
+            if better {
+                labels.insert(*addr, name.clone());
+            }
+        }
+        let emu = &mut self.emu;
+        emu.add_code_hook(code_start, code_end, move |uc, addr, size| {
+            let line = by_addr.get(&addr).map(|(line, _)| *line);
+            if uc.get_data().limits.is_cancelled() {
+                uc.get_data_mut().error = Some("Cancelled".to_string());
+                let _ = uc.emu_stop();
+                return;
+            }
+            let kind = *controls
+                .entry(addr)
+                .or_insert_with(|| read_control(uc, addr, size));
+            track_calls(uc, addr, size, kind, line, &labels);
+            let data = uc.get_data_mut();
+            data.instructions += 1;
+            if trace {
+                let line = match by_addr.get(&addr) {
+                    Some((line, text)) => format!("L{:<4} {}", line, text),
+                    None => "?".to_string(),
+                };
+                let n = data.instructions;
+                data.trace.push(format!("{:>6}  0x{:x}  {}", n, addr, line));
+            }
+        })
+        .map_err(|e| format!("add_code_hook failed: {e:?}"))?;
+
+        // Syscall hook
+        emu.add_insn_sys_hook(X86Insn::SYSCALL, code_start, code_end, |uc| {
+            let rax = uc.reg_read(RegisterX86::RAX).unwrap_or(0);
+            match rax {
+                0 => {
+                    // read(fd=rdi, buf=rsi, count=rdx) - we ignore fd and always read from input queue
+                    let count = uc.reg_read(RegisterX86::RDX).unwrap_or(0) as usize;
+                    let addr = uc.reg_read(RegisterX86::RSI).unwrap_or(0);
+                    let mut read = 0usize;
+                    for i in 0..count {
+                        if let Some(v) = uc.get_data_mut().input.pop_front() {
+                            let b = (v & 0xff) as u8;
+                            note_access(uc, WatchKind::Write, addr + i as u64, &[b]);
+                            let _ = uc.mem_write(addr + i as u64, &[b]);
+                            read += 1;
                         } else {
-                            b as i64
-                        };
-                        uc.get_data_mut().output.push(val);
+                            break;
+                        }
                     }
-                    let _ = uc.reg_write(RegisterX86::RAX, count as u64);
-                } else {
-                    uc.get_data_mut().error = Some("sys_write: invalid address".to_string());
+                    let _ = uc.reg_write(RegisterX86::RAX, read as u64);
+                }
+                1 => {
+                    // write(fd=rdi, buf=rsi, count=rdx)
+                    let count = uc.reg_read(RegisterX86::RDX).unwrap_or(0) as usize;
+                    let addr = uc.reg_read(RegisterX86::RSI).unwrap_or(0);
+                    let mut buf = vec![0u8; count];
+                    if uc.mem_read(addr, &mut buf).is_ok() {
+                        note_access(uc, WatchKind::Read, addr, &buf);
+                        for b in buf {
+                            let val = if (b & 0x80) != 0 {
+                                (b as i8) as i64
+                            } else {
+                                b as i64
+                            };
+                            uc.get_data_mut().output.push(val);
+                        }
+                        let _ = uc.reg_write(RegisterX86::RAX, count as u64);
+                    } else {
+                        uc.get_data_mut().error = Some("sys_write: invalid address".to_string());
+                        let _ = uc.emu_stop();
+                    }
+                }
+                9 => {
+                    let addr = sys_mmap(uc);
+                    let _ = uc.reg_write(RegisterX86::RAX, addr);
+                }
+                11 => {
+                    let result = sys_munmap(uc);
+                    let _ = uc.reg_write(RegisterX86::RAX, result);
+                }
+                12 => {
+                    let brk = sys_brk(uc);
+                    let _ = uc.reg_write(RegisterX86::RAX, brk);
+                }
+                60 => {
+                    uc.get_data_mut().exited = true;
                     let _ = uc.emu_stop();
                 }
-            }
-            60 => {
-                uc.get_data_mut().exited = true;
-                let _ = uc.emu_stop();
-            }
-            1000 => {
-                // pseudo-IN: pop next i64 from input queue into RAX
-                if let Some(v) = uc.get_data_mut().input.pop_front() {
-                    let _ = uc.reg_write(RegisterX86::RAX, v as u64);
-                } else {
-                    uc.get_data_mut().error = Some("Input buffer empty".to_string());
+                1000 => {
+                    // pseudo-IN: pop next i64 from input queue into RAX
+                    if let Some(v) = uc.get_data_mut().input.pop_front() {
+                        let _ = uc.reg_write(RegisterX86::RAX, v as u64);
+                    } else {
+                        uc.get_data_mut().error = Some("Input buffer empty".to_string());
+                        let _ = uc.emu_stop();
+                    }
+                }
+                other => {
+                    uc.get_data_mut().error = Some(format!("Unknown syscall: {}", other));
                     let _ = uc.emu_stop();
                 }
             }
-            other => {
-                uc.get_data_mut().error = Some(format!("Unknown syscall: {}", other));
-                let _ = uc.emu_stop();
+        })
+        .map_err(|e| format!("add_insn_sys_hook failed: {e:?}"))?;
+
+        Ok(())
+    }
+
+    /// Execute up to `max_instructions` instructions (0 means no limit), stopping early on exit
+    /// or a fault.
+    pub fn run(&mut self, max_instructions: usize) {
+        if self.is_finished() {
+            return;
+        }
+        self.emu.get_data_mut().writes.clear();
+        let limits = self.emu.get_data().limits.clone();
+        // Unicorn's timeout is in microseconds; 0 means none.
+        let timeout_us = match limits.deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                (left.as_micros() as u64).max(1)
             }
+            None => 0,
+        };
+        if limits.is_cancelled() {
+            self.emu.get_data_mut().error = Some("Cancelled".to_string());
+            return;
+        }
+        let start = self.rip();
+        if let Err(e) = self
+            .emu
+            .emu_start(start, self.code_end, timeout_us, max_instructions)
+        {
+            self.emu.get_data_mut().error = Some(format!("Emulation error: {e:?}"));
+        }
+        if !self.is_finished() && limits.is_past_deadline() {
+            self.emu.get_data_mut().error = Some("Timed out".to_string());
+            limits.timed_out.store(true, Ordering::Relaxed);
         }
-    })
-    .map_err(|e| format!("add_insn_sys_hook failed: {e:?}"))?;
+    }
 
-    // Run
-    let start = entry;
-    let until = CODE_BASE + code_size;
-    let run_res = emu.emu_start(start, until, 0, max_instructions);
+    pub fn step(&mut self) {
+        self.run(1);
+    }
 
-    if let Err(e) = run_res {
-        emu.get_data_mut().error = Some(format!("Emulation error: {e:?}"));
-    } else if !emu.get_data().exited && emu.get_data().error.is_none() {
-        // If we stopped without exit and without explicit error, we likely hit instruction limit.
-        emu.get_data_mut().error = Some("Instruction limit reached".to_string());
-    }
-
-    // Build VmState
-    let mut registers = HashMap::new();
-    let reg_map: &[(Register, RegisterX86)] = &[
-        (Register::RAX, RegisterX86::RAX),
-        (Register::RBX, RegisterX86::RBX),
-        (Register::RCX, RegisterX86::RCX),
-        (Register::RDX, RegisterX86::RDX),
-        (Register::RSI, RegisterX86::RSI),
-        (Register::RDI, RegisterX86::RDI),
-        (Register::RSP, RegisterX86::RSP),
-        (Register::RBP, RegisterX86::RBP),
-        (Register::R8, RegisterX86::R8),
-        (Register::R9, RegisterX86::R9),
-        (Register::R10, RegisterX86::R10),
-        (Register::R11, RegisterX86::R11),
-        (Register::R12, RegisterX86::R12),
-        (Register::R13, RegisterX86::R13),
-        (Register::R14, RegisterX86::R14),
-        (Register::R15, RegisterX86::R15),
-    ];
+    fn install_memory_hook(&mut self) -> Result<(), String> {
+        if self.memory_hook {
+            return Ok(());
+        }
+        // begin > end hooks every address.
+        self.emu
+            .add_mem_hook(
+                HookType::MEM_READ | HookType::MEM_WRITE,
+                1,
+                0,
+                |uc, access, addr, size, value| {
+                    match access {
+                        MemType::WRITE => {
+                            let bytes = value.to_le_bytes();
+                            note_access(uc, WatchKind::Write, addr, &bytes[..size.min(8)]);
+                        }
+                        _ => {
+                            let mut bytes = vec![0u8; size];
+                            let _ = uc.mem_read(addr, &mut bytes);
+                            note_access(uc, WatchKind::Read, addr, &bytes);
+                        }
+                    }
+                    true
+                },
+            )
+            .map_err(|e| format!("add_mem_hook failed: {e:?}"))?;
+        self.memory_hook = true;
+        Ok(())
+    }
 
-    for (k, r) in reg_map {
-        let v = emu.reg_read(*r).unwrap_or(0) as i64;
-        registers.insert(*k, v);
+    /// Record accesses to these ranges from now on, replacing any earlier watchpoints.
+    pub fn set_watchpoints(&mut self, watchpoints: Vec<Watchpoint>) -> Result<(), String> {
+        if !watchpoints.is_empty() {
+            self.install_memory_hook()?;
+        }
+        self.emu.get_data_mut().watchpoints = watchpoints;
+        Ok(())
     }
 
-    let rflags = emu.reg_read(RegisterX86::EFLAGS).unwrap_or(0);
-    let zf = (rflags & (1 << 6)) != 0;
-    let sf = (rflags & (1 << 7)) != 0;
+    /// Fill `VmState::memory_changes` with what each `run` or `step` changes from now on.
+    pub fn track_memory_changes(&mut self) -> Result<(), String> {
+        self.install_memory_hook()?;
+        self.emu.get_data_mut().track_writes = true;
+        Ok(())
+    }
 
-    let rip = emu.reg_read(RegisterX86::RIP).unwrap_or(entry);
-    let pc = if rip >= CODE_BASE {
-        (rip - CODE_BASE) as usize
-    } else {
-        0
-    };
+    /// Bytes the latest `run` or `step` changed, once `track_memory_changes` is on.
+    pub fn memory_changes(&self) -> Vec<MemoryChange> {
+        compact_changes(&self.emu.get_data().writes)
+    }
 
-    // Return first 512 bytes from BSS region for UI
-    let mut mem512 = vec![0u8; 512];
-    let _ = emu.mem_read(BSS_BASE, &mut mem512);
-
-    let state = VmState {
-        registers,
-        zf,
-        sf,
-        pc,
-        output: emu.get_data().output.clone(),
-        stack: Vec::new(),
-        memory: mem512,
-        input_remaining: emu.get_data().input.len(),
-        finished: emu.get_data().exited || emu.get_data().error.is_some(),
-        exited: emu.get_data().exited,
-        error: emu.get_data().error.clone(),
-    };
+    /// Watched accesses since the last call, oldest first.
+    pub fn take_watch_hits(&mut self) -> Vec<WatchHit> {
+        std::mem::take(&mut self.emu.get_data_mut().watch_hits)
+    }
+
+    /// The program called exit or faulted.
+    pub fn is_finished(&self) -> bool {
+        self.emu.get_data().exited || self.emu.get_data().error.is_some()
+    }
+
+    /// RIP has left the assembled code (e.g. a missing `exit`).
+    pub fn is_past_end(&self) -> bool {
+        let base = self.assembled.base_addr;
+        let end = base + self.assembled.bytes.len() as u64;
+        !(base..end).contains(&self.rip())
+    }
+
+    pub fn rip(&self) -> u64 {
+        self.emu.reg_read(RegisterX86::RIP).unwrap_or(CODE_BASE)
+    }
+
+    pub fn register(&self, reg: Register) -> u64 {
+        REG_MAP
+            .iter()
+            .find(|(k, _)| *k == reg)
+            .and_then(|(_, r)| self.emu.reg_read(*r).ok())
+            .unwrap_or(0)
+    }
+
+    /// Every general-purpose register, RAX to R15.
+    pub fn registers(&self) -> Vec<(Register, u64)> {
+        REG_MAP
+            .iter()
+            .map(|(k, r)| (*k, self.emu.reg_read(*r).unwrap_or(0)))
+            .collect()
+    }
+
+    /// Bytes written so far by `write`, sign-extended.
+    pub fn output(&self) -> &[i64] {
+        &self.emu.get_data().output
+    }
+
+    /// Highest stack address in use when the program started.
+    pub fn stack_top(&self) -> u64 {
+        self.stack_top
+    }
+
+    pub fn rflags(&self) -> u64 {
+        self.emu.reg_read(RegisterX86::EFLAGS).unwrap_or(0)
+    }
+
+    pub fn read_memory(&self, addr: u64, len: usize) -> Result<Vec<u8>, String> {
+        let mut buf = vec![0u8; len];
+        self.emu
+            .mem_read(addr, &mut buf)
+            .map_err(|e| format!("Cannot read {} byte(s) at 0x{:x}: {e:?}", len, addr))?;
+        Ok(buf)
+    }
+
+    /// Values not yet consumed by `read` or the `in` pseudo-instruction.
+    pub fn input(&self) -> Vec<i64> {
+        self.emu.get_data().input.iter().copied().collect()
+    }
 
-    Ok(RunResult {
-        state,
-        execution_log,
-    })
+    pub fn push_input(&mut self, values: &[i64]) {
+        self.emu.get_data_mut().input.extend(values);
+    }
+
+    pub fn assembled(&self) -> &AssembleResult {
+        &self.assembled
+    }
+
+    /// `.bss` labels and their absolute addresses.
+    pub fn bss_labels(&self) -> &HashMap<String, u64> {
+        &self.bss_labels
+    }
+
+    pub fn instruction_at(&self, addr: u64) -> Option<&AssembledInstruction> {
+        self.assembled
+            .instructions
+            .iter()
+            .find(|i| i.address == addr)
+    }
+
+    pub fn state(&self) -> VmState {
+        let mut registers = HashMap::new();
+        for (k, r) in REG_MAP {
+            let v = self.emu.reg_read(*r).unwrap_or(0) as i64;
+            registers.insert(*k, v);
+        }
+
+        let rflags = self.rflags();
+        let zf = (rflags & (1 << 6)) != 0;
+        let sf = (rflags & (1 << 7)) != 0;
+
+        let rip = self.rip();
+        let pc = rip.saturating_sub(self.assembled.base_addr) as usize;
+
+        // Return first 512 bytes from BSS region for UI
+        let mut mem512 = vec![0u8; 512];
+        let _ = self.emu.mem_read(self.data_base, &mut mem512);
+
+        let call_stack = self.call_stack();
+        let data = self.emu.get_data();
+        VmState {
+            registers,
+            zf,
+            sf,
+            pc,
+            output: data.output.clone(),
+            stack: Vec::new(),
+            memory: mem512,
+            input_remaining: data.input.len(),
+            finished: data.exited || data.error.is_some(),
+            exited: data.exited,
+            error: data.error.clone(),
+            instructions_executed: data.instructions,
+            call_stack,
+            call_issues: data.call_issues.clone(),
+            memory_map: data.memory_map.clone(),
+            memory_changes: compact_changes(&data.writes),
+        }
+    }
+
+    /// Active calls, outermost first.
+    pub fn call_stack(&self) -> Vec<CallFrame> {
+        let calls = &self.emu.get_data().calls;
+        let mut stack: Vec<CallFrame> = calls.iter().map(|f| f.frame.clone()).collect();
+        // Each frame stores RSP/RBP at its `call`, which belong to the frame below it.
+        for i in 0..stack.len() {
+            let (rsp, rbp) = match stack.get(i + 1) {
+                Some(inner) => (inner.rsp, inner.rbp),
+                None => (self.register(Register::RSP), self.register(Register::RBP)),
+            };
+            stack[i].rsp = rsp;
+            stack[i].rbp = rbp;
+        }
+        stack
+    }
+
+    pub fn finish(mut self) -> RunResult {
+        let state = self.state();
+        let rflags = self.rflags();
+        self.execution_log
+            .append(&mut self.emu.get_data_mut().trace);
+        RunResult {
+            state,
+            execution_log: self.execution_log,
+            rflags,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -716,4 +1761,275 @@ _start:
         assert!(res.state.exited, "VmState: {:?}", res.state);
         assert!(res.state.error.is_none(), "VmState: {:?}", res.state);
     }
+
+    #[test]
+    fn counts_executed_instructions() {
+        let code = r#"
+section .text
+    global _start
+
+_start:
+    mov rcx, 3
+.loop:
+    dec rcx
+    jnz .loop
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+"#;
+        let res = run_x86_64(code, Syntax::Intel, vec![], 10_000).unwrap();
+        // 1 + 3 * 2 + 3
+        assert_eq!(res.state.instructions_executed, 10);
+    }
+
+    #[test]
+    fn deadline_and_cancel_stop_an_endless_loop() {
+        let code = "_start:\nspin:\n    jmp spin\n";
+        let limits = RunLimits {
+            deadline: Some(Instant::now() + std::time::Duration::from_millis(50)),
+            ..RunLimits::default()
+        };
+        let mut session = with_limits(limits.clone(), || {
+            Session::new(code, Syntax::Intel, vec![], false)
+        })
+        .unwrap();
+        session.run(0);
+        assert_eq!(session.state().error.as_deref(), Some("Timed out"));
+        assert!(limits.has_timed_out());
+
+        let limits = RunLimits {
+            cancel: Arc::new(AtomicBool::new(true)),
+            ..RunLimits::default()
+        };
+        let mut session = with_limits(limits.clone(), || {
+            Session::new(code, Syntax::Intel, vec![], false)
+        })
+        .unwrap();
+        session.run(0);
+        assert_eq!(session.state().error.as_deref(), Some("Cancelled"));
+        assert!(!limits.has_timed_out());
+    }
+
+    #[test]
+    fn initial_stack_follows_the_linux_layout() {
+        let exe = elf::Executable {
+            entry: 0x401000,
+            segments: Vec::new(),
+            phdr_addr: Some(0x400040),
+            phnum: 2,
+            symbols: HashMap::new(),
+        };
+        let args = ["a.out".to_string(), "x".to_string()];
+        let (rsp, frame) = initial_stack(0x8000, &exe, &args, &["HOME=/".to_string()]);
+        assert_eq!(rsp % 16, 0);
+        assert!(rsp + frame.len() as u64 <= 0x8000);
+        let word = |i: usize| u64::from_le_bytes(frame[8 * i..8 * i + 8].try_into().unwrap());
+        let string = |addr: u64| {
+            let rest = &frame[(addr - rsp) as usize..];
+            String::from_utf8(rest[..rest.iter().position(|b| *b == 0).unwrap()].to_vec()).unwrap()
+        };
+        assert_eq!(word(0), 2);
+        assert_eq!(
+            (string(word(1)), string(word(2)), word(3)),
+            ("a.out".into(), "x".into(), 0)
+        );
+        assert_eq!((string(word(4)), word(5)), ("HOME=/".into(), 0));
+        let auxv: Vec<(u64, u64)> = (6..)
+            .step_by(2)
+            .map(|i| (word(i), word(i + 1)))
+            .take_while(|p| p.0 != AT_NULL)
+            .collect();
+        assert!(auxv.contains(&(AT_ENTRY, 0x401000)));
+        assert!(auxv.contains(&(AT_PHDR, 0x400040)));
+        assert!(auxv.contains(&(AT_PHNUM, 2)));
+    }
+
+    #[test]
+    fn classifies_call_and_ret_encodings() {
+        assert_eq!(control_kind(&[0xe8, 0, 0, 0, 0]), Control::Call);
+        assert_eq!(control_kind(&[0xff, 0xd0]), Control::Call); // call rax
+        assert_eq!(control_kind(&[0x41, 0xff, 0xd3]), Control::Call); // call r11
+        assert_eq!(control_kind(&[0xff, 0xe0]), Control::Other); // jmp rax
+        assert_eq!(control_kind(&[0xc3]), Control::Ret);
+        assert_eq!(control_kind(&[0xf3, 0xc3]), Control::Ret);
+        assert_eq!(control_kind(&[0x48, 0x89, 0xc3]), Control::Other); // mov rbx, rax
+    }
+
+    #[test]
+    fn tracks_calls_and_flags_unbalanced_ret() {
+        let code = "_start:
+    call outer
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+outer:
+    call inner
+    ret
+inner:
+    push rbx
+    ret
+";
+        let mut session = Session::new(code, Syntax::Intel, vec![], false).unwrap();
+        session.run(3);
+        let stack = session.state().call_stack;
+        let names: Vec<(&str, Option<usize>)> = stack
+            .iter()
+            .map(|f| (f.function.as_str(), f.call_line))
+            .collect();
+        assert_eq!(names, vec![("outer", Some(2)), ("inner", Some(7))]);
+        assert_eq!(stack[1].entry_rsp, STACK_TOP - 16);
+        assert_eq!(stack[1].rsp, STACK_TOP - 24);
+        assert_eq!(stack[0].rsp, stack[1].entry_rsp + 8);
+
+        session.step();
+        let issues = session.state().call_issues;
+        assert_eq!(issues.len(), 1, "{:?}", issues);
+        assert_eq!(issues[0].line, Some(11));
+        assert_eq!(issues[0].function.as_deref(), Some("inner"));
+        assert!(issues[0].message.contains("8 bytes below"), "{:?}", issues);
+    }
+
+    #[test]
+    fn munmap_splits_a_mapping() {
+        let region = MemoryRegion {
+            start: 0x1000,
+            size: 0x4000,
+            perms: "rw-".to_string(),
+            origin: MemoryOrigin::Mmap,
+        };
+        let left = cut_region(&region, 0x2000, 0x3000);
+        let spans: Vec<(u64, u64)> = left.iter().map(|r| (r.start, r.size)).collect();
+        assert_eq!(spans, vec![(0x1000, 0x1000), (0x3000, 0x2000)]);
+        assert!(cut_region(&region, 0x0, 0x8000).is_empty());
+        assert_eq!(cut_region(&region, 0x5000, 0x6000), vec![region]);
+    }
+
+    #[test]
+    fn compacts_written_bytes_into_runs() {
+        let writes = vec![
+            (0x10, vec![0, 0], vec![1, 2]),
+            (0x12, vec![0], vec![3]),
+            (0x11, vec![2], vec![0]),
+            (0x20, vec![5], vec![5]),
+            (0x30, vec![7], vec![8]),
+        ];
+        let changes: Vec<(u64, Vec<u8>, Vec<u8>)> = compact_changes(&writes)
+            .into_iter()
+            .map(|c| (c.address, c.old, c.new))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                (0x10, vec![0], vec![1]),
+                (0x12, vec![0], vec![3]),
+                (0x30, vec![7], vec![8]),
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_report_the_bytes_they_change() {
+        let code = "section .bss
+    buf resb 8
+
+section .text
+_start:
+    mov qword [buf], 0x1234
+    mov byte [buf + 1], 0x12
+    mov word [buf + 4], 0x0101
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+";
+        let mut session = Session::new(code, Syntax::Intel, vec![], false).unwrap();
+        session.track_memory_changes().unwrap();
+        let buf = session.bss_labels()["buf"];
+        session.step();
+        let changes = session.state().memory_changes;
+        assert_eq!(
+            changes,
+            vec![MemoryChange {
+                address: buf,
+                old: vec![0, 0],
+                new: vec![0x34, 0x12],
+            }]
+        );
+        // Writing a byte's own value is no change.
+        session.step();
+        assert!(session.state().memory_changes.is_empty());
+        session.step();
+        assert_eq!(session.memory_changes()[0].address, buf + 4);
+        session.step();
+        assert!(session.state().memory_changes.is_empty());
+    }
+
+    #[test]
+    fn brk_and_mmap_grow_the_memory_map() {
+        let code = "_start:
+    mov rax, 12
+    xor rdi, rdi
+    syscall
+    lea rdi, [rax + 0x1800]
+    mov rax, 12
+    syscall
+    mov byte ptr [rax - 1], 7
+    mov rax, 9
+    xor rdi, rdi
+    mov rsi, 100
+    mov rdx, 3
+    mov r10, 0x22
+    mov r8, -1
+    xor r9, r9
+    syscall
+    mov qword ptr [rax], 42
+    mov rax, 60
+    xor rdi, rdi
+    syscall
+";
+        let state = run_x86_64(code, Syntax::Intel, vec![], 1_000)
+            .unwrap()
+            .state;
+        assert!(state.exited, "{:?}", state.error);
+        let map: Vec<(u64, u64, &str, MemoryOrigin)> = state
+            .memory_map
+            .iter()
+            .map(|r| (r.start, r.size, r.perms.as_str(), r.origin))
+            .collect();
+        assert_eq!(
+            map,
+            vec![
+                (CODE_BASE, PAGE_SIZE, "rwx", MemoryOrigin::Code),
+                (BSS_BASE, PAGE_SIZE, "rwx", MemoryOrigin::Bss),
+                (STACK_BASE, STACK_SIZE, "rwx", MemoryOrigin::Stack),
+                (HEAP_BASE, 2 * PAGE_SIZE, "rw-", MemoryOrigin::Heap),
+                (MMAP_BASE, PAGE_SIZE, "rw-", MemoryOrigin::Mmap),
+            ]
+        );
+    }
+
+    #[test]
+    fn preprocessed_lines_keep_their_source_line() {
+        let code =
+            "section .bss\n    buf resb 4\nsection .text\n_start:\n    in rbx\n    loop _start\n";
+        let lines = preprocess_text(code, &Syntax::Intel, &parse_bss_layout(code).labels);
+        let numbers: Vec<usize> = lines.iter().map(|(n, _)| *n).collect();
+        // `_start:` on 4, five lines for `in rbx` on 5, two for `loop` on 6
+        assert_eq!(numbers, vec![4, 5, 5, 5, 5, 5, 6, 6]);
+        assert_eq!(lines[6].1, "dec rcx");
+    }
+
+    #[test]
+    fn trace_points_at_source_lines() {
+        let code = "_start:\n    in rax\n    mov rax, 60\n    syscall\n";
+        let config = RunConfig {
+            max_instructions: 100,
+            trace: true,
+        };
+        let res = run_x86_64_with(code, Syntax::Intel, vec![7], &config).unwrap();
+        let trace: Vec<&String> = res.execution_log.iter().skip(1).collect();
+        assert_eq!(trace.len(), 4);
+        assert!(trace[0].contains("L2"), "{:?}", trace);
+        assert!(trace[1].contains("L2"), "{:?}", trace);
+        assert!(trace[2].contains("L3"), "{:?}", trace);
+    }
 }
@@ -0,0 +1,244 @@
+//! Every stage ships a reference solution in both syntaxes, an alternative approach in Intel
+//! syntax and a starter `ini.asm`. The references must pass everything the app checks; the
+//! starters must not.
+
+use opcode_logic_lib::grader;
+use opcode_logic_lib::levels::{self, Level};
+use opcode_logic_lib::vm::Syntax;
+use opcode_logic_lib::x86_asm;
+use opcode_logic_lib::{translate, x86_runtime};
+
+const MAX_INSTRUCTIONS: usize = 50_000;
+const PROPERTY_CASES: usize = 50;
+
+fn stage_levels() -> Vec<(Level, &'static str)> {
+    levels::get_levels()
+        .into_iter()
+        .filter_map(|level| levels::level_dir_for_id(&level.id).map(|dir| (level, dir)))
+        .collect()
+}
+
+/// Everything that keeps `code` from being accepted for `level`, in the order the app checks it.
+fn problems(code: &str, syntax: &Syntax, level: &Level) -> Result<Vec<String>, String> {
+    let mut problems = grader::check_static_constraints(code, syntax, &level.constraints)?;
+    for (input, expected, hidden) in level.graded_cases() {
+        let case = grader::grade_case(code, syntax, input, expected, MAX_INSTRUCTIONS)?;
+        let tag = if hidden { "hidden case" } else { "case" };
+        if !case.passed {
+            problems.push(format!(
+                "{} {:?}: expected {:?}, got {:?}{}",
+                tag,
+                input,
+                expected,
+                case.got,
+                case.state
+                    .error
+                    .as_ref()
+                    .map(|e| format!(" ({})", e))
+                    .unwrap_or_default()
+            ));
+        }
+        if let Some(violation) = grader::instruction_limit_violation(&level.constraints, &case) {
+            problems.push(format!("{} {:?}: {}", tag, input, violation));
+        }
+    }
+    Ok(problems)
+}
+
+#[test]
+fn every_stage_ships_solutions_and_starter_code() {
+    let stages = stage_levels();
+    assert!(!stages.is_empty());
+    for (level, dir) in &stages {
+        for file in [
+            "collect.asm",
+            "collect_Att.asm",
+            "collect_alt.asm",
+            "ini.asm",
+        ] {
+            assert!(
+                levels::read_stage_file(&format!("{}/{}", dir, file)).is_ok(),
+                "{}: missing {}",
+                level.id,
+                file
+            );
+        }
+    }
+}
+
+#[test]
+fn shipped_files_are_detected_as_their_syntax() {
+    let mut wrong = Vec::new();
+    for (level, dir) in stage_levels() {
+        for (file, want) in [
+            ("collect.asm", Syntax::Intel),
+            ("collect_Att.asm", Syntax::Att),
+            ("collect_alt.asm", Syntax::Intel),
+            ("ini.asm", Syntax::Intel),
+            ("ini_Att.asm", Syntax::Att),
+        ] {
+            let Ok(code) = levels::read_stage_file(&format!("{}/{}", dir, file)) else {
+                continue;
+            };
+            let found = x86_asm::analyze_syntax(&code);
+            // Starter code may be too short to tell; it just must not point the wrong way.
+            let right = std::mem::discriminant(&found.syntax) == std::mem::discriminant(&want);
+            if !right || (found.ambiguous && file.starts_with("collect")) {
+                wrong.push(format!("{}/{}: {}", level.id, file, found.describe()));
+            }
+        }
+    }
+    assert!(wrong.is_empty(), "\n{}", wrong.join("\n"));
+}
+
+#[test]
+fn translated_stage_files_assemble_to_identical_bytes() {
+    let mut failures = Vec::new();
+    let (mut compared, mut ran) = (0, 0);
+    for (level, dir) in stage_levels() {
+        for (file, from, to) in [
+            ("collect.asm", Syntax::Intel, Syntax::Att),
+            ("ini.asm", Syntax::Intel, Syntax::Att),
+            ("collect_Att.asm", Syntax::Att, Syntax::Intel),
+            ("ini_Att.asm", Syntax::Att, Syntax::Intel),
+        ] {
+            let Ok(code) = levels::read_stage_file(&format!("{}/{}", dir, file)) else {
+                continue;
+            };
+            let name = format!("{}/{}", level.id, file);
+            let translated = match translate::translate(&code, &from, &to) {
+                Ok(t) => t,
+                Err(e) => {
+                    failures.push(format!("{}: {}", name, e));
+                    continue;
+                }
+            };
+            let original = x86_runtime::assemble_program(&code, from.clone())
+                .unwrap_or_else(|e| panic!("{}: {}", name, e));
+            let ours = match x86_runtime::assemble_program(&translated, to.clone()) {
+                Ok(a) => a,
+                Err(e) => {
+                    failures.push(format!("{} translated: {}", name, e));
+                    continue;
+                }
+            };
+            // The Intel loader reaches `[label ...]` operands through r15, which an AT&T
+            // displacement does not need; those programs are compared by running them.
+            let via_scratch = original.instructions.iter().any(|i| i.text.contains("r15"))
+                && !code.contains("r15");
+            if via_scratch {
+                ran += 1;
+                failures.extend(
+                    behaviour_differences(&code, &from, &translated, &to, &level)
+                        .into_iter()
+                        .map(|d| format!("{}: {}\n{}", name, d, translated)),
+                );
+                continue;
+            }
+            compared += 1;
+            if original.bytes != ours.bytes {
+                failures.push(format!("{}: bytes differ\n{}", name, translated));
+            }
+        }
+    }
+    assert!(compared > 0 && ran > 0);
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+/// Inputs of `level` on which `a` and `b` print something different or stop differently.
+fn behaviour_differences(
+    a: &str,
+    a_syntax: &Syntax,
+    b: &str,
+    b_syntax: &Syntax,
+    level: &Level,
+) -> Vec<String> {
+    let mut differences = Vec::new();
+    for (input, _, _) in level.graded_cases() {
+        let run = |code, syntax: &Syntax| {
+            x86_runtime::run_x86_64(code, syntax.clone(), input.clone(), MAX_INSTRUCTIONS)
+                .map(|r| (r.state.output, r.state.exited, r.state.error))
+        };
+        let (before, after) = (run(a, a_syntax), run(b, b_syntax));
+        if before != after {
+            differences.push(format!(
+                "input {:?}: {:?} before, {:?} translated",
+                input, before, after
+            ));
+        }
+    }
+    differences
+}
+
+#[test]
+fn reference_solutions_pass_in_both_syntaxes() {
+    let mut failures = Vec::new();
+    for (level, _) in stage_levels() {
+        for (syntax, code) in levels::reference_solutions(&level.id).unwrap() {
+            match problems(&code, &syntax, &level) {
+                Ok(found) => failures.extend(
+                    found
+                        .into_iter()
+                        .map(|p| format!("{} ({:?}): {}", level.id, syntax, p)),
+                ),
+                Err(e) => failures.push(format!("{} ({:?}): {}", level.id, syntax, e)),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn reference_solutions_pass_generated_cases() {
+    let mut failures = Vec::new();
+    for (level, _) in stage_levels() {
+        for (syntax, code) in levels::reference_solutions(&level.id).unwrap() {
+            let report = grader::check_property(
+                &code,
+                &syntax,
+                &level,
+                PROPERTY_CASES,
+                grader::DEFAULT_SEED,
+                MAX_INSTRUCTIONS,
+            );
+            match report {
+                Ok(report) => {
+                    if let Some(failure) = report.failure {
+                        failures.push(format!(
+                            "{} ({:?}): input {:?} expected {:?}, got {:?}",
+                            level.id,
+                            syntax,
+                            failure.minimal.input,
+                            failure.minimal.expected,
+                            failure.minimal.got
+                        ));
+                    }
+                }
+                Err(e) => failures.push(format!("{} ({:?}): {}", level.id, syntax, e)),
+            }
+        }
+    }
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn starter_code_is_not_a_solution() {
+    let mut accepted = Vec::new();
+    for (level, dir) in stage_levels() {
+        let starters = [("ini.asm", Syntax::Intel), ("ini_Att.asm", Syntax::Att)];
+        for (file, syntax) in starters {
+            let Ok(code) = levels::read_stage_file(&format!("{}/{}", dir, file)) else {
+                continue;
+            };
+            // Not assembling counts as failing too.
+            if matches!(problems(&code, &syntax, &level), Ok(p) if p.is_empty()) {
+                accepted.push(format!("{}/{}", dir, file));
+            }
+        }
+    }
+    assert!(
+        accepted.is_empty(),
+        "starter code passes the level:\n{}",
+        accepted.join("\n")
+    );
+}
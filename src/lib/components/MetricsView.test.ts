@@ -0,0 +1,44 @@
+import { describe, it, expect } from 'vitest';
+import { render, screen } from '@testing-library/svelte/svelte5';
+import MetricsView from './MetricsView.svelte';
+import { mergeBest } from '$lib/metrics';
+
+const mine = { instructions_executed: 412, code_bytes: 60, source_lines: 20, registers_used: 5 };
+const ref = { instructions_executed: 388, code_bytes: 64, source_lines: 22, registers_used: 4 };
+
+describe('MetricsView', () => {
+  it('renders all metrics', () => {
+    render(MetricsView, { latest: null, best: null, reference: [] });
+
+    expect(screen.getByText('Instructions')).toBeInTheDocument();
+    expect(screen.getByText('Bytes')).toBeInTheDocument();
+    expect(screen.getByText('Lines')).toBeInTheDocument();
+    expect(screen.getByText('Registers')).toBeInTheDocument();
+  });
+
+  it('shows the reference range', () => {
+    render(MetricsView, {
+      latest: mine,
+      best: mine,
+      reference: [ref, { ...ref, instructions_executed: 400 }],
+    });
+
+    expect(screen.getByText('388–400')).toBeInTheDocument();
+    expect(screen.getByText('64')).toBeInTheDocument();
+  });
+});
+
+describe('mergeBest', () => {
+  it('keeps the minimum of each metric', () => {
+    expect(mergeBest(mine, ref)).toEqual({
+      instructions_executed: 388,
+      code_bytes: 60,
+      source_lines: 20,
+      registers_used: 4,
+    });
+  });
+
+  it('takes the first result as is', () => {
+    expect(mergeBest(undefined, mine)).toEqual(mine);
+  });
+});
@@ -0,0 +1,59 @@
+import { writable } from "svelte/store";
+
+const STORAGE_KEY = "opcode_level_metrics";
+
+/** Mirrors `grader::SolutionMetrics` on the Rust side. Lower is better everywhere. */
+export interface SolutionMetrics {
+  instructions_executed: number;
+  code_bytes: number;
+  source_lines: number;
+  registers_used: number;
+}
+
+export const METRIC_KEYS: (keyof SolutionMetrics)[] = [
+  "instructions_executed",
+  "code_bytes",
+  "source_lines",
+  "registers_used",
+];
+
+/** Best value seen for each metric, per level id. */
+export const bestMetricsStore = writable<Record<string, SolutionMetrics>>({});
+
+export function loadMetricsFromStorage() {
+  if (typeof localStorage === "undefined") return;
+  const stored = localStorage.getItem(STORAGE_KEY);
+  if (!stored) {
+    bestMetricsStore.set({});
+    return;
+  }
+  try {
+    const obj = JSON.parse(stored);
+    bestMetricsStore.set(obj && typeof obj === "object" ? obj : {});
+  } catch {
+    bestMetricsStore.set({});
+  }
+}
+
+/** Each metric is kept independently, so the best values may come from different solutions. */
+export function mergeBest(
+  prev: SolutionMetrics | undefined,
+  next: SolutionMetrics,
+): SolutionMetrics {
+  if (!prev) return { ...next };
+  const merged = { ...prev };
+  for (const key of METRIC_KEYS) {
+    merged[key] = Math.min(prev[key], next[key]);
+  }
+  return merged;
+}
+
+export function recordMetrics(id: string, metrics: SolutionMetrics) {
+  bestMetricsStore.update((all) => {
+    const next = { ...all, [id]: mergeBest(all[id], metrics) };
+    if (typeof localStorage !== "undefined") {
+      localStorage.setItem(STORAGE_KEY, JSON.stringify(next));
+    }
+    return next;
+  });
+}
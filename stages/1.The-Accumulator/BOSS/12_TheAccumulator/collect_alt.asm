@@ -0,0 +1,63 @@
+section .bss
+    buf resb 32
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: The Accumulator (BOSS STAGE)
+    ; - A-Z -> a-z
+    ; - 0-9 -> increment (wrap 9->0)
+    ; - others unchanged
+    ; Alternative: one unsigned compare per range, and the bytes are rewritten in place.
+
+    ; read(0, buf, 32)
+    mov rax, 0
+    mov rdi, 0
+    mov rsi, buf
+    mov rdx, 32
+    syscall
+
+    mov rdx, rax        ; bytes read, also the write length
+    xor r8, r8          ; index = 0
+
+.loop:
+    cmp r8, rdx
+    jge .done
+
+    mov al, byte [buf + r8]
+
+    ; uppercase? 'A'..'Z' is 0..25 after subtracting 'A'; anything else is above
+    mov bl, al
+    sub bl, 'A'
+    cmp bl, 25
+    ja .check_digit
+    or al, 0x20
+    jmp .store
+
+.check_digit:
+    mov bl, al
+    sub bl, '0'
+    cmp bl, 9
+    ja .store
+    inc al
+    cmp bl, 9           ; was it '9'?
+    jne .store
+    mov al, '0'
+
+.store:
+    mov byte [buf + r8], al
+    inc r8
+    jmp .loop
+
+.done:
+    ; write(1, buf, rdx)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
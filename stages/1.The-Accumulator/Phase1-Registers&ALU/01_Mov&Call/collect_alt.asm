@@ -0,0 +1,28 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Mov & Call
+    ; read from stdin (syscall 0), write to stdout (syscall 1)
+    ; Alternative: 32-bit moves zero the upper half too and encode shorter.
+
+    ; read(0, buf, 16)
+    xor eax, eax        ; syscall: read
+    xor edi, edi        ; stdin
+    mov esi, buf        ; buffer
+    mov edx, 16         ; size
+    syscall
+
+    ; write(1, buf, rax)
+    mov edx, eax        ; number of bytes read
+    mov eax, 1          ; syscall: write
+    mov edi, 1          ; stdout
+    syscall             ; rsi still points at buf
+
+    ; exit(0)
+    mov eax, 60
+    xor edi, edi
+    syscall
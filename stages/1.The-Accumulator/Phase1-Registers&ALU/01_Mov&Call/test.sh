@@ -14,3 +14,7 @@ cargo run --quiet --manifest-path "$ROOT_DIR/src-tauri/Cargo.toml" --bin stage_r
   --syntax Att \
   --asm "$STAGE_DIR/collect_Att.asm"
 
+cargo run --quiet --manifest-path "$ROOT_DIR/src-tauri/Cargo.toml" --bin stage_runner -- \
+  --level-id "01_Mov&Call" \
+  --syntax Intel \
+  --asm "$STAGE_DIR/collect_alt.asm"
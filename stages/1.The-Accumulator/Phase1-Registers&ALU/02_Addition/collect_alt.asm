@@ -0,0 +1,38 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Addition
+    ; read from stdin, add 1 to each byte, write to stdout
+    ; Alternative: walk a pointer over the buffer and add in memory.
+
+    ; read(0, buf, 16)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 16         ; size
+    syscall
+
+    mov rdx, rax        ; bytes read, also the write length
+    lea r8, [buf + rax] ; one past the last byte
+.loop:
+    cmp rsi, r8
+    jae .write
+    add byte [rsi], 1
+    inc rsi
+    jmp .loop
+
+.write:
+    ; write(1, buf, rdx)
+    mov rax, 1          ; syscall: write
+    mov rdi, 1          ; stdout
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,38 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Subtraction
+    ; read from stdin, subtract 1 from each byte, write to stdout
+    ; Alternative: count down from the last byte and subtract in memory.
+
+    ; read(0, buf, 16)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 16         ; size
+    syscall
+
+    mov rdx, rax        ; bytes read, also the write length
+    mov rcx, rax        ; bytes left, last one first
+.loop:
+    test rcx, rcx
+    jz .write
+    sub byte [buf + rcx - 1], 1
+    dec rcx
+    jmp .loop
+
+.write:
+    ; write(1, buf, rdx)
+    mov rax, 1          ; syscall: write
+    mov rdi, 1          ; stdout
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,33 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: The XOR Trick
+    ; read from stdin, XOR each byte with 0x20, write to stdout
+    ; Alternative: no loop; flip eight bytes at a time. Bytes past the input are never written.
+
+    ; read(0, buf, 16)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 16         ; size
+    syscall
+
+    mov rdx, rax                ; bytes read, also the write length
+    mov rax, 0x2020202020202020 ; 0x20 in every byte
+    xor qword [buf], rax
+    xor qword [buf + 8], rax
+
+    ; write(1, buf, rdx)
+    mov rax, 1          ; syscall: write
+    mov rdi, 1          ; stdout
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
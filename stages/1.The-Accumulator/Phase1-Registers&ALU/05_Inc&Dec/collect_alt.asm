@@ -0,0 +1,42 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Inc & Dec
+    ; read from stdin, inc even-indexed, dec odd-indexed bytes, write to stdout
+    ; Alternative: handle the bytes in pairs, so no parity test is needed.
+
+    ; read(0, buf, 16)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 16         ; size
+    syscall
+
+    mov rdx, rax        ; bytes read, also the write length
+    xor r8, r8          ; index of the next even byte
+.loop:
+    cmp r8, rdx
+    jge .write
+    inc byte [buf + r8] ; even index
+    inc r8
+    cmp r8, rdx
+    jge .write
+    dec byte [buf + r8] ; odd index
+    inc r8
+    jmp .loop
+
+.write:
+    ; write(1, buf, rdx)
+    mov rax, 1          ; syscall: write
+    mov rdi, 1          ; stdout
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,32 @@
+section .bss
+    buf resb 1
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Unconditional
+    ; read from stdin, write to stdout using jmp
+    ; Alternative: copy one byte at a time and jmp back until input runs out.
+
+.copy:
+    ; read(0, buf, 1)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 1          ; size
+    syscall
+    cmp rax, 0          ; nothing left?
+    je .exit
+
+    ; write(1, buf, 1)
+    mov rax, 1          ; syscall: write
+    mov rdi, 1          ; stdout
+    syscall             ; rsi and rdx are unchanged
+    jmp .copy
+
+.exit:
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
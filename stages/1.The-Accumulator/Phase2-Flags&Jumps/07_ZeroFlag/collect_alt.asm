@@ -0,0 +1,42 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Zero Flag
+    ; read from stdin, replace null bytes with spaces, write to stdout
+    ; Alternative: no branch per byte; cmovz picks the space when ZF is set.
+
+    ; read(0, buf, 16)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 16         ; size
+    syscall
+
+    mov rdx, rax        ; bytes read, also the write length
+    lea r8, [buf + rax] ; one past the last byte
+    mov ebx, 0x20       ; space
+.loop:
+    cmp rsi, r8
+    jae .write
+    movzx eax, byte [rsi]
+    test eax, eax       ; sets ZF if the byte is 0
+    cmovz eax, ebx
+    mov byte [rsi], al
+    inc rsi
+    jmp .loop
+
+.write:
+    ; write(1, buf, rdx)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,34 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Sign Flag
+    ; read from stdin, check if first byte is negative, write result
+    ; Alternative: no jump; '+' is 0x2b and '-' is 0x2d, so add twice the sign bit.
+
+    ; read(0, buf, 16)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 16         ; size
+    syscall
+
+    movzx eax, byte [buf]
+    shr eax, 7              ; sign bit: 1 if negative
+    lea eax, [rax + rax + 0x2b]
+    mov byte [buf], al
+
+    ; write(1, buf, 1)
+    mov rdx, 1
+    mov rax, 1          ; syscall: write
+    mov rdi, 1          ; stdout
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,46 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Comparison
+    ; read from stdin, compare consecutive bytes, output +/=/- markers
+    ; Alternative: write each marker over the first byte of its pair, so no second buffer.
+
+    ; read(0, buf, 16)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 16         ; size
+    syscall
+
+    mov rdx, rax        ; bytes read
+    dec rdx             ; one marker per neighbouring pair
+    jle .exit           ; fewer than two bytes: nothing to print
+    lea r8, [buf + rdx] ; last byte
+.loop:
+    mov al, byte [rsi]  ; prev
+    mov bl, '='
+    cmp byte [rsi + 1], al
+    je .mark
+    mov bl, '+'
+    jg .mark
+    mov bl, '-'
+.mark:
+    mov byte [rsi], bl
+    inc rsi
+    cmp rsi, r8
+    jb .loop
+
+    ; write(1, buf, rdx)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+
+.exit:
+    mov rax, 60
+    xor rdi, rdi
+    syscall
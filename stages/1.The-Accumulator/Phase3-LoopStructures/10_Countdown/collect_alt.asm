@@ -0,0 +1,38 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Countdown
+    ; read a digit, count down from it to '0'
+    ; Alternative: count down the ASCII digit itself instead of a number.
+
+    ; read(0, buf, 1)
+    mov rax, 0          ; syscall: read
+    mov rdi, 0          ; stdin
+    mov rsi, buf        ; buffer
+    mov rdx, 1          ; size
+    syscall
+
+    mov al, byte [buf]  ; e.g. '3'
+.loop:
+    mov byte [rsi], al
+    inc rsi
+    dec al
+    cmp al, '0'
+    jge .loop
+
+    ; write(1, buf, rsi - buf)
+    mov rdx, rsi
+    sub rdx, buf
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,36 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; MISSION: Accumulate 3
+    ; read input, sum the first 3 bytes (u8), write 1 byte result
+    ; Alternative: a loop over the three bytes, last one first.
+
+    ; read(0, buf, 16)
+    mov rax, 0
+    mov rdi, 0
+    mov rsi, buf
+    mov rdx, 16
+    syscall
+
+    xor eax, eax        ; sum
+    mov ecx, 3          ; bytes left
+.loop:
+    add al, byte [buf + rcx - 1]
+    loop .loop
+    mov byte [buf], al
+
+    ; write(1, buf, 1)
+    mov rdx, 1
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,57 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: keep the top of the stack in RBX, so each operator pops only once.
+    xor rbx, rbx         ; top
+.loop:
+    in rax
+    cmp rax, 0
+    jz .done
+    js .op
+    ; positive -> push
+    push rbx
+    mov rbx, rax
+    jmp .loop
+
+.op:
+    cmp rax, -1
+    jz .do_add
+    cmp rax, -2
+    jz .do_sub
+    cmp rax, -3
+    jz .do_xor
+    jmp .loop            ; unknown op -> ignore
+
+.do_add:
+    pop rcx              ; a
+    add rbx, rcx         ; a+b
+    jmp .loop
+
+.do_sub:
+    pop rcx              ; a
+    sub rcx, rbx         ; a-b
+    mov rbx, rcx
+    jmp .loop
+
+.do_xor:
+    pop rcx              ; a
+    xor rbx, rcx         ; a^b
+    jmp .loop
+
+.done:
+    mov byte [buf], bl
+    ; write(1, buf, 1)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 1
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,26 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: keep A on the stack and let write(2) print it from there.
+    ; read A
+    in rax
+    push rax
+
+    ; write(1, rsp, 1): the low byte of A is at the top of the stack
+    mov rsi, rsp
+    mov rax, 1
+    mov rdi, 1
+    mov rdx, 1
+    syscall
+
+    ; drop A
+    pop rax
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
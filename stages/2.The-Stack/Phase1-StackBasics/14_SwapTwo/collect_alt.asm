@@ -0,0 +1,27 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: two registers are enough; no stack needed.
+    ; read A, then B
+    in rbx
+    in rax
+
+    ; output B then A
+    mov byte [buf], al
+    mov byte [buf + 1], bl
+
+    ; write(1, buf, 2)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 2
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,23 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: copy the byte into AH and store both with one word write.
+    in rax
+    mov ah, al
+    mov word [buf], ax
+
+    ; write(1, buf, 2)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 2
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
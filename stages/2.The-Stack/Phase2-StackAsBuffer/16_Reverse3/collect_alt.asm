@@ -0,0 +1,26 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: store each value straight into its reversed slot.
+    in rax
+    mov byte [buf + 2], al
+    in rax
+    mov byte [buf + 1], al
+    in rax
+    mov byte [buf], al
+
+    ; write(1, buf, 3)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 3
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
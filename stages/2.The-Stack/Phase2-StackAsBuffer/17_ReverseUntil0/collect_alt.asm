@@ -0,0 +1,44 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: store the values in order, then reverse the buffer in place with two pointers.
+    mov rsi, buf          ; next free byte
+.read_loop:
+    in rax
+    cmp rax, 0
+    jz .read_done
+    mov byte [rsi], al
+    inc rsi
+    jmp .read_loop
+
+.read_done:
+    mov rdx, rsi
+    sub rdx, buf          ; count, also the write length
+    mov rdi, buf          ; left end
+    dec rsi               ; right end
+.swap_loop:
+    cmp rdi, rsi
+    jae .do_write
+    mov al, byte [rdi]
+    mov bl, byte [rsi]
+    mov byte [rdi], bl
+    mov byte [rsi], al
+    inc rdi
+    dec rsi
+    jmp .swap_loop
+
+.do_write:
+    ; write(1, buf, count)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,40 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: only the depth is printed, so count it and never touch the stack.
+    xor rbx, rbx          ; depth = 0
+.loop:
+    in rax
+    cmp rax, 0
+    jz .done
+    cmp rax, 1
+    jz .do_push
+    cmp rax, -1
+    jnz .loop             ; unknown token -> ignore
+    test rbx, rbx
+    jz .loop              ; nothing to pop
+    dec rbx
+    jmp .loop
+
+.do_push:
+    in rax                ; consume the value
+    inc rbx
+    jmp .loop
+
+.done:
+    mov byte [buf], bl
+    ; write(1, buf, 1)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 1
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,38 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: keep the top of the stack in RBX; only the values below it are pushed.
+    xor rbx, rbx          ; top
+.loop:
+    in rax
+    cmp rax, 0
+    jz .done
+    cmp rax, -1
+    jz .add
+    ; push number
+    push rbx
+    mov rbx, rax
+    jmp .loop
+
+.add:
+    pop rcx               ; second from the top
+    add rbx, rcx
+    jmp .loop
+
+.done:
+    mov byte [buf], bl
+    ; write(1, buf, 1)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 1
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
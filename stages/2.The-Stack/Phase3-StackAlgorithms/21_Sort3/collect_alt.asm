@@ -0,0 +1,52 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: sort B,C first, then A,B, then B,C again; each swap goes through the stack.
+    ; read A,B,C
+    in rax
+    in rbx
+    in rcx
+
+    ; if B > C then swap(B,C)
+    cmp rbx, rcx
+    jle .bc_ok
+    push rbx
+    push rcx
+    pop rbx
+    pop rcx
+.bc_ok:
+    ; if A > B then swap(A,B)
+    cmp rax, rbx
+    jle .ab_ok
+    push rax
+    push rbx
+    pop rax
+    pop rbx
+.ab_ok:
+    ; again: if B > C then swap(B,C)
+    cmp rbx, rcx
+    jle .bc2_ok
+    push rbx
+    push rcx
+    pop rbx
+    pop rcx
+.bc2_ok:
+    mov byte [buf], al
+    mov byte [buf + 1], bl
+    mov byte [buf + 2], cl
+
+    ; write(1, buf, 3)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 3
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall
@@ -0,0 +1,26 @@
+section .bss
+    buf resb 16
+
+section .text
+    global _start
+
+_start:
+    ; Alternative: store each value straight into its rotated slot.
+    in rax
+    mov byte [buf + 2], al  ; A goes last
+    in rax
+    mov byte [buf], al      ; B first
+    in rax
+    mov byte [buf + 1], al  ; C in the middle
+
+    ; write(1, buf, 3)
+    mov rax, 1
+    mov rdi, 1
+    mov rsi, buf
+    mov rdx, 3
+    syscall
+
+    ; exit(0)
+    mov rax, 60
+    xor rdi, rdi
+    syscall